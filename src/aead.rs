@@ -1,14 +1,60 @@
-//! Authenticated Encryption with Associated Data Algorithms: AES-GCM, ChaCha20Poly1305
+//! Authenticated Encryption with Associated Data Algorithms: AES-GCM, ChaCha20Poly1305,
+//! XChaCha20Poly1305
+
+pub mod chacha20poly1305_openssh;
+pub mod committing;
+pub mod datagram;
+pub mod derived_key;
+pub mod limits;
+pub mod nonce_sequence;
+pub mod quic;
+pub mod tls13;
 
+#[cfg(feature = "alloc")]
+pub mod age;
+#[cfg(feature = "alloc")]
+pub mod cose;
+#[cfg(feature = "alloc")]
+pub mod envelope;
+#[cfg(feature = "alloc")]
+pub mod hpke;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(all(feature = "alloc", feature = "digest"))]
+pub mod jwe;
+#[cfg(feature = "alloc")]
+pub mod keyset;
+#[cfg(all(feature = "alloc", feature = "digest"))]
+pub mod noise;
+#[cfg(feature = "alloc")]
+pub mod prefixed;
+#[cfg(feature = "alloc")]
+pub mod vectored;
+
+mod any;
+#[cfg(feature = "alloc")]
+mod base64;
+mod replay_window;
+mod xchacha20poly1305;
+
+pub use self::{
+    any::{AeadAlgorithm, AnyAead},
+    chacha20poly1305_openssh::ChaCha20Poly1305OpenSsh,
+    xchacha20poly1305::XChaCha20Poly1305,
+};
 pub use aead::{AeadCore, AeadInPlace, Buffer, Error, KeyInit, KeySizeUser};
 
 #[cfg(feature = "alloc")]
 pub use aead::{Aead, Payload};
 
+#[cfg(feature = "stream")]
+pub use aead::stream;
+
 use aead::{
     consts::{U0, U12, U16, U32},
     generic_array::GenericArray,
 };
+use core::fmt;
 use ring::aead::{
     Aad, LessSafeKey as Key, Nonce, UnboundKey, AES_128_GCM, AES_256_GCM, CHACHA20_POLY1305,
 };
@@ -17,12 +63,15 @@ use ring::aead::{
 pub type Tag = GenericArray<u8, U16>;
 
 /// AES-GCM with a 128-bit key
+#[derive(Clone)]
 pub struct Aes128Gcm(Cipher);
 
 /// AES-GCM with a 256-bit key
+#[derive(Clone)]
 pub struct Aes256Gcm(Cipher);
 
 /// ChaCha20Poly1305
+#[derive(Clone)]
 pub struct ChaCha20Poly1305(Cipher);
 
 macro_rules! impl_aead {
@@ -67,12 +116,19 @@ macro_rules! impl_aead {
 
             fn decrypt_in_place_detached(
                 &self,
-                _nonce: &GenericArray<u8, Self::NonceSize>,
-                _associated_data: &[u8],
-                _buffer: &mut [u8],
-                _tag: &Tag,
+                nonce: &GenericArray<u8, Self::NonceSize>,
+                associated_data: &[u8],
+                buffer: &mut [u8],
+                tag: &Tag,
             ) -> Result<(), Error> {
-                unimplemented!(); // ring does not allow us to implement this API
+                self.0
+                    .decrypt_in_place_detached(nonce.as_slice(), associated_data, buffer, tag)
+            }
+        }
+
+        impl fmt::Debug for $cipher {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($cipher)).finish_non_exhaustive()
             }
         }
     };
@@ -83,6 +139,7 @@ impl_aead!(Aes256Gcm, AES_256_GCM, U32);
 impl_aead!(ChaCha20Poly1305, CHACHA20_POLY1305, U32);
 
 /// Generic AEAD cipher support
+#[derive(Clone)]
 pub(crate) struct Cipher(Key);
 
 impl Cipher {
@@ -127,4 +184,24 @@ impl Cipher {
         buffer.truncate(pt_len);
         Ok(())
     }
+
+    /// Decrypt the ciphertext in place, checking it against a detached tag
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        self.0
+            .open_in_place_separate_tag(
+                Nonce::try_assume_unique_for_key(nonce).map_err(|_| Error)?,
+                Aad::from(associated_data),
+                ring::aead::Tag::try_from(tag.as_slice()).map_err(|_| Error)?,
+                buffer,
+                0..,
+            )
+            .map(|_| ())
+            .map_err(|_| Error)
+    }
 }
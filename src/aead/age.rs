@@ -0,0 +1,711 @@
+//! [age] v1 file encryption with X25519 recipients
+//!
+//! Files start with a textual header containing one `X25519` stanza per
+//! recipient, each wrapping a random 128-bit file key, and a MAC over the
+//! header. The payload which follows is encrypted with [`ChaCha20Poly1305`]
+//! in 64 KiB STREAM chunks under a key derived from the file key.
+//!
+//! The sender's ephemeral key agreement is performed with *ring*'s
+//! `agreement` module, and decryption with static [`X25519`] identities
+//! provided as a [`PrivateKey`].
+//!
+//! Recipients and identities use age's Bech32 encodings, i.e. `age1...` and
+//! `AGE-SECRET-KEY-1...` (see [`decode_identity`]). Stanzas of other types
+//! (e.g. `scrypt` or plugin stanzas) are ignored when decrypting.
+//!
+//! [age]: https://age-encryption.org/v1
+
+use super::{base64, AeadInPlace, ChaCha20Poly1305, KeyInit};
+use crate::agreement::{PrivateKey, X25519};
+use aead::{consts::U12, generic_array::GenericArray};
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+use ring::{
+    agreement, hkdf, hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
+
+/// Size of a plaintext payload chunk: 64 KiB
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Size of X25519 keys
+pub const KEY_LEN: usize = 32;
+
+/// First line of the header
+const VERSION_LINE: &str = "age-encryption.org/v1";
+
+/// Type of X25519 recipient stanzas
+const X25519_TAG: &str = "X25519";
+
+/// HKDF info used to derive X25519 stanza wrapping keys
+const X25519_LABEL: &[u8] = b"age-encryption.org/v1/X25519";
+
+/// Bech32 human-readable part of recipients
+const RECIPIENT_HRP: &str = "age";
+
+/// Bech32 human-readable part of identities (encoded in upper case)
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+/// Size of the file key
+const FILE_KEY_LEN: usize = 16;
+
+/// Size of the nonce which precedes the payload
+const PAYLOAD_NONCE_LEN: usize = 16;
+
+/// Size of ChaCha20Poly1305 tags
+const TAG_LEN: usize = 16;
+
+/// Size of the header MAC
+const MAC_LEN: usize = 32;
+
+/// Number of base64 characters in a full stanza body line
+const COLUMNS: usize = 64;
+
+/// Errors returned when encrypting or decrypting age files.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The header is malformed, isn't an age v1 header, or isn't followed by
+    /// a payload nonce
+    Header,
+
+    /// The header MAC didn't verify
+    HeaderMac,
+
+    /// None of the identities could unwrap the file key
+    NoMatchingKeys,
+
+    /// No recipients were given
+    NoRecipients,
+
+    /// The payload failed to decrypt or was truncated
+    Payload,
+
+    /// A recipient or identity is invalid
+    InvalidKey,
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header => f.write_str("malformed age header"),
+            Self::HeaderMac => f.write_str("age header MAC mismatch"),
+            Self::NoMatchingKeys => f.write_str("no identity matched any of the recipients"),
+            Self::NoRecipients => f.write_str("no recipients"),
+            Self::Payload => f.write_str("age payload decryption failed"),
+            Self::InvalidKey => f.write_str("invalid age recipient or identity"),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// X25519 recipient: a public key, encoded as `age1...`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Recipient([u8; KEY_LEN]);
+
+impl Recipient {
+    /// Create a recipient from a raw X25519 public key.
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Borrow the raw X25519 public key.
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+
+    /// Wrap a file key to this recipient, returning the ephemeral share and
+    /// stanza body.
+    fn wrap(
+        &self,
+        rng: &dyn SecureRandom,
+        file_key: &[u8; FILE_KEY_LEN],
+    ) -> Result<([u8; KEY_LEN], Vec<u8>), Error> {
+        let private_key = agreement::EphemeralPrivateKey::generate(&agreement::X25519, rng)
+            .map_err(|_| Error::Rng)?;
+
+        let mut share = [0u8; KEY_LEN];
+        share.copy_from_slice(
+            private_key
+                .compute_public_key()
+                .map_err(|_| Error::Rng)?
+                .as_ref(),
+        );
+
+        // *ring* rejects recipients for which the shared secret is zero
+        let public_key = agreement::UnparsedPublicKey::new(&agreement::X25519, &self.0);
+        let wrap_key = agreement::agree_ephemeral(private_key, &public_key, |shared| {
+            stanza_key(shared, &share, &self.0)
+        })
+        .map_err(|_| Error::InvalidKey)?;
+
+        #[cfg(feature = "zeroize")]
+        let wrap_key = Zeroizing::new(wrap_key);
+
+        let mut body = file_key.to_vec();
+        let tag = ChaCha20Poly1305::new(GenericArray::from_slice(&wrap_key[..]))
+            .encrypt_in_place_detached(&Default::default(), b"", &mut body)
+            .map_err(|_| Error::InvalidKey)?;
+
+        body.extend_from_slice(&tag);
+        Ok((share, body))
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (hrp, data) = bech32_decode(s)?;
+
+        if hrp != RECIPIENT_HRP || s.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(Error::InvalidKey);
+        }
+
+        data.try_into().map(Self).map_err(|_| Error::InvalidKey)
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&bech32_encode(RECIPIENT_HRP, &self.0))
+    }
+}
+
+/// Decode a raw X25519 private key from `AGE-SECRET-KEY-1...`.
+pub fn decode_identity(s: &str) -> Result<[u8; KEY_LEN], Error> {
+    let (hrp, data) = bech32_decode(s)?;
+
+    if hrp != IDENTITY_HRP || s.bytes().any(|b| b.is_ascii_lowercase()) {
+        return Err(Error::InvalidKey);
+    }
+
+    data.try_into().map_err(|_| Error::InvalidKey)
+}
+
+/// Encode a raw X25519 private key as `AGE-SECRET-KEY-1...`.
+pub fn encode_identity(secret: &[u8; KEY_LEN]) -> String {
+    bech32_encode(IDENTITY_HRP, secret).to_ascii_uppercase()
+}
+
+/// Encrypt `plaintext` to the given recipients.
+pub fn encrypt(recipients: &[Recipient], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    if recipients.is_empty() {
+        return Err(Error::NoRecipients);
+    }
+
+    let rng = SystemRandom::new();
+    let mut file_key = [0u8; FILE_KEY_LEN];
+    rng.fill(&mut file_key).map_err(|_| Error::Rng)?;
+
+    #[cfg(feature = "zeroize")]
+    let file_key = Zeroizing::new(file_key);
+
+    let mut header = String::from(VERSION_LINE);
+    header.push('\n');
+
+    for recipient in recipients {
+        let (share, body) = recipient.wrap(&rng, &file_key)?;
+        write_stanza(&mut header, &[X25519_TAG, &encode_base64(&share)], &body);
+    }
+
+    header.push_str("---");
+    let mac = hmac::sign(&header_key(&file_key), header.as_bytes());
+    header.push(' ');
+    header.push_str(&encode_base64(mac.as_ref()));
+    header.push('\n');
+
+    let mut nonce = [0u8; PAYLOAD_NONCE_LEN];
+    rng.fill(&mut nonce).map_err(|_| Error::Rng)?;
+
+    let mut out = header.into_bytes();
+    out.extend_from_slice(&nonce);
+
+    let key = payload_key(&file_key, &nonce);
+
+    #[cfg(feature = "zeroize")]
+    let key = Zeroizing::new(key);
+
+    encrypt_payload(&key, plaintext, &mut out)?;
+    Ok(out)
+}
+
+/// Decrypt an age file with any of the given identities.
+///
+/// Fails with [`Error::InvalidKey`] if an identity isn't an [`X25519`] key.
+pub fn decrypt(identities: &[&dyn PrivateKey], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    let (header, payload) = Header::parse(ciphertext)?;
+    let file_key = header.unwrap_file_key(identities)?;
+
+    #[cfg(feature = "zeroize")]
+    let file_key = Zeroizing::new(file_key);
+
+    hmac::verify(&header_key(&file_key), header.mac_input, &header.mac)
+        .map_err(|_| Error::HeaderMac)?;
+
+    if payload.len() < PAYLOAD_NONCE_LEN {
+        return Err(Error::Header);
+    }
+
+    let (nonce, chunks) = payload.split_at(PAYLOAD_NONCE_LEN);
+    let key = payload_key(&file_key, nonce);
+
+    #[cfg(feature = "zeroize")]
+    let key = Zeroizing::new(key);
+
+    decrypt_payload(&key, chunks)
+}
+
+/// Parsed age header
+struct Header<'a> {
+    /// Recipient stanzas
+    stanzas: Vec<Stanza<'a>>,
+
+    /// Header bytes covered by the MAC, up to and including `---`
+    mac_input: &'a [u8],
+
+    /// Header MAC
+    mac: [u8; MAC_LEN],
+}
+
+/// Recipient stanza
+struct Stanza<'a> {
+    /// Stanza type, e.g. `X25519`
+    tag: &'a str,
+
+    /// Stanza arguments which follow the type
+    args: Vec<&'a str>,
+
+    /// Decoded stanza body
+    body: Vec<u8>,
+}
+
+impl<'a> Header<'a> {
+    /// Parse a header, returning it along with the payload which follows.
+    fn parse(input: &'a [u8]) -> Result<(Self, &'a [u8]), Error> {
+        let mut pos = 0;
+
+        if next_line(input, &mut pos)? != VERSION_LINE.as_bytes() {
+            return Err(Error::Header);
+        }
+
+        let mut stanzas = Vec::new();
+
+        loop {
+            let line_start = pos;
+            let line = next_line(input, &mut pos)?;
+
+            if let Some(encoded_mac) = line.strip_prefix(b"--- ") {
+                let mac = decode_base64(encoded_mac)?
+                    .try_into()
+                    .map_err(|_| Error::Header)?;
+
+                let header = Self {
+                    stanzas,
+                    mac_input: &input[..line_start + 3],
+                    mac,
+                };
+
+                return Ok((header, &input[pos..]));
+            }
+
+            let args = line.strip_prefix(b"-> ").ok_or(Error::Header)?;
+            let args = core::str::from_utf8(args).map_err(|_| Error::Header)?;
+            let mut args = args.split(' ');
+
+            if !args
+                .clone()
+                .all(|arg| !arg.is_empty() && arg.bytes().all(|b| b.is_ascii_graphic()))
+            {
+                return Err(Error::Header);
+            }
+
+            let tag = args.next().ok_or(Error::Header)?;
+            let args = args.collect();
+            let mut body = Vec::new();
+
+            loop {
+                let line = next_line(input, &mut pos)?;
+
+                if line.len() > COLUMNS {
+                    return Err(Error::Header);
+                }
+
+                body.extend_from_slice(line);
+
+                if line.len() < COLUMNS {
+                    break;
+                }
+            }
+
+            let body = decode_base64(&body)?;
+            stanzas.push(Stanza { tag, args, body });
+        }
+    }
+
+    /// Unwrap the file key from the first X25519 stanza which matches one of
+    /// the identities.
+    fn unwrap_file_key(&self, identities: &[&dyn PrivateKey]) -> Result<[u8; FILE_KEY_LEN], Error> {
+        for stanza in self
+            .stanzas
+            .iter()
+            .filter(|stanza| stanza.tag == X25519_TAG)
+        {
+            let (share, body) = match (stanza.args.as_slice(), stanza.body.as_slice()) {
+                ([share], body) => (
+                    decode_base64(share.as_bytes())?
+                        .try_into()
+                        .map_err(|_| Error::Header)?,
+                    body.try_into().map_err(|_| Error::Header)?,
+                ),
+                _ => return Err(Error::Header),
+            };
+
+            for &identity in identities {
+                if let Some(file_key) = unwrap_stanza(identity, &share, body)? {
+                    return Ok(file_key);
+                }
+            }
+        }
+
+        Err(Error::NoMatchingKeys)
+    }
+}
+
+/// Try to unwrap the file key from an X25519 stanza with an identity.
+fn unwrap_stanza(
+    identity: &dyn PrivateKey,
+    share: &[u8; KEY_LEN],
+    body: &[u8; FILE_KEY_LEN + TAG_LEN],
+) -> Result<Option<[u8; FILE_KEY_LEN]>, Error> {
+    if identity.algorithm() != &X25519 {
+        return Err(Error::InvalidKey);
+    }
+
+    let recipient: &[u8; KEY_LEN] = identity
+        .public_key()
+        .try_into()
+        .map_err(|_| Error::InvalidKey)?;
+
+    // Low-order shares are rejected by the identity or by the check below
+    let mut shared = [0u8; KEY_LEN];
+    let result = identity.diffie_hellman(share, &mut shared);
+
+    #[cfg(feature = "zeroize")]
+    let shared = Zeroizing::new(shared);
+
+    result.map_err(|_| Error::Header)?;
+
+    if shared.iter().fold(0, |acc, b| acc | b) == 0 {
+        return Err(Error::Header);
+    }
+
+    let wrap_key = stanza_key(&shared[..], share, recipient);
+
+    #[cfg(feature = "zeroize")]
+    let wrap_key = Zeroizing::new(wrap_key);
+
+    let (ciphertext, tag) = body.split_at(FILE_KEY_LEN);
+    let mut file_key = [0u8; FILE_KEY_LEN];
+    file_key.copy_from_slice(ciphertext);
+
+    let result = ChaCha20Poly1305::new(GenericArray::from_slice(&wrap_key[..]))
+        .decrypt_in_place_detached(
+            &Default::default(),
+            b"",
+            &mut file_key,
+            GenericArray::from_slice(tag),
+        );
+
+    #[cfg(feature = "zeroize")]
+    if result.is_err() {
+        file_key.zeroize();
+    }
+
+    Ok(result.ok().map(|()| file_key))
+}
+
+/// Read a line terminated by `\n`, advancing `pos` past it.
+fn next_line<'a>(input: &'a [u8], pos: &mut usize) -> Result<&'a [u8], Error> {
+    let rest = &input[*pos..];
+    let len = rest.iter().position(|&b| b == b'\n').ok_or(Error::Header)?;
+    *pos += len + 1;
+    Ok(&rest[..len])
+}
+
+/// Append a stanza to a header, wrapping its body at 64 columns.
+fn write_stanza(header: &mut String, args: &[&str], body: &[u8]) {
+    header.push_str("->");
+
+    for arg in args {
+        header.push(' ');
+        header.push_str(arg);
+    }
+
+    header.push('\n');
+
+    let body = encode_base64(body);
+
+    for line in body.as_bytes().chunks(COLUMNS) {
+        header.push_str(core::str::from_utf8(line).unwrap());
+        header.push('\n');
+    }
+
+    // The body ends with a line shorter than 64 columns, which may be empty
+    if body.len() % COLUMNS == 0 {
+        header.push('\n');
+    }
+}
+
+/// Derive the key which wraps the file key in an X25519 stanza.
+fn stanza_key(shared: &[u8], share: &[u8; KEY_LEN], recipient: &[u8; KEY_LEN]) -> [u8; 32] {
+    let mut salt = [0u8; 2 * KEY_LEN];
+    salt[..KEY_LEN].copy_from_slice(share);
+    salt[KEY_LEN..].copy_from_slice(recipient);
+    hkdf_sha256(shared, &salt, X25519_LABEL)
+}
+
+/// Derive the header MAC key.
+fn header_key(file_key: &[u8; FILE_KEY_LEN]) -> hmac::Key {
+    let key = hkdf_sha256(file_key, b"", b"header");
+
+    #[cfg(feature = "zeroize")]
+    let key = Zeroizing::new(key);
+
+    hmac::Key::new(hmac::HMAC_SHA256, &key[..])
+}
+
+/// Derive the payload key.
+fn payload_key(file_key: &[u8; FILE_KEY_LEN], nonce: &[u8]) -> [u8; 32] {
+    hkdf_sha256(file_key, nonce, b"payload")
+}
+
+/// HKDF-SHA256 with a 32-byte output
+fn hkdf_sha256(ikm: &[u8], salt: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+        .extract(ikm)
+        .expand(&[info], hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut out))
+        .expect("HKDF-SHA256 output should fit");
+    out
+}
+
+/// Nonce for a payload chunk: an 88-bit big endian counter and a last chunk
+/// flag
+fn chunk_nonce(counter: u64, last: bool) -> GenericArray<u8, U12> {
+    let mut nonce = GenericArray::default();
+    nonce[3..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = u8::from(last);
+    nonce
+}
+
+/// Encrypt the payload in 64 KiB chunks, appending it to `out`.
+///
+/// The last chunk may be full, and is only empty if the plaintext is.
+fn encrypt_payload(key: &[u8; 32], plaintext: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let chunks = plaintext.len().saturating_sub(1) / CHUNK_SIZE + 1;
+
+    for counter in 0..chunks {
+        let start = counter * CHUNK_SIZE;
+        let chunk = &plaintext[start..plaintext.len().min(start + CHUNK_SIZE)];
+        let nonce = chunk_nonce(counter as u64, counter + 1 == chunks);
+
+        let chunk_start = out.len();
+        out.extend_from_slice(chunk);
+
+        let tag = cipher
+            .encrypt_in_place_detached(&nonce, b"", &mut out[chunk_start..])
+            .map_err(|_| Error::Payload)?;
+
+        out.extend_from_slice(&tag);
+    }
+
+    Ok(())
+}
+
+/// Decrypt a payload encrypted in 64 KiB chunks.
+fn decrypt_payload(key: &[u8; 32], mut ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    let mut counter = 0u64;
+
+    loop {
+        let last = ciphertext.len() <= CHUNK_SIZE + TAG_LEN;
+        let (chunk, rest) = if last {
+            (ciphertext, &[][..])
+        } else {
+            ciphertext.split_at(CHUNK_SIZE + TAG_LEN)
+        };
+
+        if chunk.len() < TAG_LEN {
+            return Err(Error::Payload);
+        }
+
+        let (chunk, tag) = chunk.split_at(chunk.len() - TAG_LEN);
+
+        // Only the payload of an empty file may end with an empty chunk
+        if chunk.is_empty() && counter != 0 {
+            return Err(Error::Payload);
+        }
+
+        let chunk_start = plaintext.len();
+        plaintext.extend_from_slice(chunk);
+
+        cipher
+            .decrypt_in_place_detached(
+                &chunk_nonce(counter, last),
+                b"",
+                &mut plaintext[chunk_start..],
+                GenericArray::from_slice(tag),
+            )
+            .map_err(|_| Error::Payload)?;
+
+        if last {
+            return Ok(plaintext);
+        }
+
+        ciphertext = rest;
+        counter += 1;
+    }
+}
+
+/// Encode as standard base64 without padding.
+fn encode_base64(data: &[u8]) -> String {
+    base64::encode(base64::STANDARD, data)
+}
+
+/// Decode standard base64 without padding, rejecting non-canonical
+/// encodings.
+fn decode_base64(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+    base64::decode(base64::STANDARD, encoded).ok_or(Error::Header)
+}
+
+/// Bech32 character set
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Encode data as Bech32 (BIP 173) with a lowercase human-readable part.
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    let mut values = convert_bits(data, 8, 5);
+    let checksum = bech32_polymod(hrp, &values, &[0; 6]) ^ 1;
+    values.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8));
+
+    let mut out = String::with_capacity(hrp.len() + 1 + values.len());
+    out.push_str(hrp);
+    out.push('1');
+    out.extend(
+        values
+            .iter()
+            .map(|&v| char::from(BECH32_CHARSET[usize::from(v)])),
+    );
+    out
+}
+
+/// Decode a Bech32 string, returning its lowercase human-readable part and
+/// data.
+///
+/// Unlike BIP 173, strings longer than 90 characters are accepted.
+fn bech32_decode(s: &str) -> Result<(String, Vec<u8>), Error> {
+    if s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(Error::InvalidKey);
+    }
+
+    let s = s.to_ascii_lowercase();
+    let separator = s.rfind('1').ok_or(Error::InvalidKey)?;
+    let (hrp, encoded) = (&s[..separator], &s.as_bytes()[separator + 1..]);
+
+    if hrp.is_empty() || encoded.len() < 6 || !hrp.bytes().all(|b| (0x21..=0x7e).contains(&b)) {
+        return Err(Error::InvalidKey);
+    }
+
+    let values = encoded
+        .iter()
+        .map(|c| BECH32_CHARSET.iter().position(|b| b == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(Error::InvalidKey)?;
+
+    if bech32_polymod(hrp, &values, &[]) != 1 {
+        return Err(Error::InvalidKey);
+    }
+
+    let values = &values[..values.len() - 6];
+    let data = convert_bits(values, 5, 8);
+
+    // Padding must be less than a byte and all zero
+    if values.len() * 5 - data.len() * 8 >= 5
+        || values
+            .last()
+            .map_or(0, |v| v & ((1 << (values.len() * 5 % 8)) - 1))
+            != 0
+    {
+        return Err(Error::InvalidKey);
+    }
+
+    Ok((String::from(hrp), data))
+}
+
+/// Bech32 checksum of a human-readable part followed by data values.
+fn bech32_polymod(hrp: &str, values: &[u8], trailer: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+
+    let expanded_hrp = hrp
+        .bytes()
+        .map(|b| b >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|b| b & 0x1f));
+
+    expanded_hrp
+        .chain(values.iter().copied())
+        .chain(trailer.iter().copied())
+        .fold(1, |chk, value| {
+            let top = chk >> 25;
+            let chk = ((chk & 0x1ff_ffff) << 5) ^ u32::from(value);
+
+            GENERATOR
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (top >> i) & 1 == 1)
+                .fold(chk, |chk, (_, g)| chk ^ g)
+        })
+}
+
+/// Regroup bits from `from`-bit values into `to`-bit values, padding the
+/// last value with zeros when encoding and dropping incomplete values when
+/// decoding.
+fn convert_bits(data: &[u8], from: u32, to: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for &value in data {
+        acc = ((acc << from) | u32::from(value)) & ((1 << (from + to)) - 1);
+        bits += from;
+
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & ((1 << to) - 1)) as u8);
+        }
+    }
+
+    if from == 8 && bits > 0 {
+        out.push(((acc << (to - bits)) & ((1 << to) - 1)) as u8);
+    }
+
+    out
+}
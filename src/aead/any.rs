@@ -0,0 +1,178 @@
+//! Runtime-selectable AEAD algorithms
+//!
+//! [`AnyAead`] lets the algorithm be chosen at runtime, e.g. from a
+//! configuration file or protocol negotiation, instead of statically.
+
+use super::{AeadCore, AeadInPlace, Buffer, Cipher, Error, Tag};
+use aead::{
+    consts::{U0, U12, U16},
+    generic_array::GenericArray,
+};
+use core::{fmt, str::FromStr};
+use ring::aead::{UnboundKey, AES_128_GCM, AES_256_GCM, CHACHA20_POLY1305};
+
+/// AEAD algorithm identifiers.
+///
+/// Numeric identifiers are the ones assigned in the IANA
+/// [AEAD Algorithms registry](https://www.iana.org/assignments/aead-parameters/aead-parameters.xhtml).
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum AeadAlgorithm {
+    /// AES-GCM with a 128-bit key
+    Aes128Gcm,
+
+    /// AES-GCM with a 256-bit key
+    Aes256Gcm,
+
+    /// ChaCha20Poly1305
+    ChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    /// Look up an algorithm by its IANA numeric identifier.
+    pub fn from_id(id: u16) -> Result<Self, Error> {
+        match id {
+            1 => Ok(Self::Aes128Gcm),
+            2 => Ok(Self::Aes256Gcm),
+            29 => Ok(Self::ChaCha20Poly1305),
+            _ => Err(Error),
+        }
+    }
+
+    /// IANA numeric identifier for this algorithm.
+    pub fn id(self) -> u16 {
+        match self {
+            Self::Aes128Gcm => 1,
+            Self::Aes256Gcm => 2,
+            Self::ChaCha20Poly1305 => 29,
+        }
+    }
+
+    /// IANA name for this algorithm, e.g. `AEAD_AES_128_GCM`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Aes128Gcm => "AEAD_AES_128_GCM",
+            Self::Aes256Gcm => "AEAD_AES_256_GCM",
+            Self::ChaCha20Poly1305 => "AEAD_CHACHA20_POLY1305",
+        }
+    }
+
+    /// Size of a key in bytes.
+    pub fn key_size(self) -> usize {
+        self.ring_algorithm().key_len()
+    }
+
+    /// Size of a nonce in bytes.
+    pub fn nonce_size(self) -> usize {
+        self.ring_algorithm().nonce_len()
+    }
+
+    /// Size of an authentication tag in bytes.
+    pub fn tag_size(self) -> usize {
+        self.ring_algorithm().tag_len()
+    }
+
+    /// *ring* algorithm
+    fn ring_algorithm(self) -> &'static ring::aead::Algorithm {
+        match self {
+            Self::Aes128Gcm => &AES_128_GCM,
+            Self::Aes256Gcm => &AES_256_GCM,
+            Self::ChaCha20Poly1305 => &CHACHA20_POLY1305,
+        }
+    }
+}
+
+impl fmt::Display for AeadAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AeadAlgorithm {
+    type Err = Error;
+
+    /// Parse an IANA algorithm name, with or without the `AEAD_` prefix.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let name = s.strip_prefix("AEAD_").unwrap_or(s);
+
+        [Self::Aes128Gcm, Self::Aes256Gcm, Self::ChaCha20Poly1305]
+            .into_iter()
+            .find(|alg| &alg.as_str()["AEAD_".len()..] == name)
+            .ok_or(Error)
+    }
+}
+
+/// AEAD cipher whose algorithm is selected at runtime.
+///
+/// All of the algorithms it supports use 96-bit nonces and 128-bit tags, so
+/// those sizes are static, while the key size depends on the algorithm.
+#[derive(Clone)]
+pub struct AnyAead {
+    algorithm: AeadAlgorithm,
+    pub(super) cipher: Cipher,
+}
+
+impl AnyAead {
+    /// Create a new cipher for the given algorithm and key, which must be
+    /// [`AeadAlgorithm::key_size`] bytes.
+    pub fn new(algorithm: AeadAlgorithm, key: &[u8]) -> Result<Self, Error> {
+        let key = UnboundKey::new(algorithm.ring_algorithm(), key).map_err(|_| Error)?;
+
+        Ok(Self {
+            algorithm,
+            cipher: Cipher::new(key),
+        })
+    }
+
+    /// Algorithm of this cipher.
+    pub fn algorithm(&self) -> AeadAlgorithm {
+        self.algorithm
+    }
+}
+
+impl fmt::Debug for AnyAead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyAead")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AeadCore for AnyAead {
+    type NonceSize = U12;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+impl AeadInPlace for AnyAead {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, Error> {
+        self.cipher
+            .encrypt_in_place_detached(nonce.as_slice(), associated_data, buffer)
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        self.cipher
+            .decrypt_in_place(nonce.as_slice(), associated_data, buffer)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        self.cipher
+            .decrypt_in_place_detached(nonce.as_slice(), associated_data, buffer, tag)
+    }
+}
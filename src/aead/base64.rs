@@ -0,0 +1,62 @@
+//! Base64 without padding, shared by the age and JWE formats
+
+use alloc::{string::String, vec::Vec};
+
+/// Standard base64 alphabet
+pub(crate) const STANDARD: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// URL-safe base64 alphabet
+#[cfg(feature = "digest")]
+pub(crate) const URL_SAFE: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode with the given alphabet, without padding.
+pub(crate) fn encode(alphabet: &[u8; 64], data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 4 + 2) / 3);
+
+    for chunk in data.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+
+        for i in 0..=chunk.len() {
+            let index = (bits >> (18 - 6 * i)) & 0x3f;
+            out.push(char::from(alphabet[index as usize]));
+        }
+    }
+
+    out
+}
+
+/// Decode with the given alphabet, without padding.
+///
+/// Returns `None` for invalid characters or lengths and for non-canonical
+/// encodings.
+pub(crate) fn decode(alphabet: &[u8; 64], encoded: &[u8]) -> Option<Vec<u8>> {
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+
+    for chunk in encoded.chunks(4) {
+        let mut bits = 0u32;
+
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = alphabet.iter().position(|&b| b == c)?;
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+
+        let bytes = &bits.to_be_bytes()[1..chunk.len()];
+
+        // Unused trailing bits must be zero
+        if bits & (0xff_ffff >> (8 * bytes.len())) != 0 {
+            return None;
+        }
+
+        out.extend_from_slice(bytes);
+    }
+
+    Some(out)
+}
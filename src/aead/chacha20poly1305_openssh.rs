@@ -0,0 +1,109 @@
+//! The `chacha20-poly1305@openssh.com` SSH packet cipher
+//!
+//! This construction is only useful to SSH implementations. It differs from
+//! [`ChaCha20Poly1305`][`super::ChaCha20Poly1305`] in that the packet length
+//! is encrypted separately from the rest of the packet, and the nonce is
+//! derived from the SSH packet sequence number, so it can't be used through
+//! the [`AeadInPlace`][`super::AeadInPlace`] trait.
+//!
+//! Packets are expected in the [RFC 4253] binary packet format, i.e.
+//! `packet_length || padding_length || payload || random padding`.
+//!
+//! <https://cvsweb.openbsd.org/src/usr.bin/ssh/PROTOCOL.chacha20poly1305?annotate=HEAD>
+//!
+//! [RFC 4253]: https://tools.ietf.org/html/rfc4253#section-6
+
+use super::{Error, KeyInit, KeySizeUser, Tag};
+use aead::{consts::U64, generic_array::GenericArray};
+use core::fmt;
+use ring::aead::chacha20_poly1305_openssh::{OpeningKey, SealingKey, KEY_LEN};
+
+/// Size of the encrypted `packet_length` field in bytes
+pub const PACKET_LENGTH_LEN: usize = ring::aead::chacha20_poly1305_openssh::PACKET_LENGTH_LEN;
+
+/// `chacha20-poly1305@openssh.com` key: `K_2 || K_1` as derived by the SSH
+/// key exchange
+pub type Key = GenericArray<u8, U64>;
+
+/// `chacha20-poly1305@openssh.com` packet cipher
+pub struct ChaCha20Poly1305OpenSsh {
+    sealing_key: SealingKey,
+    opening_key: OpeningKey,
+}
+
+impl fmt::Debug for ChaCha20Poly1305OpenSsh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChaCha20Poly1305OpenSsh")
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeySizeUser for ChaCha20Poly1305OpenSsh {
+    type KeySize = U64;
+}
+
+impl KeyInit for ChaCha20Poly1305OpenSsh {
+    fn new(key: &Key) -> Self {
+        let key_material: &[u8; KEY_LEN] = key.as_ref();
+
+        Self {
+            sealing_key: SealingKey::new(key_material),
+            opening_key: OpeningKey::new(key_material),
+        }
+    }
+}
+
+impl ChaCha20Poly1305OpenSsh {
+    /// Encrypt a packet in place, returning the tag
+    ///
+    /// On input, `packet` must contain `packet_length || plaintext` where
+    /// `plaintext` is `padding_length || payload || random padding`. On
+    /// output, it contains `encrypted_packet_length || ciphertext`.
+    pub fn seal_in_place(&self, sequence_number: u32, packet: &mut [u8]) -> Result<Tag, Error> {
+        if packet.len() < PACKET_LENGTH_LEN {
+            return Err(Error);
+        }
+
+        let mut tag = Tag::default();
+        self.sealing_key
+            .seal_in_place(sequence_number, packet, tag.as_mut());
+        Ok(tag)
+    }
+
+    /// Decrypt the `packet_length` field of a packet
+    ///
+    /// This allows a receiver to learn how many more bytes to read before the
+    /// rest of the packet has arrived. The result is *not* authenticated until
+    /// the whole packet has been passed to [`Self::open_in_place`].
+    pub fn decrypt_packet_length(
+        &self,
+        sequence_number: u32,
+        encrypted_packet_length: [u8; PACKET_LENGTH_LEN],
+    ) -> u32 {
+        u32::from_be_bytes(
+            self.opening_key
+                .decrypt_packet_length(sequence_number, encrypted_packet_length),
+        )
+    }
+
+    /// Authenticate and decrypt a packet in place
+    ///
+    /// `packet` must contain `encrypted_packet_length || ciphertext`. On
+    /// success the ciphertext is replaced by the plaintext, which is returned
+    /// as `&packet[PACKET_LENGTH_LEN..]`. The `packet_length` field is left
+    /// encrypted; use [`Self::decrypt_packet_length`] to decrypt it.
+    pub fn open_in_place<'a>(
+        &self,
+        sequence_number: u32,
+        packet: &'a mut [u8],
+        tag: &Tag,
+    ) -> Result<&'a [u8], Error> {
+        if packet.len() < PACKET_LENGTH_LEN {
+            return Err(Error);
+        }
+
+        self.opening_key
+            .open_in_place(sequence_number, packet, tag.as_ref())
+            .map_err(|_| Error)
+    }
+}
@@ -0,0 +1,206 @@
+//! Key-committing AEAD
+//!
+//! AES-GCM and ChaCha20Poly1305 are not key-committing: it's possible to
+//! construct a ciphertext which decrypts successfully under more than one key,
+//! which enables partitioning oracle attacks against multi-recipient and
+//! password-based protocols.
+//!
+//! [`Committing`] applies the UtC ("unique-to-committing") transform described
+//! in Bellare and Hoang, [Efficient Schemes for Committing Authenticated
+//! Encryption][1], using HMAC-SHA-256 as the committing PRF. For each message
+//! with key `K` and nonce `N` it derives:
+//!
+//! - a commitment `P = HMAC-SHA-256(K, 0x01 || A || N)`
+//! - a message key `L = HMAC-SHA-256(K, 0x02 || A || N)`, truncated to the key
+//!   size of the underlying cipher
+//!
+//! where `A` is the underlying cipher's [`AeadAlgorithm::id`] as a 16-bit big
+//! endian integer, so the same key used with different ciphers yields
+//! unrelated commitments and message keys.
+//!
+//! The message is encrypted with the underlying cipher under `L` and `N`, and
+//! the 32-byte commitment `P` is appended to the tag. Decryption recomputes
+//! the commitment and rejects the ciphertext unless it matches, before
+//! attempting to decrypt.
+//!
+//! [1]: https://eprint.iacr.org/2022/268
+
+use super::{
+    AeadAlgorithm, AeadCore, AeadInPlace, Aes128Gcm, Aes256Gcm, Buffer, ChaCha20Poly1305, Error,
+};
+use aead::{
+    consts::{U0, U16, U48},
+    generic_array::GenericArray,
+    Key, KeyInit, KeySizeUser, Nonce, Tag,
+};
+use core::{fmt, marker::PhantomData};
+use ring::{constant_time, hmac};
+
+/// Size of the commitment in bytes
+const COMMITMENT_SIZE: usize = 32;
+
+/// Size of the underlying cipher's tag in bytes
+const INNER_TAG_SIZE: usize = 16;
+
+/// Domain separator for the commitment
+const COMMITMENT_LABEL: u8 = 0x01;
+
+/// Domain separator for the message key
+const MESSAGE_KEY_LABEL: u8 = 0x02;
+
+/// Key-committing AES-GCM with a 128-bit key
+pub type CommittingAes128Gcm = Committing<Aes128Gcm>;
+
+/// Key-committing AES-GCM with a 256-bit key
+pub type CommittingAes256Gcm = Committing<Aes256Gcm>;
+
+/// Key-committing ChaCha20Poly1305
+pub type CommittingChaCha20Poly1305 = Committing<ChaCha20Poly1305>;
+
+/// Ciphers which can be wrapped by [`Committing`].
+pub trait CommittableAead: AeadInPlace<TagSize = U16> + KeyInit {
+    /// Algorithm bound into the commitment and message key
+    const ALGORITHM: AeadAlgorithm;
+}
+
+impl CommittableAead for Aes128Gcm {
+    const ALGORITHM: AeadAlgorithm = AeadAlgorithm::Aes128Gcm;
+}
+
+impl CommittableAead for Aes256Gcm {
+    const ALGORITHM: AeadAlgorithm = AeadAlgorithm::Aes256Gcm;
+}
+
+impl CommittableAead for ChaCha20Poly1305 {
+    const ALGORITHM: AeadAlgorithm = AeadAlgorithm::ChaCha20Poly1305;
+}
+
+/// Key-committing wrapper around an AEAD cipher.
+///
+/// Tags are 48 bytes: the underlying cipher's 16-byte tag followed by the
+/// 32-byte key commitment.
+#[derive(Clone)]
+pub struct Committing<A: KeySizeUser> {
+    key: hmac::Key,
+    cipher: PhantomData<A>,
+}
+
+impl<A: KeySizeUser> fmt::Debug for Committing<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Committing").finish_non_exhaustive()
+    }
+}
+
+impl<A> KeySizeUser for Committing<A>
+where
+    A: KeySizeUser,
+{
+    type KeySize = A::KeySize;
+}
+
+impl<A> KeyInit for Committing<A>
+where
+    A: KeySizeUser,
+{
+    fn new(key: &Key<Self>) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key),
+            cipher: PhantomData,
+        }
+    }
+}
+
+impl<A> AeadCore for Committing<A>
+where
+    A: AeadCore<TagSize = U16> + KeySizeUser,
+{
+    type NonceSize = A::NonceSize;
+    type TagSize = U48;
+    type CiphertextOverhead = U0;
+}
+
+impl<A> AeadInPlace for Committing<A>
+where
+    A: CommittableAead,
+{
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag<Self>, Error> {
+        let inner_tag = self.message_cipher(nonce)?.encrypt_in_place_detached(
+            nonce,
+            associated_data,
+            buffer,
+        )?;
+
+        let mut tag = Tag::<Self>::default();
+        tag[..INNER_TAG_SIZE].copy_from_slice(&inner_tag);
+        tag[INNER_TAG_SIZE..].copy_from_slice(self.commitment(nonce).as_ref());
+        Ok(tag)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag<Self>,
+    ) -> Result<(), Error> {
+        let (inner_tag, commitment) = tag.split_at(INNER_TAG_SIZE);
+
+        constant_time::verify_slices_are_equal(self.commitment(nonce).as_ref(), commitment)
+            .map_err(|_| Error)?;
+
+        self.message_cipher(nonce)?.decrypt_in_place_detached(
+            nonce,
+            associated_data,
+            buffer,
+            GenericArray::from_slice(inner_tag),
+        )
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        let tag_pos = buffer
+            .len()
+            .checked_sub(INNER_TAG_SIZE + COMMITMENT_SIZE)
+            .ok_or(Error)?;
+
+        let (msg, tag) = buffer.as_mut().split_at_mut(tag_pos);
+        self.decrypt_in_place_detached(nonce, associated_data, msg, GenericArray::from_slice(tag))?;
+        buffer.truncate(tag_pos);
+        Ok(())
+    }
+}
+
+impl<A> Committing<A>
+where
+    A: CommittableAead,
+{
+    /// Compute the commitment to the key for the given nonce
+    fn commitment(&self, nonce: &Nonce<A>) -> hmac::Tag {
+        self.prf(COMMITMENT_LABEL, nonce)
+    }
+
+    /// Instantiate the underlying cipher with the message key for the given
+    /// nonce
+    fn message_cipher(&self, nonce: &Nonce<A>) -> Result<A, Error> {
+        let message_key = self.prf(MESSAGE_KEY_LABEL, nonce);
+        let message_key = message_key.as_ref().get(..A::key_size()).ok_or(Error)?;
+        A::new_from_slice(message_key).map_err(|_| Error)
+    }
+
+    fn prf(&self, label: u8, nonce: &[u8]) -> hmac::Tag {
+        let mut ctx = hmac::Context::with_key(&self.key);
+        ctx.update(&[label]);
+        ctx.update(&A::ALGORITHM.id().to_be_bytes());
+        ctx.update(nonce);
+        ctx.sign()
+    }
+}
@@ -0,0 +1,511 @@
+//! CBOR Object Signing and Encryption (COSE) as described in [RFC 9052]
+//!
+//! Supports single-recipient messages with a shared key:
+//!
+//! - `COSE_Encrypt0` with algorithms 1 (A128GCM), 3 (A256GCM) and
+//!   24 (ChaCha20/Poly1305)
+//! - `COSE_Mac0` with algorithms 5 (HMAC 256/256) and 6 (HMAC 384/384)
+//!
+//! Messages are produced with the algorithm in the protected header and the
+//! IV and key ID in the unprotected header. When processing messages, header
+//! parameters may appear in either bucket but not in both. Detached content,
+//! partial IVs and critical header parameters (`crit`) aren't supported.
+//!
+//! [RFC 9052]: https://www.rfc-editor.org/rfc/rfc9052
+
+mod cbor;
+
+use self::cbor::Decoder;
+use super::{AeadInPlace, Aes128Gcm, Aes256Gcm, ChaCha20Poly1305, KeyInit, Tag};
+use alloc::vec::Vec;
+use core::fmt;
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+
+/// CBOR tag of a `COSE_Encrypt0` message
+pub const ENCRYPT0_TAG: u64 = 16;
+
+/// CBOR tag of a `COSE_Mac0` message
+pub const MAC0_TAG: u64 = 17;
+
+/// Header label: algorithm
+const ALG: i64 = 1;
+
+/// Header label: critical header parameters
+const CRIT: i64 = 2;
+
+/// Header label: key identifier
+const KID: i64 = 4;
+
+/// Header label: full initialization vector
+const IV: i64 = 5;
+
+/// Header label: partial initialization vector
+const PARTIAL_IV: i64 = 6;
+
+/// Size of an IV for all supported algorithms
+const IV_SIZE: usize = 12;
+
+/// Size of an AEAD authentication tag
+const TAG_SIZE: usize = 16;
+
+/// Errors returned by COSE operations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CoseError {
+    /// Message or its headers are malformed
+    Malformed,
+
+    /// An algorithm or header parameter isn't supported
+    Unsupported,
+
+    /// Key size doesn't match the algorithm
+    InvalidKey,
+
+    /// Encryption failed, e.g. because the message is too long
+    Encryption,
+
+    /// Decryption failed: the key is wrong or the message is corrupt
+    Decryption,
+
+    /// MAC verification failed
+    Verification,
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for CoseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed COSE message"),
+            Self::Unsupported => f.write_str("unsupported COSE algorithm or header parameter"),
+            Self::InvalidKey => f.write_str("invalid COSE key"),
+            Self::Encryption => f.write_str("COSE encryption failed"),
+            Self::Decryption => f.write_str("COSE decryption failed"),
+            Self::Verification => f.write_str("COSE MAC verification failed"),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CoseError {}
+
+/// Content encryption algorithms for `COSE_Encrypt0`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EncryptionAlgorithm {
+    /// A128GCM: [`Aes128Gcm`]
+    A128Gcm,
+
+    /// A256GCM: [`Aes256Gcm`]
+    A256Gcm,
+
+    /// ChaCha20/Poly1305: [`ChaCha20Poly1305`]
+    ChaCha20Poly1305,
+}
+
+impl EncryptionAlgorithm {
+    /// COSE algorithm identifier
+    pub fn id(self) -> i64 {
+        match self {
+            Self::A128Gcm => 1,
+            Self::A256Gcm => 3,
+            Self::ChaCha20Poly1305 => 24,
+        }
+    }
+
+    /// Look up an algorithm by its COSE identifier.
+    pub fn from_id(id: i64) -> Option<Self> {
+        match id {
+            1 => Some(Self::A128Gcm),
+            3 => Some(Self::A256Gcm),
+            24 => Some(Self::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+
+    fn seal(
+        self,
+        key: &[u8],
+        iv: &[u8; IV_SIZE],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, CoseError> {
+        let result = match self {
+            Self::A128Gcm => {
+                cipher::<Aes128Gcm>(key)?.encrypt_in_place_detached(iv.into(), aad, buffer)
+            }
+            Self::A256Gcm => {
+                cipher::<Aes256Gcm>(key)?.encrypt_in_place_detached(iv.into(), aad, buffer)
+            }
+            Self::ChaCha20Poly1305 => {
+                cipher::<ChaCha20Poly1305>(key)?.encrypt_in_place_detached(iv.into(), aad, buffer)
+            }
+        };
+
+        result.map_err(|_| CoseError::Encryption)
+    }
+
+    fn open(
+        self,
+        key: &[u8],
+        iv: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), CoseError> {
+        let tag = Tag::from_slice(tag);
+        let result =
+            match self {
+                Self::A128Gcm => {
+                    cipher::<Aes128Gcm>(key)?.decrypt_in_place_detached(iv.into(), aad, buffer, tag)
+                }
+                Self::A256Gcm => {
+                    cipher::<Aes256Gcm>(key)?.decrypt_in_place_detached(iv.into(), aad, buffer, tag)
+                }
+                Self::ChaCha20Poly1305 => cipher::<ChaCha20Poly1305>(key)?
+                    .decrypt_in_place_detached(iv.into(), aad, buffer, tag),
+            };
+
+        result.map_err(|_| CoseError::Decryption)
+    }
+}
+
+/// MAC algorithms for `COSE_Mac0`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum MacAlgorithm {
+    /// HMAC 256/256: HMAC-SHA256 with a full-length tag
+    Hmac256,
+
+    /// HMAC 384/384: HMAC-SHA384 with a full-length tag
+    Hmac384,
+}
+
+impl MacAlgorithm {
+    /// COSE algorithm identifier
+    pub fn id(self) -> i64 {
+        match self {
+            Self::Hmac256 => 5,
+            Self::Hmac384 => 6,
+        }
+    }
+
+    /// Look up an algorithm by its COSE identifier.
+    pub fn from_id(id: i64) -> Option<Self> {
+        match id {
+            5 => Some(Self::Hmac256),
+            6 => Some(Self::Hmac384),
+            _ => None,
+        }
+    }
+
+    fn key(self, key: &[u8]) -> hmac::Key {
+        let algorithm = match self {
+            Self::Hmac256 => hmac::HMAC_SHA256,
+            Self::Hmac384 => hmac::HMAC_SHA384,
+        };
+
+        hmac::Key::new(algorithm, key)
+    }
+}
+
+/// Decrypted `COSE_Encrypt0` message
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Encrypt0 {
+    /// Content encryption algorithm
+    pub algorithm: EncryptionAlgorithm,
+
+    /// Key identifier, which is empty if absent
+    pub key_id: Vec<u8>,
+
+    /// Decrypted content
+    pub plaintext: Vec<u8>,
+}
+
+/// Verified `COSE_Mac0` message
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mac0 {
+    /// MAC algorithm
+    pub algorithm: MacAlgorithm,
+
+    /// Key identifier, which is empty if absent
+    pub key_id: Vec<u8>,
+
+    /// Authenticated content
+    pub payload: Vec<u8>,
+}
+
+/// Encrypt a message as a tagged `COSE_Encrypt0` with a random IV.
+///
+/// `key_id` is omitted from the unprotected header if empty.
+pub fn seal_encrypt0(
+    algorithm: EncryptionAlgorithm,
+    key: &[u8],
+    key_id: &[u8],
+    plaintext: &[u8],
+    external_aad: &[u8],
+) -> Result<Vec<u8>, CoseError> {
+    let mut iv = [0u8; IV_SIZE];
+    SystemRandom::new()
+        .fill(&mut iv)
+        .map_err(|_| CoseError::Rng)?;
+
+    let protected = protected_header(algorithm.id());
+    let aad = enc_structure(&protected, external_aad);
+
+    let mut ciphertext = Vec::with_capacity(plaintext.len() + TAG_SIZE);
+    ciphertext.extend_from_slice(plaintext);
+    let tag = algorithm.seal(key, &iv, &aad, &mut ciphertext)?;
+    ciphertext.extend_from_slice(&tag);
+
+    let mut out = Vec::new();
+    cbor::write_head(&mut out, cbor::TAG, ENCRYPT0_TAG);
+    cbor::write_head(&mut out, cbor::ARRAY, 3);
+    cbor::write_bytes(&mut out, &protected);
+    write_unprotected_header(&mut out, key_id, Some(&iv));
+    cbor::write_bytes(&mut out, &ciphertext);
+    Ok(out)
+}
+
+/// Decrypt a tagged or untagged `COSE_Encrypt0` message.
+pub fn open_encrypt0(
+    key: &[u8],
+    message: &[u8],
+    external_aad: &[u8],
+) -> Result<Encrypt0, CoseError> {
+    let (mut decoder, protected, headers) = decode_message(message, ENCRYPT0_TAG, 3)?;
+    let ciphertext = content(&mut decoder)?;
+    decoder.finish()?;
+
+    let algorithm = headers.algorithm()?;
+    let algorithm = EncryptionAlgorithm::from_id(algorithm).ok_or(CoseError::Unsupported)?;
+
+    let iv = headers.iv.ok_or(CoseError::Malformed)?;
+
+    if iv.len() != IV_SIZE || ciphertext.len() < TAG_SIZE {
+        return Err(CoseError::Malformed);
+    }
+
+    let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
+    let mut plaintext = ciphertext.to_vec();
+    let aad = enc_structure(protected, external_aad);
+    algorithm.open(key, iv, &aad, &mut plaintext, tag)?;
+
+    Ok(Encrypt0 {
+        algorithm,
+        key_id: headers.kid.unwrap_or_default().to_vec(),
+        plaintext,
+    })
+}
+
+/// Authenticate a message as a tagged `COSE_Mac0`.
+///
+/// `key_id` is omitted from the unprotected header if empty.
+pub fn create_mac0(
+    algorithm: MacAlgorithm,
+    key: &[u8],
+    key_id: &[u8],
+    payload: &[u8],
+    external_aad: &[u8],
+) -> Vec<u8> {
+    let protected = protected_header(algorithm.id());
+    let tag = hmac::sign(
+        &algorithm.key(key),
+        &mac_structure(&protected, external_aad, payload),
+    );
+
+    let mut out = Vec::new();
+    cbor::write_head(&mut out, cbor::TAG, MAC0_TAG);
+    cbor::write_head(&mut out, cbor::ARRAY, 4);
+    cbor::write_bytes(&mut out, &protected);
+    write_unprotected_header(&mut out, key_id, None);
+    cbor::write_bytes(&mut out, payload);
+    cbor::write_bytes(&mut out, tag.as_ref());
+    out
+}
+
+/// Verify a tagged or untagged `COSE_Mac0` message.
+pub fn verify_mac0(key: &[u8], message: &[u8], external_aad: &[u8]) -> Result<Mac0, CoseError> {
+    let (mut decoder, protected, headers) = decode_message(message, MAC0_TAG, 4)?;
+    let payload = content(&mut decoder)?;
+    let tag = decoder.bytes()?;
+    decoder.finish()?;
+
+    let algorithm = headers.algorithm()?;
+    let algorithm = MacAlgorithm::from_id(algorithm).ok_or(CoseError::Unsupported)?;
+
+    hmac::verify(
+        &algorithm.key(key),
+        &mac_structure(protected, external_aad, payload),
+        tag,
+    )
+    .map_err(|_| CoseError::Verification)?;
+
+    Ok(Mac0 {
+        algorithm,
+        key_id: headers.kid.unwrap_or_default().to_vec(),
+        payload: payload.to_vec(),
+    })
+}
+
+/// Header parameters understood by this crate
+#[derive(Default)]
+struct Headers<'a> {
+    alg: Option<i64>,
+    kid: Option<&'a [u8]>,
+    iv: Option<&'a [u8]>,
+}
+
+/// Header label
+#[derive(PartialEq)]
+enum Label<'a> {
+    Int(i64),
+    Text(&'a [u8]),
+}
+
+impl<'a> Headers<'a> {
+    /// Decode the protected header from its serialization, followed by the
+    /// unprotected header from the message.
+    fn decode(protected: &'a [u8], decoder: &mut Decoder<'a>) -> Result<Self, CoseError> {
+        let mut headers = Self::default();
+        let mut labels = Vec::new();
+
+        // A zero-length protected header is equivalent to an empty map
+        if !protected.is_empty() {
+            let mut protected = Decoder::new(protected);
+            headers.decode_map(&mut protected, &mut labels)?;
+            protected.finish()?;
+        }
+
+        headers.decode_map(decoder, &mut labels)?;
+        Ok(headers)
+    }
+
+    fn decode_map(
+        &mut self,
+        decoder: &mut Decoder<'a>,
+        labels: &mut Vec<Label<'a>>,
+    ) -> Result<(), CoseError> {
+        let len = decoder.expect(cbor::MAP)?;
+
+        for _ in 0..len {
+            let label = match decoder.peek_major()? {
+                cbor::UNSIGNED | cbor::NEGATIVE => Label::Int(decoder.int()?),
+                cbor::TEXT => Label::Text(decoder.skip()?),
+                _ => return Err(CoseError::Malformed),
+            };
+
+            if labels.contains(&label) {
+                return Err(CoseError::Malformed);
+            }
+
+            match label {
+                Label::Int(ALG) => match decoder.peek_major()? {
+                    cbor::UNSIGNED | cbor::NEGATIVE => self.alg = Some(decoder.int()?),
+                    cbor::TEXT => return Err(CoseError::Unsupported),
+                    _ => return Err(CoseError::Malformed),
+                },
+                Label::Int(CRIT | PARTIAL_IV) => return Err(CoseError::Unsupported),
+                Label::Int(KID) => self.kid = Some(decoder.bytes()?),
+                Label::Int(IV) => self.iv = Some(decoder.bytes()?),
+                _ => {
+                    decoder.skip()?;
+                }
+            }
+
+            labels.push(label);
+        }
+
+        Ok(())
+    }
+
+    fn algorithm(&self) -> Result<i64, CoseError> {
+        self.alg.ok_or(CoseError::Malformed)
+    }
+}
+
+/// Decode the CBOR tag (if present), array head and headers of a message.
+fn decode_message(
+    message: &[u8],
+    tag: u64,
+    len: u64,
+) -> Result<(Decoder<'_>, &[u8], Headers<'_>), CoseError> {
+    let mut decoder = Decoder::new(message);
+
+    if decoder.peek_major()? == cbor::TAG && decoder.expect(cbor::TAG)? != tag {
+        return Err(CoseError::Malformed);
+    }
+
+    if decoder.expect(cbor::ARRAY)? != len {
+        return Err(CoseError::Malformed);
+    }
+
+    let protected = decoder.bytes()?;
+    let headers = Headers::decode(protected, &mut decoder)?;
+    Ok((decoder, protected, headers))
+}
+
+/// Decode the content of a message, which must not be detached.
+fn content<'a>(decoder: &mut Decoder<'a>) -> Result<&'a [u8], CoseError> {
+    if decoder.null() {
+        return Err(CoseError::Unsupported);
+    }
+
+    decoder.bytes()
+}
+
+/// Serialize a protected header containing the algorithm.
+fn protected_header(algorithm: i64) -> Vec<u8> {
+    let mut out = Vec::new();
+    cbor::write_head(&mut out, cbor::MAP, 1);
+    cbor::write_int(&mut out, ALG);
+    cbor::write_int(&mut out, algorithm);
+    out
+}
+
+/// Append an unprotected header containing the key ID and IV.
+fn write_unprotected_header(out: &mut Vec<u8>, key_id: &[u8], iv: Option<&[u8]>) {
+    let len = usize::from(!key_id.is_empty()) + usize::from(iv.is_some());
+    cbor::write_head(out, cbor::MAP, len as u64);
+
+    if !key_id.is_empty() {
+        cbor::write_int(out, KID);
+        cbor::write_bytes(out, key_id);
+    }
+
+    if let Some(iv) = iv {
+        cbor::write_int(out, IV);
+        cbor::write_bytes(out, iv);
+    }
+}
+
+/// `Enc_structure` for `COSE_Encrypt0`, used as the AEAD's associated data
+fn enc_structure(protected: &[u8], external_aad: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    cbor::write_head(&mut out, cbor::ARRAY, 3);
+    cbor::write_text(&mut out, "Encrypt0");
+    cbor::write_bytes(&mut out, protected);
+    cbor::write_bytes(&mut out, external_aad);
+    out
+}
+
+/// `MAC_structure` for `COSE_Mac0`, which is the input to the MAC
+fn mac_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    cbor::write_head(&mut out, cbor::ARRAY, 4);
+    cbor::write_text(&mut out, "MAC0");
+    cbor::write_bytes(&mut out, protected);
+    cbor::write_bytes(&mut out, external_aad);
+    cbor::write_bytes(&mut out, payload);
+    out
+}
+
+/// Initialize a cipher, checking the key size.
+fn cipher<A: KeyInit>(key: &[u8]) -> Result<A, CoseError> {
+    A::new_from_slice(key).map_err(|_| CoseError::InvalidKey)
+}
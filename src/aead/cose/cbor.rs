@@ -0,0 +1,205 @@
+//! Minimal CBOR support for COSE messages
+//!
+//! Only definite-length items are supported.
+
+use super::CoseError;
+use alloc::vec::Vec;
+
+/// Maximum nesting depth of skipped arrays, maps and tags
+const MAX_DEPTH: usize = 16;
+
+/// Major type: unsigned integer
+pub(super) const UNSIGNED: u8 = 0;
+
+/// Major type: negative integer
+pub(super) const NEGATIVE: u8 = 1;
+
+/// Major type: byte string
+pub(super) const BYTES: u8 = 2;
+
+/// Major type: text string
+pub(super) const TEXT: u8 = 3;
+
+/// Major type: array
+pub(super) const ARRAY: u8 = 4;
+
+/// Major type: map
+pub(super) const MAP: u8 = 5;
+
+/// Major type: tag
+pub(super) const TAG: u8 = 6;
+
+/// Encoded `null`
+pub(super) const NULL: u8 = 0xf6;
+
+/// Append the head of a data item.
+pub(super) fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Append an integer.
+pub(super) fn write_int(out: &mut Vec<u8>, value: i64) {
+    if value < 0 {
+        write_head(out, NEGATIVE, !value as u64);
+    } else {
+        write_head(out, UNSIGNED, value as u64);
+    }
+}
+
+/// Append a byte string.
+pub(super) fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_head(out, BYTES, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Append a text string.
+pub(super) fn write_text(out: &mut Vec<u8>, text: &str) {
+    write_head(out, TEXT, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Decoder for a sequence of data items.
+pub(super) struct Decoder<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub(super) fn new(input: &'a [u8]) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    /// Check that all input has been consumed.
+    pub(super) fn finish(&self) -> Result<(), CoseError> {
+        if self.pos == self.input.len() {
+            Ok(())
+        } else {
+            Err(CoseError::Malformed)
+        }
+    }
+
+    /// Peek at the major type of the next item.
+    pub(super) fn peek_major(&self) -> Result<u8, CoseError> {
+        self.input
+            .get(self.pos)
+            .map(|b| b >> 5)
+            .ok_or(CoseError::Malformed)
+    }
+
+    /// Consume a `null` if it's the next item.
+    pub(super) fn null(&mut self) -> bool {
+        if self.input.get(self.pos) == Some(&NULL) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Decode the head of a data item, returning its major type and argument.
+    pub(super) fn head(&mut self) -> Result<(u8, u64), CoseError> {
+        let initial = self.take(1)?[0];
+        let major = initial >> 5;
+
+        let value = match initial & 0x1f {
+            n @ 0..=23 => u64::from(n),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().unwrap())),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().unwrap()),
+            _ => return Err(CoseError::Malformed),
+        };
+
+        Ok((major, value))
+    }
+
+    /// Decode the head of an item of the given major type, returning its
+    /// argument.
+    pub(super) fn expect(&mut self, major: u8) -> Result<u64, CoseError> {
+        match self.head()? {
+            (m, value) if m == major => Ok(value),
+            _ => Err(CoseError::Malformed),
+        }
+    }
+
+    /// Decode a byte string.
+    pub(super) fn bytes(&mut self) -> Result<&'a [u8], CoseError> {
+        let len = self.expect(BYTES)?;
+        self.take(len)
+    }
+
+    /// Decode an integer.
+    pub(super) fn int(&mut self) -> Result<i64, CoseError> {
+        match self.head()? {
+            (UNSIGNED, value) => i64::try_from(value).map_err(|_| CoseError::Malformed),
+            (NEGATIVE, value) => i64::try_from(value)
+                .map(|value| !value)
+                .map_err(|_| CoseError::Malformed),
+            _ => Err(CoseError::Malformed),
+        }
+    }
+
+    /// Skip a data item, returning its encoding.
+    pub(super) fn skip(&mut self) -> Result<&'a [u8], CoseError> {
+        let start = self.pos;
+        self.skip_item(0)?;
+        Ok(&self.input[start..self.pos])
+    }
+
+    fn skip_item(&mut self, depth: usize) -> Result<(), CoseError> {
+        if depth > MAX_DEPTH {
+            return Err(CoseError::Malformed);
+        }
+
+        match self.head()? {
+            (UNSIGNED | NEGATIVE, _) => (),
+            (BYTES, len) => {
+                self.take(len)?;
+            }
+            (TEXT, len) => {
+                core::str::from_utf8(self.take(len)?).map_err(|_| CoseError::Malformed)?;
+            }
+            (ARRAY, len) => {
+                for _ in 0..len {
+                    self.skip_item(depth + 1)?;
+                }
+            }
+            (MAP, len) => {
+                for _ in 0..len {
+                    self.skip_item(depth + 1)?;
+                    self.skip_item(depth + 1)?;
+                }
+            }
+            (TAG, _) => self.skip_item(depth + 1)?,
+            // Simple values and floats, whose arguments have been consumed
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn take(&mut self, len: u64) -> Result<&'a [u8], CoseError> {
+        let len = usize::try_from(len).map_err(|_| CoseError::Malformed)?;
+        let end = self.pos.checked_add(len).ok_or(CoseError::Malformed)?;
+        let bytes = self.input.get(self.pos..end).ok_or(CoseError::Malformed)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,409 @@
+//! Replay-protected datagram sessions
+//!
+//! Protects datagrams sent over an unreliable transport such as UDP, where
+//! they may be lost, duplicated or reordered. Each datagram carries the epoch
+//! of the keys it was sealed with and a 64-bit sequence number, which is
+//! combined with a per-epoch IV to form the nonce:
+//!
+//! ```text
+//! epoch (2 bytes) || sequence number (8 bytes) || ciphertext || tag
+//! ```
+//!
+//! Received sequence numbers are tracked by a sliding anti-replay window in
+//! the style of [RFC 6479], so datagrams arriving out of order within the
+//! window are accepted, while duplicates and datagrams behind the window are
+//! rejected.
+//!
+//! Each direction's keys are derived from a traffic secret with TLS 1.3's
+//! `HKDF-Expand-Label`, using the labels `dgram key` and `dgram iv`. The
+//! sender moves to the next epoch with [`Session::rekey`], which derives the
+//! next traffic secret (`dgram upd`). The receiver follows once a datagram of
+//! the next epoch authenticates, and keeps accepting datagrams of the
+//! previous epoch until the one after that, so that datagrams still in flight
+//! aren't lost.
+//!
+//! [RFC 6479]: https://www.rfc-editor.org/rfc/rfc6479
+
+use super::{
+    replay_window::{Rejected, ReplayWindow},
+    tls13::{CipherSuite, Iv, Secret},
+};
+use aead::generic_array::GenericArray;
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of the datagram header: the epoch followed by the sequence number
+pub const HEADER_LEN: usize = 10;
+
+/// Size of the authentication tag
+pub const TAG_LEN: usize = 16;
+
+/// Number of sequence numbers behind the highest one received in an epoch
+/// which are accepted
+pub const WINDOW_SIZE: u64 = ReplayWindow::SIZE;
+
+/// Errors returned by datagram sessions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DatagramError {
+    /// Traffic secret doesn't match the cipher's HKDF algorithm
+    InvalidKey,
+
+    /// Datagram is too short to hold a header and tag
+    Malformed,
+
+    /// Datagram's sequence number has already been received
+    Duplicate,
+
+    /// Datagram is behind the replay window, or belongs to a retired epoch
+    Stale,
+
+    /// Datagram belongs to an epoch which is too far ahead
+    UnknownEpoch,
+
+    /// Datagram failed to authenticate
+    Decryption,
+
+    /// Encryption failed
+    Encryption,
+
+    /// Sequence numbers of the current epoch have been exhausted, and the
+    /// session must be rekeyed
+    SequenceExhausted,
+
+    /// Epochs have been exhausted
+    EpochExhausted,
+}
+
+impl fmt::Display for DatagramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidKey => "invalid traffic secret",
+            Self::Malformed => "malformed datagram",
+            Self::Duplicate => "duplicate datagram",
+            Self::Stale => "stale datagram",
+            Self::UnknownEpoch => "unknown epoch",
+            Self::Decryption => "decryption failed",
+            Self::Encryption => "encryption failed",
+            Self::SequenceExhausted => "sequence numbers exhausted",
+            Self::EpochExhausted => "epochs exhausted",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DatagramError {}
+
+impl From<Rejected> for DatagramError {
+    fn from(rejected: Rejected) -> Self {
+        match rejected {
+            Rejected::Duplicate => Self::Duplicate,
+            Rejected::Stale => Self::Stale,
+        }
+    }
+}
+
+/// Session protecting datagrams in both directions.
+///
+/// The peers' sending and receiving traffic secrets must be swapped, i.e. one
+/// peer's sending secret is the other's receiving secret.
+pub struct Session<A: CipherSuite> {
+    send: Sender<A>,
+    receive: Receiver<A>,
+}
+
+impl<A: CipherSuite> Session<A> {
+    /// Create a session from the traffic secrets of epoch 0, which must be
+    /// the output size of the cipher's HKDF hash function.
+    pub fn new(send_secret: &[u8], receive_secret: &[u8]) -> Result<Self, DatagramError> {
+        let send_secret = traffic_secret::<A>(send_secret)?;
+        let receive_secret = traffic_secret::<A>(receive_secret)?;
+
+        Ok(Self {
+            send: Sender {
+                keys: EpochKeys::new(0, &send_secret),
+                secret: send_secret,
+                sequence_number: 0,
+            },
+            receive: Receiver::new(&receive_secret),
+        })
+    }
+
+    /// Epoch of the keys datagrams are sealed with.
+    pub fn send_epoch(&self) -> u16 {
+        self.send.keys.epoch
+    }
+
+    /// Sequence number of the next datagram sealed.
+    pub fn sequence_number(&self) -> u64 {
+        self.send.sequence_number
+    }
+
+    /// Most recent epoch of the keys datagrams have been opened with.
+    pub fn receive_epoch(&self) -> u16 {
+        self.receive.current.epoch
+    }
+
+    /// Move to the next epoch's keys for sealing datagrams, restarting the
+    /// sequence numbers at 0.
+    pub fn rekey(&mut self) -> Result<(), DatagramError> {
+        let epoch = self
+            .send
+            .keys
+            .epoch
+            .checked_add(1)
+            .ok_or(DatagramError::EpochExhausted)?;
+
+        self.send.secret = next_secret(&self.send.secret);
+        self.send.keys = EpochKeys::new(epoch, &self.send.secret);
+        self.send.sequence_number = 0;
+        Ok(())
+    }
+
+    /// Seal a datagram, returning it along with its header and tag.
+    #[cfg(feature = "alloc")]
+    pub fn seal(
+        &mut self,
+        associated_data: &[u8],
+        payload: &[u8],
+    ) -> Result<Vec<u8>, DatagramError> {
+        let mut datagram = Vec::with_capacity(HEADER_LEN + payload.len() + TAG_LEN);
+        datagram.resize(HEADER_LEN, 0);
+        datagram.extend_from_slice(payload);
+        datagram.resize(datagram.len() + TAG_LEN, 0);
+
+        self.seal_in_place(associated_data, &mut datagram)?;
+        Ok(datagram)
+    }
+
+    /// Seal a datagram in place.
+    ///
+    /// The payload must be placed after [`HEADER_LEN`] bytes reserved for the
+    /// header and followed by [`TAG_LEN`] bytes reserved for the tag.
+    pub fn seal_in_place(
+        &mut self,
+        associated_data: &[u8],
+        datagram: &mut [u8],
+    ) -> Result<(), DatagramError> {
+        if datagram.len() < HEADER_LEN + TAG_LEN {
+            return Err(DatagramError::Malformed);
+        }
+
+        let sequence_number = self.send.sequence_number;
+
+        if sequence_number == u64::MAX {
+            return Err(DatagramError::SequenceExhausted);
+        }
+
+        let (header, payload) = datagram.split_at_mut(HEADER_LEN);
+        let (payload, tag) = payload.split_at_mut(payload.len() - TAG_LEN);
+        let keys = &self.send.keys;
+
+        header[..2].copy_from_slice(&keys.epoch.to_be_bytes());
+        header[2..].copy_from_slice(&sequence_number.to_be_bytes());
+
+        tag.copy_from_slice(
+            &keys
+                .aead
+                .encrypt_in_place_detached(
+                    &keys.iv.nonce(sequence_number),
+                    associated_data,
+                    payload,
+                )
+                .map_err(|_| DatagramError::Encryption)?,
+        );
+
+        self.send.sequence_number += 1;
+        Ok(())
+    }
+
+    /// Open a datagram in place, returning its payload.
+    ///
+    /// Its sequence number is only recorded once it has authenticated.
+    pub fn open_in_place<'a>(
+        &mut self,
+        associated_data: &[u8],
+        datagram: &'a mut [u8],
+    ) -> Result<&'a [u8], DatagramError> {
+        if datagram.len() < HEADER_LEN + TAG_LEN {
+            return Err(DatagramError::Malformed);
+        }
+
+        let (header, payload) = datagram.split_at_mut(HEADER_LEN);
+        let epoch = u16::from_be_bytes([header[0], header[1]]);
+        let sequence_number = u64::from_be_bytes(header[2..].try_into().unwrap());
+
+        let (payload, tag) = payload.split_at_mut(payload.len() - TAG_LEN);
+        self.receive.open(
+            epoch,
+            sequence_number,
+            associated_data,
+            payload,
+            GenericArray::from_slice(tag),
+        )?;
+
+        Ok(payload)
+    }
+}
+
+impl<A: CipherSuite> fmt::Debug for Session<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("send_epoch", &self.send_epoch())
+            .field("sequence_number", &self.sequence_number())
+            .field("receive_epoch", &self.receive_epoch())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sending half of a session
+struct Sender<A> {
+    /// Keys of the current epoch
+    keys: EpochKeys<A>,
+
+    /// Traffic secret of the current epoch
+    secret: Secret,
+
+    /// Sequence number of the next datagram
+    sequence_number: u64,
+}
+
+/// Receiving half of a session
+struct Receiver<A> {
+    /// Keys of the epoch before the current one, if any
+    previous: Option<EpochKeys<A>>,
+
+    /// Keys of the most recent epoch a datagram has been opened with
+    current: EpochKeys<A>,
+
+    /// Keys and traffic secret of the epoch after the current one, if any
+    next: Option<(EpochKeys<A>, Secret)>,
+}
+
+impl<A: CipherSuite> Receiver<A> {
+    fn new(secret: &Secret) -> Self {
+        let next_secret = next_secret(secret);
+
+        Self {
+            previous: None,
+            current: EpochKeys::new(0, secret),
+            next: Some((EpochKeys::new(1, &next_secret), next_secret)),
+        }
+    }
+
+    fn open(
+        &mut self,
+        epoch: u16,
+        sequence_number: u64,
+        associated_data: &[u8],
+        payload: &mut [u8],
+        tag: &GenericArray<u8, A::TagSize>,
+    ) -> Result<(), DatagramError> {
+        let keys = if epoch == self.current.epoch {
+            &mut self.current
+        } else if epoch < self.current.epoch {
+            match &mut self.previous {
+                Some(previous) if previous.epoch == epoch => previous,
+                _ => return Err(DatagramError::Stale),
+            }
+        } else {
+            match &mut self.next {
+                Some((next, _)) if next.epoch == epoch => next,
+                _ => return Err(DatagramError::UnknownEpoch),
+            }
+        };
+
+        keys.open(sequence_number, associated_data, payload, tag)?;
+
+        if epoch > self.current.epoch {
+            self.advance();
+        }
+
+        Ok(())
+    }
+
+    /// Make the next epoch current, once a datagram of it has been opened.
+    fn advance(&mut self) {
+        let (keys, secret) = self.next.take().expect("next epoch should exist");
+
+        self.next = keys.epoch.checked_add(1).map(|epoch| {
+            let next_secret = next_secret(&secret);
+            (EpochKeys::new(epoch, &next_secret), next_secret)
+        });
+
+        self.previous = Some(core::mem::replace(&mut self.current, keys));
+    }
+}
+
+/// AEAD key, IV and replay window of one epoch
+struct EpochKeys<A> {
+    epoch: u16,
+    aead: A,
+    iv: Iv,
+    window: ReplayWindow,
+}
+
+impl<A: CipherSuite> EpochKeys<A> {
+    /// Derive the keys of an epoch from its traffic secret.
+    fn new(epoch: u16, secret: &Secret) -> Self {
+        let mut key = GenericArray::<u8, A::KeySize>::default();
+        let mut iv = [0u8; 12];
+
+        secret
+            .expand_label(b"dgram key", &[], &mut key)
+            .expect("key label should fit");
+        secret
+            .expand_label(b"dgram iv", &[], &mut iv)
+            .expect("IV label should fit");
+
+        let aead = A::new(&key);
+
+        #[cfg(feature = "zeroize")]
+        key.as_mut_slice().zeroize();
+
+        Self {
+            epoch,
+            aead,
+            iv: Iv::from(iv),
+            window: ReplayWindow::default(),
+        }
+    }
+
+    fn open(
+        &mut self,
+        sequence_number: u64,
+        associated_data: &[u8],
+        payload: &mut [u8],
+        tag: &GenericArray<u8, A::TagSize>,
+    ) -> Result<(), DatagramError> {
+        self.window.check(sequence_number)?;
+
+        self.aead
+            .decrypt_in_place_detached(
+                &self.iv.nonce(sequence_number),
+                associated_data,
+                payload,
+                tag,
+            )
+            .map_err(|_| DatagramError::Decryption)?;
+
+        Ok(self.window.update(sequence_number)?)
+    }
+}
+
+/// Create the traffic secret of epoch 0 for the given cipher.
+fn traffic_secret<A: CipherSuite>(secret: &[u8]) -> Result<Secret, DatagramError> {
+    Secret::new(A::hkdf_algorithm(), secret).map_err(|_| DatagramError::InvalidKey)
+}
+
+/// Derive the traffic secret of the next epoch (`dgram upd`).
+fn next_secret(secret: &Secret) -> Secret {
+    secret
+        .derive_secret(b"dgram upd", &[])
+        .expect("update label should fit")
+}
@@ -0,0 +1,152 @@
+//! AES-256-GCM with per-message derived keys and extended nonces
+//!
+//! AES-GCM's 96-bit nonces are too short to be chosen at random for more
+//! than 2^32 messages under one key. [`DerivedKeyAes256Gcm`] accepts a 192-bit
+//! or 256-bit nonce instead: all but its last 96 bits are used to derive a
+//! per-message key from the long-term key with HKDF-SHA-256, and the last 96
+//! bits are used as the AES-GCM nonce under that derived key.
+//!
+//! # Key derivation
+//!
+//! ```text
+//! PRK = HKDF-Extract(salt = "", IKM = key)
+//! message key = HKDF-Expand(PRK, "ring-compat derived-key AES-256-GCM" || nonce[..len - 12], 32)
+//! ```
+//!
+//! # Security bounds
+//!
+//! Assuming HMAC-SHA-256 is a PRF, each derived key behaves as an independent
+//! AES-256-GCM key. With uniformly random 192-bit nonces, the probability
+//! that two of `q` messages share both a derived key and a GCM nonce is
+//! about q²/2^193, so up to 2^80 messages may be encrypted under one
+//! long-term key while keeping it below 2^-32. With 256-bit nonces the
+//! derivation input is 160 bits and derived keys themselves are not expected
+//! to repeat.
+//!
+//! Each message is still limited to 2^36 - 32 bytes. Forgeries choose their
+//! own nonce, so an attacker can direct every attempt at the same derived key
+//! by reusing a nonce prefix: failed decryptions are bounded by AES-GCM's
+//! integrity limit of 2^52 per derived key for messages of up to 2^16 bytes
+//! ([RFC 9001 Section 6.6]), which applies to the long-term key as a whole
+//! when attempts aren't tracked per derived key.
+//!
+//! [RFC 9001 Section 6.6]: https://www.rfc-editor.org/rfc/rfc9001#section-6.6
+
+use super::{AeadCore, AeadInPlace, Buffer, Cipher, Error, KeyInit, KeySizeUser, Tag};
+use aead::{
+    consts::{U0, U16, U24, U32},
+    generic_array::{ArrayLength, GenericArray},
+};
+use core::{fmt, marker::PhantomData};
+use ring::{
+    aead::{UnboundKey, AES_256_GCM},
+    hkdf,
+};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// HKDF info prefix for derived message keys
+const INFO: &[u8] = b"ring-compat derived-key AES-256-GCM";
+
+/// Size of the AES-GCM nonce at the end of an extended nonce
+const GCM_NONCE_SIZE: usize = 12;
+
+/// AES-256-GCM with per-message keys derived from an extended nonce of size
+/// `N`, which is either 24 (the default) or 32 bytes.
+#[derive(Clone)]
+pub struct DerivedKeyAes256Gcm<N: ExtendedNonceSize = U24> {
+    prk: hkdf::Prk,
+    nonce_size: PhantomData<N>,
+}
+
+impl<N: ExtendedNonceSize> fmt::Debug for DerivedKeyAes256Gcm<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedKeyAes256Gcm")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Extended nonce sizes supported by [`DerivedKeyAes256Gcm`]: 24 and 32
+/// bytes.
+pub trait ExtendedNonceSize: ArrayLength<u8> + sealed::Sealed {}
+
+impl ExtendedNonceSize for U24 {}
+impl ExtendedNonceSize for U32 {}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::U24 {}
+    impl Sealed for super::U32 {}
+}
+
+impl<N: ExtendedNonceSize> KeySizeUser for DerivedKeyAes256Gcm<N> {
+    type KeySize = U32;
+}
+
+impl<N: ExtendedNonceSize> KeyInit for DerivedKeyAes256Gcm<N> {
+    fn new(key: &GenericArray<u8, Self::KeySize>) -> Self {
+        Self {
+            prk: hkdf::Salt::new(hkdf::HKDF_SHA256, &[]).extract(key),
+            nonce_size: PhantomData,
+        }
+    }
+}
+
+impl<N: ExtendedNonceSize> AeadCore for DerivedKeyAes256Gcm<N> {
+    type NonceSize = N;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+impl<N: ExtendedNonceSize> AeadInPlace for DerivedKeyAes256Gcm<N> {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &GenericArray<u8, N>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, Error> {
+        let (cipher, nonce) = self.derive(nonce);
+        cipher.encrypt_in_place_detached(nonce, associated_data, buffer)
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &GenericArray<u8, N>,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        let (cipher, nonce) = self.derive(nonce);
+        cipher.decrypt_in_place(nonce, associated_data, buffer)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &GenericArray<u8, N>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        let (cipher, nonce) = self.derive(nonce);
+        cipher.decrypt_in_place_detached(nonce, associated_data, buffer, tag)
+    }
+}
+
+impl<N: ExtendedNonceSize> DerivedKeyAes256Gcm<N> {
+    /// Derive the AES-256-GCM cipher for a given extended nonce, returning it
+    /// along with the 96-bit GCM nonce.
+    fn derive<'n>(&self, nonce: &'n GenericArray<u8, N>) -> (Cipher, &'n [u8]) {
+        let (derivation_nonce, gcm_nonce) = nonce.split_at(nonce.len() - GCM_NONCE_SIZE);
+        let mut subkey = [0u8; 32];
+        self.prk
+            .expand(&[INFO, derivation_nonce], &AES_256_GCM)
+            .and_then(|okm| okm.fill(&mut subkey))
+            .expect("HKDF output should be the AES-256 key size");
+        let key = UnboundKey::new(&AES_256_GCM, &subkey).unwrap();
+
+        #[cfg(feature = "zeroize")]
+        subkey.zeroize();
+
+        (Cipher::new(key), gcm_nonce)
+    }
+}
@@ -0,0 +1,265 @@
+//! Envelope encryption
+//!
+//! Each message is encrypted under a fresh random AES-256-GCM data key, and
+//! the data key is in turn encrypted ("wrapped") by a key-encryption key
+//! (KEK), which is typically held by a key management service or hardware
+//! security module. The wrapped data key is stored alongside the ciphertext.
+//!
+//! KEKs are pluggable through the [`KeyEncryptionKey`] trait. A
+//! [`LocalKeyEncryptionKey`] backed by an in-process AES-256-GCM key is
+//! provided as a stand-in for testing and development.
+//!
+//! # Envelope format
+//!
+//! Envelopes are self-describing. All integers are big endian:
+//!
+//! ```text
+//! version (1 byte, 0x01) || algorithm (2 bytes) || wrapped key length (2 bytes)
+//!   || wrapped key || nonce (12 bytes) || ciphertext || tag (16 bytes)
+//! ```
+//!
+//! The algorithm is the [`AeadAlgorithm::id`] of the data encryption
+//! algorithm, currently always AES-256-GCM. Everything which precedes the
+//! ciphertext is authenticated as part of the associated data, followed by
+//! any caller-supplied associated data.
+
+use super::{AeadAlgorithm, AeadInPlace, Aes256Gcm, Error, KeyInit};
+use aead::generic_array::GenericArray;
+use alloc::vec::Vec;
+use core::fmt;
+use ring::rand::{SecureRandom, SystemRandom};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
+
+/// Current envelope format version
+const VERSION: u8 = 0x01;
+
+/// Size of a nonce
+const NONCE_SIZE: usize = 12;
+
+/// Size of a tag
+const TAG_SIZE: usize = 16;
+
+/// Size of an AES-256-GCM data key
+const DATA_KEY_SIZE: usize = 32;
+
+/// Associated data used by [`LocalKeyEncryptionKey`] when wrapping data keys
+const LOCAL_KEK_AAD: &[u8] = b"ring-compat envelope data key";
+
+/// Key-encryption key which wraps and unwraps data keys.
+pub trait KeyEncryptionKey {
+    /// Wrap (encrypt) a data key.
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Unwrap (decrypt) a data key.
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// Key-encryption key backed by a local AES-256-GCM key.
+///
+/// Wrapped keys are `nonce || ciphertext || tag`. This is intended as a
+/// stand-in for a key management service during testing and development.
+#[derive(Clone)]
+pub struct LocalKeyEncryptionKey(Aes256Gcm);
+
+impl LocalKeyEncryptionKey {
+    /// Create a key-encryption key from a 256-bit key.
+    pub fn new(key: &GenericArray<u8, <Aes256Gcm as aead::KeySizeUser>::KeySize>) -> Self {
+        Self(Aes256Gcm::new(key))
+    }
+
+    /// Generate a random key-encryption key.
+    pub fn generate() -> Result<Self, Error> {
+        let mut key = GenericArray::default();
+        SystemRandom::new().fill(&mut key).map_err(|_| Error)?;
+        let kek = Self::new(&key);
+
+        #[cfg(feature = "zeroize")]
+        key.as_mut_slice().zeroize();
+
+        Ok(kek)
+    }
+}
+
+impl fmt::Debug for LocalKeyEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalKeyEncryptionKey")
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyEncryptionKey for LocalKeyEncryptionKey {
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut out = alloc::vec![0u8; NONCE_SIZE];
+        SystemRandom::new().fill(&mut out).map_err(|_| Error)?;
+        out.extend_from_slice(data_key);
+
+        let (nonce, msg) = out.split_at_mut(NONCE_SIZE);
+        let tag = self.0.encrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            LOCAL_KEK_AAD,
+            msg,
+        )?;
+
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>, Error> {
+        let (nonce, msg, tag) = split_sealed(wrapped_key)?;
+        let mut data_key = msg.to_vec();
+
+        let result = self.0.decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            LOCAL_KEK_AAD,
+            &mut data_key,
+            GenericArray::from_slice(tag),
+        );
+
+        #[cfg(feature = "zeroize")]
+        if result.is_err() {
+            data_key.zeroize();
+        }
+
+        result?;
+        Ok(data_key)
+    }
+}
+
+/// Encrypt a message under a fresh data key wrapped by the given KEK,
+/// returning the envelope.
+pub fn seal(
+    kek: &impl KeyEncryptionKey,
+    plaintext: &[u8],
+    associated_data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let rng = SystemRandom::new();
+    let mut data_key = [0u8; DATA_KEY_SIZE];
+    rng.fill(&mut data_key).map_err(|_| Error)?;
+
+    let wrapped_key = kek.wrap_key(&data_key);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(&data_key));
+
+    #[cfg(feature = "zeroize")]
+    data_key.zeroize();
+
+    let wrapped_key = wrapped_key?;
+    let wrapped_key_len = u16::try_from(wrapped_key.len()).map_err(|_| Error)?;
+
+    let mut out = Vec::new();
+    out.push(VERSION);
+    out.extend_from_slice(&AeadAlgorithm::Aes256Gcm.id().to_be_bytes());
+    out.extend_from_slice(&wrapped_key_len.to_be_bytes());
+    out.extend_from_slice(&wrapped_key);
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    rng.fill(&mut nonce).map_err(|_| Error)?;
+    out.extend_from_slice(&nonce);
+
+    let header_len = out.len();
+    let aad = [&out[..], associated_data].concat();
+    out.extend_from_slice(plaintext);
+
+    let tag = cipher.encrypt_in_place_detached(&nonce.into(), &aad, &mut out[header_len..])?;
+
+    out.extend_from_slice(&tag);
+    Ok(out)
+}
+
+/// Open an envelope, unwrapping its data key with the given KEK.
+pub fn open(
+    kek: &impl KeyEncryptionKey,
+    envelope: &[u8],
+    associated_data: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let envelope = Envelope::parse(envelope)?;
+
+    if envelope.algorithm != AeadAlgorithm::Aes256Gcm {
+        return Err(Error);
+    }
+
+    let data_key = kek.unwrap_key(envelope.wrapped_key)?;
+
+    #[cfg(feature = "zeroize")]
+    let data_key = Zeroizing::new(data_key);
+
+    let cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|_| Error)?;
+    let aad = [envelope.header, associated_data].concat();
+    let mut buffer = envelope.ciphertext.to_vec();
+
+    cipher.decrypt_in_place_detached(
+        GenericArray::from_slice(envelope.nonce),
+        &aad,
+        &mut buffer,
+        GenericArray::from_slice(envelope.tag),
+    )?;
+
+    Ok(buffer)
+}
+
+/// Parsed envelope.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Envelope<'a> {
+    /// Data encryption algorithm
+    pub algorithm: AeadAlgorithm,
+
+    /// Data key wrapped by the KEK
+    pub wrapped_key: &'a [u8],
+
+    /// Nonce used with the data key
+    pub nonce: &'a [u8],
+
+    /// Encrypted message
+    pub ciphertext: &'a [u8],
+
+    /// Authentication tag
+    pub tag: &'a [u8],
+
+    /// Everything preceding the ciphertext
+    header: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// Parse an envelope.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, Error> {
+        match bytes {
+            [VERSION, a1, a2, l1, l2, rest @ ..] => {
+                let algorithm = AeadAlgorithm::from_id(u16::from_be_bytes([*a1, *a2]))?;
+                let wrapped_key_len = usize::from(u16::from_be_bytes([*l1, *l2]));
+
+                if rest.len() < wrapped_key_len {
+                    return Err(Error);
+                }
+
+                let (wrapped_key, sealed) = rest.split_at(wrapped_key_len);
+                let (nonce, ciphertext, tag) = split_sealed(sealed)?;
+                let header_len = bytes.len() - ciphertext.len() - tag.len();
+
+                Ok(Self {
+                    algorithm,
+                    wrapped_key,
+                    nonce,
+                    ciphertext,
+                    tag,
+                    header: &bytes[..header_len],
+                })
+            }
+            _ => Err(Error),
+        }
+    }
+}
+
+/// Borrowed `(nonce, ciphertext, tag)`
+type Sealed<'a> = (&'a [u8], &'a [u8], &'a [u8]);
+
+/// Split `nonce || ciphertext || tag`
+fn split_sealed(bytes: &[u8]) -> Result<Sealed<'_>, Error> {
+    if bytes.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(Error);
+    }
+
+    let (nonce, rest) = bytes.split_at(NONCE_SIZE);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+    Ok((nonce, ciphertext, tag))
+}
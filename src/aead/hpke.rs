@@ -0,0 +1,611 @@
+//! Hybrid Public Key Encryption as described in [RFC 9180]
+//!
+//! Supports the base and PSK modes, i.e. those without sender
+//! authentication, with the following algorithms:
+//!
+//! - KEMs: DHKEM(X25519, HKDF-SHA256) and DHKEM(P-256, HKDF-SHA256)
+//! - KDFs: HKDF-SHA256, HKDF-SHA384 and HKDF-SHA512
+//! - AEADs: [`Aes128Gcm`], [`Aes256Gcm`] and [`ChaCha20Poly1305`]
+//!
+//! Encapsulation uses ephemeral keys from *ring*'s `agreement` module, and
+//! decapsulation uses a static [`PrivateKey`] of the KEM's algorithm.
+//!
+//! [RFC 9180]: https://www.rfc-editor.org/rfc/rfc9180
+
+use super::{
+    tls13::OutputLen, AeadCore, AeadInPlace, Aes128Gcm, Aes256Gcm, ChaCha20Poly1305, KeyInit, Tag,
+};
+use crate::agreement::PrivateKey;
+use aead::{
+    consts::{U12, U16},
+    generic_array::GenericArray,
+};
+use alloc::vec::Vec;
+use core::fmt;
+use ring::{
+    agreement,
+    hkdf::{self, KeyType},
+    hmac,
+    rand::SystemRandom,
+};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
+
+/// Prefix of all labels
+const VERSION_LABEL: &[u8] = b"HPKE-v1";
+
+/// Size of the KEM shared secret and Diffie-Hellman output of both KEMs
+pub const SHARED_SECRET_LEN: usize = 32;
+
+/// Maximum size of a KDF's hash output (SHA-512)
+const MAX_HASH_LEN: usize = 64;
+
+/// Size of the HPKE suite ID: `"HPKE" || kem_id || kdf_id || aead_id`
+const SUITE_ID_LEN: usize = 10;
+
+/// Errors returned by HPKE operations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum HpkeError {
+    /// Encryption failed, e.g. because the message is too long
+    Seal,
+
+    /// Decryption failed
+    Open,
+
+    /// A public key or encapsulated key is invalid, a private key doesn't
+    /// belong to the KEM, or Diffie-Hellman failed or produced the all-zero
+    /// value
+    InvalidKey,
+
+    /// The PSK or PSK ID is empty in PSK mode
+    InvalidPsk,
+
+    /// The sequence number can't be incremented any further
+    MessageLimitReached,
+
+    /// Exported secret is longer than 255 times the KDF's hash output
+    ExportTooLong,
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for HpkeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Seal => f.write_str("HPKE encryption failed"),
+            Self::Open => f.write_str("HPKE decryption failed"),
+            Self::InvalidKey => f.write_str("invalid HPKE public key or encapsulated key"),
+            Self::InvalidPsk => f.write_str("PSK and PSK ID must be non-empty"),
+            Self::MessageLimitReached => f.write_str("HPKE message limit reached"),
+            Self::ExportTooLong => f.write_str("HPKE export too long"),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HpkeError {}
+
+/// HPKE mode.
+///
+/// The authenticated modes aren't supported.
+#[derive(Copy, Clone)]
+pub enum Mode<'a> {
+    /// `mode_base`
+    Base,
+
+    /// `mode_psk`: both parties hold a pre-shared key
+    Psk {
+        /// Pre-shared key, which should have at least 32 bytes of entropy
+        psk: &'a [u8],
+
+        /// Identifier of the pre-shared key
+        psk_id: &'a [u8],
+    },
+}
+
+impl Mode<'_> {
+    /// Mode identifier
+    fn id(&self) -> u8 {
+        match self {
+            Self::Base => 0x00,
+            Self::Psk { .. } => 0x01,
+        }
+    }
+
+    /// PSK and PSK ID, which are empty in base mode
+    fn psk(&self) -> Result<(&[u8], &[u8]), HpkeError> {
+        match *self {
+            Self::Base => Ok((b"", b"")),
+            Self::Psk { psk, psk_id } if !psk.is_empty() && !psk_id.is_empty() => Ok((psk, psk_id)),
+            Self::Psk { .. } => Err(HpkeError::InvalidPsk),
+        }
+    }
+}
+
+/// Diffie-Hellman based KEMs.
+pub trait Kem: sealed::Sealed {
+    /// KEM identifier
+    const KEM_ID: u16;
+
+    /// Size of serialized public keys and encapsulated keys
+    const PUBLIC_KEY_LEN: usize;
+
+    /// *ring* key agreement algorithm of ephemeral and private keys
+    fn agreement_algorithm() -> &'static agreement::Algorithm;
+}
+
+/// DHKEM(X25519, HKDF-SHA256)
+pub struct DhKemX25519HkdfSha256;
+
+impl Kem for DhKemX25519HkdfSha256 {
+    const KEM_ID: u16 = 0x0020;
+    const PUBLIC_KEY_LEN: usize = 32;
+
+    fn agreement_algorithm() -> &'static agreement::Algorithm {
+        &agreement::X25519
+    }
+}
+
+/// DHKEM(P-256, HKDF-SHA256)
+///
+/// Public keys are uncompressed SEC1 points.
+pub struct DhKemP256HkdfSha256;
+
+impl Kem for DhKemP256HkdfSha256 {
+    const KEM_ID: u16 = 0x0010;
+    const PUBLIC_KEY_LEN: usize = 65;
+
+    fn agreement_algorithm() -> &'static agreement::Algorithm {
+        &agreement::ECDH_P256
+    }
+}
+
+/// HKDF-based KDFs.
+pub trait Kdf {
+    /// KDF identifier
+    const KDF_ID: u16;
+
+    /// *ring* HKDF algorithm
+    fn algorithm() -> hkdf::Algorithm;
+}
+
+/// HKDF-SHA256
+pub struct HkdfSha256;
+
+/// HKDF-SHA384
+pub struct HkdfSha384;
+
+/// HKDF-SHA512
+pub struct HkdfSha512;
+
+impl Kdf for HkdfSha256 {
+    const KDF_ID: u16 = 0x0001;
+
+    fn algorithm() -> hkdf::Algorithm {
+        hkdf::HKDF_SHA256
+    }
+}
+
+impl Kdf for HkdfSha384 {
+    const KDF_ID: u16 = 0x0002;
+
+    fn algorithm() -> hkdf::Algorithm {
+        hkdf::HKDF_SHA384
+    }
+}
+
+impl Kdf for HkdfSha512 {
+    const KDF_ID: u16 = 0x0003;
+
+    fn algorithm() -> hkdf::Algorithm {
+        hkdf::HKDF_SHA512
+    }
+}
+
+/// AEAD algorithms which can be used with HPKE.
+pub trait HpkeAead: AeadInPlace + AeadCore<NonceSize = U12, TagSize = U16> + KeyInit {
+    /// AEAD identifier
+    const AEAD_ID: u16;
+}
+
+impl HpkeAead for Aes128Gcm {
+    const AEAD_ID: u16 = 0x0001;
+}
+
+impl HpkeAead for Aes256Gcm {
+    const AEAD_ID: u16 = 0x0002;
+}
+
+impl HpkeAead for ChaCha20Poly1305 {
+    const AEAD_ID: u16 = 0x0003;
+}
+
+/// Set up a context for encrypting messages to the holder of `public_key`,
+/// returning the encapsulated key along with it.
+pub fn setup_sender<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    public_key: &[u8],
+    info: &[u8],
+) -> Result<(Vec<u8>, SenderContext<A>), HpkeError> {
+    let (enc, shared_secret) = encap::<K>(public_key)?;
+
+    #[cfg(feature = "zeroize")]
+    let shared_secret = Zeroizing::new(shared_secret);
+
+    let context = key_schedule::<K, F, A>(mode, &shared_secret[..], info)?;
+    Ok((enc, SenderContext(context)))
+}
+
+/// Set up a context for decrypting messages sent with the encapsulated key
+/// `enc`.
+///
+/// `private_key` must have the agreement algorithm of the KEM `K`.
+pub fn setup_receiver<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    private_key: &dyn PrivateKey,
+    enc: &[u8],
+    info: &[u8],
+) -> Result<ReceiverContext<A>, HpkeError> {
+    receiver_context::<K, F, A>(mode, private_key, enc, info).map(ReceiverContext)
+}
+
+/// Encrypt a single message to the holder of `public_key`, returning the
+/// encapsulated key and ciphertext.
+pub fn seal<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    public_key: &[u8],
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), HpkeError> {
+    let (enc, mut context) = setup_sender::<K, F, A>(mode, public_key, info)?;
+    let ciphertext = context.seal(aad, plaintext)?;
+    Ok((enc, ciphertext))
+}
+
+/// Decrypt a single message.
+pub fn open<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    private_key: &dyn PrivateKey,
+    enc: &[u8],
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, HpkeError> {
+    setup_receiver::<K, F, A>(mode, private_key, enc, info)?.open(aad, ciphertext)
+}
+
+/// Sender's encryption context
+pub struct SenderContext<A: HpkeAead>(Context<A>);
+
+impl<A: HpkeAead> SenderContext<A> {
+    /// Encrypt a message, returning the ciphertext with the tag appended.
+    pub fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let mut buffer = Vec::with_capacity(plaintext.len() + Tag::default().len());
+        buffer.extend_from_slice(plaintext);
+        let tag = self.seal_in_place_detached(aad, &mut buffer)?;
+        buffer.extend_from_slice(&tag);
+        Ok(buffer)
+    }
+
+    /// Encrypt a message in place, returning the tag.
+    pub fn seal_in_place_detached(
+        &mut self,
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, HpkeError> {
+        let nonce = self.0.next_nonce()?;
+        let tag = self
+            .0
+            .aead
+            .encrypt_in_place_detached(&nonce, aad, buffer)
+            .map_err(|_| HpkeError::Seal)?;
+
+        self.0.sequence_number += 1;
+        Ok(tag)
+    }
+
+    /// Export a secret of `out.len()` bytes bound to `exporter_context`.
+    pub fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), HpkeError> {
+        self.0.export(exporter_context, out)
+    }
+
+    /// Sequence number of the next message.
+    pub fn sequence_number(&self) -> u64 {
+        self.0.sequence_number
+    }
+}
+
+impl<A: HpkeAead> fmt::Debug for SenderContext<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SenderContext")
+            .field("sequence_number", &self.0.sequence_number)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Recipient's decryption context
+pub struct ReceiverContext<A: HpkeAead>(Context<A>);
+
+impl<A: HpkeAead> ReceiverContext<A> {
+    /// Decrypt a ciphertext with the tag appended.
+    pub fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let tag_offset = ciphertext
+            .len()
+            .checked_sub(Tag::default().len())
+            .ok_or(HpkeError::Open)?;
+
+        let (ciphertext, tag) = ciphertext.split_at(tag_offset);
+        let mut buffer = ciphertext.to_vec();
+        self.open_in_place_detached(aad, &mut buffer, GenericArray::from_slice(tag))?;
+        Ok(buffer)
+    }
+
+    /// Decrypt a message in place.
+    ///
+    /// The sequence number is only incremented if decryption succeeds.
+    pub fn open_in_place_detached(
+        &mut self,
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), HpkeError> {
+        let nonce = self.0.next_nonce()?;
+        self.0
+            .aead
+            .decrypt_in_place_detached(&nonce, aad, buffer, tag)
+            .map_err(|_| HpkeError::Open)?;
+
+        self.0.sequence_number += 1;
+        Ok(())
+    }
+
+    /// Export a secret of `out.len()` bytes bound to `exporter_context`.
+    pub fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), HpkeError> {
+        self.0.export(exporter_context, out)
+    }
+
+    /// Sequence number of the next message.
+    pub fn sequence_number(&self) -> u64 {
+        self.0.sequence_number
+    }
+}
+
+impl<A: HpkeAead> fmt::Debug for ReceiverContext<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReceiverContext")
+            .field("sequence_number", &self.0.sequence_number)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Encryption context shared by senders and recipients
+struct Context<A> {
+    aead: A,
+    base_nonce: [u8; 12],
+    exporter_secret: hkdf::Prk,
+    suite_id: [u8; SUITE_ID_LEN],
+    sequence_number: u64,
+}
+
+impl<A> Context<A> {
+    /// Compute the nonce for the current sequence number, failing if it
+    /// can't be incremented afterwards.
+    fn next_nonce(&self) -> Result<GenericArray<u8, U12>, HpkeError> {
+        if self.sequence_number == u64::MAX {
+            return Err(HpkeError::MessageLimitReached);
+        }
+
+        let mut nonce = GenericArray::from(self.base_nonce);
+
+        for (n, s) in nonce[4..]
+            .iter_mut()
+            .zip(self.sequence_number.to_be_bytes())
+        {
+            *n ^= s;
+        }
+
+        Ok(nonce)
+    }
+
+    fn export(&self, exporter_context: &[u8], out: &mut [u8]) -> Result<(), HpkeError> {
+        labeled_expand(
+            &self.exporter_secret,
+            &self.suite_id,
+            b"sec",
+            &[exporter_context],
+            out,
+        )
+    }
+}
+
+/// Generate an ephemeral key pair and encapsulate a shared secret to
+/// `public_key`.
+fn encap<K: Kem>(public_key: &[u8]) -> Result<(Vec<u8>, [u8; SHARED_SECRET_LEN]), HpkeError> {
+    let rng = SystemRandom::new();
+    let private_key = agreement::EphemeralPrivateKey::generate(K::agreement_algorithm(), &rng)
+        .map_err(|_| HpkeError::Rng)?;
+
+    let enc = private_key
+        .compute_public_key()
+        .map_err(|_| HpkeError::Rng)?
+        .as_ref()
+        .to_vec();
+
+    let peer_public_key = agreement::UnparsedPublicKey::new(K::agreement_algorithm(), public_key);
+    let shared_secret = agreement::agree_ephemeral(private_key, &peer_public_key, |dh| {
+        extract_and_expand::<K>(dh, &enc, public_key)
+    })
+    .map_err(|_| HpkeError::InvalidKey)?;
+
+    Ok((enc, shared_secret))
+}
+
+/// Decapsulate the shared secret from `enc`.
+fn decap<K: Kem>(
+    private_key: &dyn PrivateKey,
+    enc: &[u8],
+) -> Result<[u8; SHARED_SECRET_LEN], HpkeError> {
+    if private_key.algorithm() != K::agreement_algorithm()
+        || private_key.public_key().len() != K::PUBLIC_KEY_LEN
+        || enc.len() != K::PUBLIC_KEY_LEN
+    {
+        return Err(HpkeError::InvalidKey);
+    }
+
+    let mut dh = [0u8; SHARED_SECRET_LEN];
+    let result = private_key
+        .diffie_hellman(enc, &mut dh)
+        .map(|()| extract_and_expand::<K>(&dh, enc, private_key.public_key()));
+
+    #[cfg(feature = "zeroize")]
+    dh.zeroize();
+
+    result.map_err(|_| HpkeError::InvalidKey)
+}
+
+/// Derive the KEM shared secret from a Diffie-Hellman output.
+fn extract_and_expand<K: Kem>(
+    dh: &[u8],
+    enc: &[u8],
+    recipient_public_key: &[u8],
+) -> [u8; SHARED_SECRET_LEN] {
+    let suite_id = kem_suite_id::<K>();
+    let eae_prk = labeled_extract(hkdf::HKDF_SHA256, &suite_id, b"", b"eae_prk", dh);
+    let mut shared_secret = [0u8; SHARED_SECRET_LEN];
+
+    labeled_expand(
+        &hkdf::Prk::new_less_safe(hkdf::HKDF_SHA256, eae_prk.as_ref()),
+        &suite_id,
+        b"shared_secret",
+        &[enc, recipient_public_key],
+        &mut shared_secret,
+    )
+    .expect("shared secret should fit");
+
+    shared_secret
+}
+
+/// Decapsulate the shared secret and run the key schedule.
+fn receiver_context<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    private_key: &dyn PrivateKey,
+    enc: &[u8],
+    info: &[u8],
+) -> Result<Context<A>, HpkeError> {
+    let shared_secret = decap::<K>(private_key, enc)?;
+
+    #[cfg(feature = "zeroize")]
+    let shared_secret = Zeroizing::new(shared_secret);
+
+    key_schedule::<K, F, A>(mode, &shared_secret[..], info)
+}
+
+/// Derive the encryption context from the KEM shared secret.
+fn key_schedule<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    shared_secret: &[u8],
+    info: &[u8],
+) -> Result<Context<A>, HpkeError> {
+    let (psk, psk_id) = mode.psk()?;
+    let algorithm = F::algorithm();
+    let suite_id = hpke_suite_id::<K, F, A>();
+
+    let psk_id_hash = labeled_extract(algorithm, &suite_id, b"", b"psk_id_hash", psk_id);
+    let info_hash = labeled_extract(algorithm, &suite_id, b"", b"info_hash", info);
+    let context = [&[mode.id()][..], psk_id_hash.as_ref(), info_hash.as_ref()];
+
+    let secret = labeled_extract(algorithm, &suite_id, shared_secret, b"secret", psk);
+    let secret = hkdf::Prk::new_less_safe(algorithm, secret.as_ref());
+
+    let mut key = GenericArray::<u8, A::KeySize>::default();
+    let mut base_nonce = [0u8; 12];
+    let mut exporter_secret = [0u8; MAX_HASH_LEN];
+    let exporter_secret = &mut exporter_secret[..algorithm.len()];
+
+    let result = labeled_expand(&secret, &suite_id, b"key", &context, &mut key)
+        .and_then(|()| labeled_expand(&secret, &suite_id, b"base_nonce", &context, &mut base_nonce))
+        .and_then(|()| labeled_expand(&secret, &suite_id, b"exp", &context, exporter_secret))
+        .map(|()| Context {
+            aead: A::new(&key),
+            base_nonce,
+            exporter_secret: hkdf::Prk::new_less_safe(algorithm, exporter_secret),
+            suite_id,
+            sequence_number: 0,
+        });
+
+    #[cfg(feature = "zeroize")]
+    {
+        key.as_mut_slice().zeroize();
+        exporter_secret.zeroize();
+    }
+
+    result
+}
+
+/// `LabeledExtract(salt, label, ikm)`
+fn labeled_extract(
+    algorithm: hkdf::Algorithm,
+    suite_id: &[u8],
+    salt: &[u8],
+    label: &[u8],
+    ikm: &[u8],
+) -> hmac::Tag {
+    let key = hmac::Key::new(algorithm.hmac_algorithm(), salt);
+    let mut context = hmac::Context::with_key(&key);
+
+    for part in [VERSION_LABEL, suite_id, label, ikm] {
+        context.update(part);
+    }
+
+    context.sign()
+}
+
+/// `LabeledExpand(prk, label, info, L)` where `info` is the concatenation of
+/// the given parts and `L` is the length of `out`
+fn labeled_expand(
+    prk: &hkdf::Prk,
+    suite_id: &[u8],
+    label: &[u8],
+    info: &[&[u8]],
+    out: &mut [u8],
+) -> Result<(), HpkeError> {
+    let len = u16::try_from(out.len())
+        .map_err(|_| HpkeError::ExportTooLong)?
+        .to_be_bytes();
+
+    let mut labeled_info = Vec::with_capacity(4 + info.len());
+    labeled_info.extend_from_slice(&[&len[..], VERSION_LABEL, suite_id, label]);
+    labeled_info.extend_from_slice(info);
+
+    prk.expand(&labeled_info, OutputLen(out.len()))
+        .and_then(|okm| okm.fill(out))
+        .map_err(|_| HpkeError::ExportTooLong)
+}
+
+/// KEM suite ID: `"KEM" || kem_id`
+fn kem_suite_id<K: Kem>() -> [u8; 5] {
+    let id = K::KEM_ID.to_be_bytes();
+    [b'K', b'E', b'M', id[0], id[1]]
+}
+
+/// HPKE suite ID: `"HPKE" || kem_id || kdf_id || aead_id`
+fn hpke_suite_id<K: Kem, F: Kdf, A: HpkeAead>() -> [u8; SUITE_ID_LEN] {
+    let mut suite_id = [0u8; SUITE_ID_LEN];
+    suite_id[..4].copy_from_slice(b"HPKE");
+    suite_id[4..6].copy_from_slice(&K::KEM_ID.to_be_bytes());
+    suite_id[6..8].copy_from_slice(&F::KDF_ID.to_be_bytes());
+    suite_id[8..].copy_from_slice(&A::AEAD_ID.to_be_bytes());
+    suite_id
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::DhKemX25519HkdfSha256 {}
+    impl Sealed for super::DhKemP256HkdfSha256 {}
+}
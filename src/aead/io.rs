@@ -0,0 +1,368 @@
+//! [`std::io`] adapters for encrypting and decrypting streams
+//!
+//! [`EncryptingWriter`] and [`DecryptingReader`] cut a stream into
+//! fixed-size chunks which are individually encrypted with the
+//! [STREAM-BE32] construction, allowing arbitrarily large streams to be
+//! piped through [`io::copy`] in constant memory.
+//!
+//! Each chunk's nonce is derived from a random prefix, a big endian chunk
+//! counter, and a flag marking the final chunk, so reordering, truncation
+//! and extension of the stream are all detected.
+//!
+//! # Stream format
+//!
+//! ```text
+//! nonce prefix || chunk 0 || chunk 1 || ... || final chunk
+//! ```
+//!
+//! The nonce prefix is the AEAD nonce size less 5 bytes (i.e. 7 bytes for
+//! AES-GCM and ChaCha20Poly1305). Every chunk but the last holds exactly
+//! `chunk_size` bytes of plaintext followed by a tag, and the final chunk
+//! holds the remaining 0..=`chunk_size` bytes followed by a tag. Both sides
+//! must use the same chunk size.
+//!
+//! # Stream limits
+//!
+//! The nonce prefix is chosen at random for every stream, so a 7-byte prefix
+//! may repeat after roughly 2<sup>28</sup> streams under the same key by the
+//! birthday bound, which would reuse nonces. Keys should be rotated well
+//! before that many streams have been encrypted with them, e.g. by deriving
+//! a fresh key for every stream.
+//!
+//! [STREAM-BE32]: https://eprint.iacr.org/2015/189.pdf
+
+use super::{
+    stream::{DecryptorBE32, EncryptorBE32, NewStream, StreamBE32},
+    AeadCore, AeadInPlace, Error,
+};
+use aead::{
+    consts::U5,
+    generic_array::{typenum::Unsigned, ArrayLength, GenericArray},
+};
+use alloc::vec::Vec;
+use core::{mem, ops::Sub};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::{self, Read, Write};
+
+/// STREAM-BE32 nonce prefix: the AEAD nonce less 5 bytes
+type NoncePrefix<A> = GenericArray<u8, <<A as AeadCore>::NonceSize as Sub<U5>>::Output>;
+
+/// Default amount of plaintext per chunk: 64 KiB
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// [`Write`] adapter which encrypts everything written to it.
+///
+/// [`EncryptingWriter::finish`] must be called once all data has been
+/// written in order to emit the final chunk: a stream which is dropped
+/// without being finished will fail to decrypt.
+pub struct EncryptingWriter<A, W>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    /// STREAM encryptor
+    encryptor: EncryptorBE32<A>,
+
+    /// Inner writer
+    inner: W,
+
+    /// Nonce prefix, until it has been written out
+    header: Option<NoncePrefix<A>>,
+
+    /// Buffered plaintext of the current chunk
+    buffer: Vec<u8>,
+
+    /// Amount of plaintext per chunk
+    chunk_size: usize,
+}
+
+impl<A, W> EncryptingWriter<A, W>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    W: Write,
+{
+    /// Create a new encrypting writer using [`DEFAULT_CHUNK_SIZE`] and a
+    /// random nonce prefix.
+    pub fn new(aead: A, inner: W) -> Result<Self, Error> {
+        Self::with_chunk_size(aead, inner, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Create a new encrypting writer with the given chunk size and a
+    /// random nonce prefix.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_size` is zero.
+    pub fn with_chunk_size(aead: A, inner: W, chunk_size: usize) -> Result<Self, Error> {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+
+        let mut nonce = NoncePrefix::<A>::default();
+        SystemRandom::new().fill(&mut nonce).map_err(|_| Error)?;
+
+        Ok(Self {
+            encryptor: EncryptorBE32::from_stream_primitive(StreamBE32::from_aead(aead, &nonce)),
+            inner,
+            header: Some(nonce),
+            buffer: Vec::with_capacity(chunk_size + A::TagSize::USIZE),
+            chunk_size,
+        })
+    }
+}
+
+impl<A, W> EncryptingWriter<A, W>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    W: Write,
+{
+    /// Borrow the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Encrypt and write the final chunk, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.buffer.len() > self.chunk_size {
+            self.write_chunk()?;
+        }
+
+        self.write_header()?;
+        self.encryptor
+            .encrypt_last_in_place(b"", &mut self.buffer)
+            .map_err(invalid_data)?;
+        self.inner.write_all(&self.buffer)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Write the nonce prefix if it hasn't been written yet.
+    fn write_header(&mut self) -> io::Result<()> {
+        if let Some(nonce) = &self.header {
+            self.inner.write_all(nonce)?;
+            self.header = None;
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt and write the first `chunk_size` bytes of the buffer.
+    ///
+    /// Chunks are only emitted once more data follows them, so the final
+    /// chunk is always the one encrypted by [`EncryptingWriter::finish`].
+    fn write_chunk(&mut self) -> io::Result<()> {
+        self.write_header()?;
+
+        let rest = self.buffer.split_off(self.chunk_size);
+        self.encryptor
+            .encrypt_next_in_place(b"", &mut self.buffer)
+            .map_err(invalid_data)?;
+        self.inner.write_all(&self.buffer)?;
+
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&rest);
+        Ok(())
+    }
+}
+
+impl<A, W> Write for EncryptingWriter<A, W>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() > self.chunk_size {
+            self.write_chunk()?;
+        }
+
+        let len = buf.len().min(self.chunk_size + 1 - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    /// Flush the inner writer.
+    ///
+    /// Buffered plaintext of an incomplete chunk is not written out.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// [`Read`] adapter which decrypts a stream produced by [`EncryptingWriter`].
+///
+/// Plaintext is only returned once the chunk containing it has been
+/// authenticated. Reads return an [`io::ErrorKind::InvalidData`] error if
+/// any chunk fails to authenticate, including when the stream has been
+/// truncated, after which every subsequent read fails.
+pub struct DecryptingReader<A, R>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+{
+    /// STREAM decryptor, once the nonce prefix has been read
+    decryptor: Option<DecryptorBE32<A>>,
+
+    /// AEAD cipher, until the nonce prefix has been read
+    aead: Option<A>,
+
+    /// Inner reader
+    inner: R,
+
+    /// Decrypted plaintext of the current chunk
+    buffer: Vec<u8>,
+
+    /// Position of the next unread byte in the buffer
+    pos: usize,
+
+    /// Ciphertext read so far of the nonce prefix or next chunk, kept across
+    /// reads which fail with e.g. [`io::ErrorKind::WouldBlock`]
+    pending: Vec<u8>,
+
+    /// Has the final chunk been decrypted?
+    finished: bool,
+
+    /// Amount of plaintext per chunk
+    chunk_size: usize,
+}
+
+impl<A, R> DecryptingReader<A, R>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    R: Read,
+{
+    /// Create a new decrypting reader using [`DEFAULT_CHUNK_SIZE`].
+    pub fn new(aead: A, inner: R) -> Self {
+        Self::with_chunk_size(aead, inner, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Create a new decrypting reader with the given chunk size.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_size` is zero.
+    pub fn with_chunk_size(aead: A, inner: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+
+        Self {
+            decryptor: None,
+            aead: Some(aead),
+            inner,
+            buffer: Vec::with_capacity(chunk_size + A::TagSize::USIZE + 1),
+            pos: 0,
+            pending: Vec::with_capacity(chunk_size + A::TagSize::USIZE + 1),
+            finished: false,
+            chunk_size,
+        }
+    }
+
+    /// Borrow the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read and decrypt the next chunk into the buffer.
+    fn read_chunk(&mut self) -> io::Result<()> {
+        if self.aead.is_some() {
+            let prefix_len = <A::NonceSize as Sub<U5>>::Output::USIZE;
+            read_up_to(&mut self.inner, &mut self.pending, prefix_len)?;
+
+            if self.pending.len() < prefix_len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            let nonce = NoncePrefix::<A>::clone_from_slice(&self.pending);
+            self.pending.clear();
+            self.decryptor = self.aead.take().map(|aead| {
+                DecryptorBE32::from_stream_primitive(StreamBE32::from_aead(aead, &nonce))
+            });
+        }
+
+        // A chunk is the final one if nothing follows it, so the first byte
+        // of the next chunk is read ahead
+        let chunk_len = self.chunk_size + A::TagSize::USIZE;
+        read_up_to(&mut self.inner, &mut self.pending, chunk_len + 1)?;
+        let next = self.pending.get(chunk_len).copied();
+
+        mem::swap(&mut self.buffer, &mut self.pending);
+        self.buffer.truncate(chunk_len);
+        self.pending.clear();
+        self.pending.extend(next);
+        self.pos = 0;
+
+        let result = if next.is_none() {
+            self.finished = true;
+            self.decryptor
+                .take()
+                .ok_or(Error)
+                .and_then(|decryptor| decryptor.decrypt_last_in_place(b"", &mut self.buffer))
+        } else {
+            self.decryptor
+                .as_mut()
+                .ok_or(Error)
+                .and_then(|decryptor| decryptor.decrypt_next_in_place(b"", &mut self.buffer))
+        };
+
+        result.map_err(|error| {
+            // Poison the reader so subsequent reads also fail
+            self.decryptor = None;
+            self.finished = false;
+            self.buffer.clear();
+            self.pending.clear();
+            invalid_data(error)
+        })
+    }
+}
+
+impl<A, R> Read for DecryptingReader<A, R>
+where
+    A: AeadInPlace,
+    A::NonceSize: Sub<U5>,
+    <A::NonceSize as Sub<U5>>::Output: ArrayLength<u8>,
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.pos == self.buffer.len() {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.read_chunk()?;
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.pos);
+        buf[..len].copy_from_slice(&self.buffer[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Read until `buf` holds `len` bytes or the reader reaches EOF.
+///
+/// Bytes read before an error are kept in `buf`, so the read can be resumed.
+fn read_up_to(reader: &mut impl Read, buf: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    let remaining = len.saturating_sub(buf.len());
+    reader.take(remaining as u64).read_to_end(buf)?;
+    Ok(())
+}
+
+/// Map an AEAD error to an I/O error.
+fn invalid_data(error: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,978 @@
+//! JSON Web Encryption (JWE) as described in [RFC 7516]
+//!
+//! Supports the compact and JSON serializations with the following
+//! algorithms from [RFC 7518] and [RFC 8037]:
+//!
+//! - Key management (`alg`): `dir` and `ECDH-ES` with P-256, P-384 or X25519
+//!   ephemeral keys and the Concat KDF over SHA-256
+//! - Content encryption (`enc`): `A128GCM` and `A256GCM`
+//!
+//! The protected header is always authenticated as part of the AAD.
+//! Compression (`zip`) and critical header extensions (`crit`) aren't
+//! supported.
+//!
+//! Encryption to `ECDH-ES` recipients uses ephemeral keys from *ring*'s
+//! `agreement` module, and decryption uses a static [`PrivateKey`].
+//!
+//! [RFC 7516]: https://www.rfc-editor.org/rfc/rfc7516
+//! [RFC 7518]: https://www.rfc-editor.org/rfc/rfc7518
+//! [RFC 8037]: https://www.rfc-editor.org/rfc/rfc8037
+
+mod json;
+
+use self::json::{Object, Value};
+use super::{base64, AeadInPlace, Aes128Gcm, Aes256Gcm, KeyInit, Tag};
+use crate::{agreement::PrivateKey, digest::Sha256};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+use digest::{FixedOutput, Update};
+use ring::{
+    agreement,
+    rand::{SecureRandom, SystemRandom},
+};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of an initialization vector
+const IV_SIZE: usize = 12;
+
+/// Size of an authentication tag
+const TAG_SIZE: usize = 16;
+
+/// Maximum size of a content encryption key
+const MAX_KEY_SIZE: usize = 32;
+
+/// Maximum size of an `ECDH-ES` shared secret, i.e. a P-384 coordinate
+const MAX_SHARED_SECRET_LEN: usize = 48;
+
+/// Errors returned by JWE operations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum JweError {
+    /// JWE or its header is malformed
+    Malformed,
+
+    /// An algorithm, `zip` or `crit` header parameter isn't supported
+    Unsupported,
+
+    /// Key doesn't match the algorithm, or a public key is invalid
+    InvalidKey,
+
+    /// Encryption failed, e.g. because the message is too long
+    Encryption,
+
+    /// Decryption failed: the key is wrong or the JWE is corrupt
+    Decryption,
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for JweError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed JWE"),
+            Self::Unsupported => f.write_str("unsupported JWE algorithm or header parameter"),
+            Self::InvalidKey => f.write_str("invalid JWE key"),
+            Self::Encryption => f.write_str("JWE encryption failed"),
+            Self::Decryption => f.write_str("JWE decryption failed"),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for JweError {}
+
+/// Key management algorithm (`alg`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Algorithm {
+    /// `dir`: the content encryption key is shared
+    Dir,
+
+    /// `ECDH-ES`: the content encryption key is derived with ECDH
+    EcdhEs,
+}
+
+impl Algorithm {
+    /// Header parameter value
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dir => "dir",
+            Self::EcdhEs => "ECDH-ES",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, JweError> {
+        match name {
+            "dir" => Ok(Self::Dir),
+            "ECDH-ES" => Ok(Self::EcdhEs),
+            _ => Err(JweError::Unsupported),
+        }
+    }
+}
+
+/// Content encryption algorithm (`enc`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Encryption {
+    /// `A128GCM`: [`Aes128Gcm`]
+    A128Gcm,
+
+    /// `A256GCM`: [`Aes256Gcm`]
+    A256Gcm,
+}
+
+impl Encryption {
+    /// Header parameter value
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::A128Gcm => "A128GCM",
+            Self::A256Gcm => "A256GCM",
+        }
+    }
+
+    /// Size of the content encryption key
+    pub fn key_size(self) -> usize {
+        match self {
+            Self::A128Gcm => 16,
+            Self::A256Gcm => 32,
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, JweError> {
+        match name {
+            "A128GCM" => Ok(Self::A128Gcm),
+            "A256GCM" => Ok(Self::A256Gcm),
+            _ => Err(JweError::Unsupported),
+        }
+    }
+
+    fn encrypt(
+        self,
+        key: &[u8],
+        iv: &[u8; IV_SIZE],
+        aad: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, JweError> {
+        let result = match self {
+            Self::A128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|_| JweError::InvalidKey)?
+                .encrypt_in_place_detached(iv.into(), aad, buffer),
+            Self::A256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|_| JweError::InvalidKey)?
+                .encrypt_in_place_detached(iv.into(), aad, buffer),
+        };
+
+        result.map_err(|_| JweError::Encryption)
+    }
+
+    fn decrypt(
+        self,
+        key: &[u8],
+        iv: &[u8],
+        aad: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), JweError> {
+        let result = match self {
+            Self::A128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|_| JweError::InvalidKey)?
+                .decrypt_in_place_detached(iv.into(), aad, buffer, Tag::from_slice(tag)),
+            Self::A256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|_| JweError::InvalidKey)?
+                .decrypt_in_place_detached(iv.into(), aad, buffer, Tag::from_slice(tag)),
+        };
+
+        result.map_err(|_| JweError::Decryption)
+    }
+}
+
+/// Elliptic curve used with `ECDH-ES` (`crv`)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Curve {
+    /// NIST P-256
+    P256,
+
+    /// NIST P-384
+    P384,
+
+    /// X25519
+    X25519,
+}
+
+impl Curve {
+    /// Header parameter value
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::P256 => "P-256",
+            Self::P384 => "P-384",
+            Self::X25519 => "X25519",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, JweError> {
+        match name {
+            "P-256" => Ok(Self::P256),
+            "P-384" => Ok(Self::P384),
+            "X25519" => Ok(Self::X25519),
+            _ => Err(JweError::Unsupported),
+        }
+    }
+
+    /// JWK key type
+    fn key_type(self) -> &'static str {
+        match self {
+            Self::P256 | Self::P384 => "EC",
+            Self::X25519 => "OKP",
+        }
+    }
+
+    /// Size of a coordinate
+    fn coordinate_len(self) -> usize {
+        match self {
+            Self::P256 | Self::X25519 => 32,
+            Self::P384 => 48,
+        }
+    }
+
+    fn agreement_algorithm(self) -> &'static agreement::Algorithm {
+        match self {
+            Self::P256 => &agreement::ECDH_P256,
+            Self::P384 => &agreement::ECDH_P384,
+            Self::X25519 => &agreement::X25519,
+        }
+    }
+}
+
+/// Key used to encrypt to a recipient.
+#[derive(Copy, Clone)]
+pub enum Recipient<'a> {
+    /// `dir`: shared content encryption key
+    Direct(&'a [u8]),
+
+    /// `ECDH-ES`: recipient's public key, either an uncompressed SEC1 point
+    /// or a raw X25519 public key
+    EcdhEs(Curve, &'a [u8]),
+}
+
+impl fmt::Debug for Recipient<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Direct(_) => f.debug_struct("Direct").finish_non_exhaustive(),
+            Self::EcdhEs(curve, public_key) => f
+                .debug_tuple("EcdhEs")
+                .field(curve)
+                .field(public_key)
+                .finish(),
+        }
+    }
+}
+
+/// Key used to decrypt as a recipient.
+#[derive(Copy, Clone)]
+pub enum DecryptionKey<'a> {
+    /// `dir`: shared content encryption key
+    Direct(&'a [u8]),
+
+    /// `ECDH-ES`: recipient's private key
+    EcdhEs(&'a dyn PrivateKey),
+}
+
+/// Ephemeral public key (`epk`)
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct EphemeralKey {
+    curve: Curve,
+
+    /// Uncompressed SEC1 point or raw X25519 public key
+    public_key: Vec<u8>,
+}
+
+impl EphemeralKey {
+    fn from_jwk(jwk: &Object) -> Result<Self, JweError> {
+        let curve = Curve::from_name(member_str(jwk, "crv")?)?;
+
+        if member_str(jwk, "kty")? != curve.key_type() {
+            return Err(JweError::Malformed);
+        }
+
+        let x = decode_base64url(member_str(jwk, "x")?)?;
+
+        if x.len() != curve.coordinate_len() {
+            return Err(JweError::InvalidKey);
+        }
+
+        let public_key = match curve {
+            Curve::X25519 => x,
+            Curve::P256 | Curve::P384 => {
+                let y = decode_base64url(member_str(jwk, "y")?)?;
+
+                if y.len() != curve.coordinate_len() {
+                    return Err(JweError::InvalidKey);
+                }
+
+                [&[0x04], &x[..], &y[..]].concat()
+            }
+        };
+
+        Ok(Self { curve, public_key })
+    }
+
+    fn write_jwk(&self, out: &mut String) {
+        let (x, y) = match self.curve {
+            Curve::X25519 => (&self.public_key[..], &[][..]),
+            Curve::P256 | Curve::P384 => self.public_key[1..].split_at(self.curve.coordinate_len()),
+        };
+
+        write_member(out, "kty", self.curve.key_type());
+        out.push(',');
+        write_member(out, "crv", self.curve.name());
+        out.push(',');
+        write_member(out, "x", &encode_base64url(x));
+
+        if !y.is_empty() {
+            out.push(',');
+            write_member(out, "y", &encode_base64url(y));
+        }
+    }
+}
+
+/// JOSE header: the union of the protected, shared unprotected and
+/// per-recipient header parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Header {
+    alg: Algorithm,
+    enc: Encryption,
+    kid: Option<String>,
+    typ: Option<String>,
+    cty: Option<String>,
+    epk: Option<EphemeralKey>,
+    apu: Vec<u8>,
+    apv: Vec<u8>,
+}
+
+impl Header {
+    /// Key management algorithm (`alg`)
+    pub fn algorithm(&self) -> Algorithm {
+        self.alg
+    }
+
+    /// Content encryption algorithm (`enc`)
+    pub fn encryption(&self) -> Encryption {
+        self.enc
+    }
+
+    /// Key ID (`kid`)
+    pub fn key_id(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /// Type (`typ`)
+    pub fn media_type(&self) -> Option<&str> {
+        self.typ.as_deref()
+    }
+
+    /// Content type (`cty`)
+    pub fn content_type(&self) -> Option<&str> {
+        self.cty.as_deref()
+    }
+
+    /// Parse the header parameters.
+    fn from_parameters(parameters: &Object) -> Result<Self, JweError> {
+        if json::get(parameters, "zip").is_some() || json::get(parameters, "crit").is_some() {
+            return Err(JweError::Unsupported);
+        }
+
+        let optional_str = |name| {
+            json::get(parameters, name)
+                .map(|value| value.as_str().map(String::from))
+                .transpose()
+        };
+        let optional_base64url = |name| {
+            json::get(parameters, name)
+                .map(|value| decode_base64url(value.as_str()?))
+                .transpose()
+        };
+
+        let header = Self {
+            alg: Algorithm::from_name(member_str(parameters, "alg")?)?,
+            enc: Encryption::from_name(member_str(parameters, "enc")?)?,
+            kid: optional_str("kid")?,
+            typ: optional_str("typ")?,
+            cty: optional_str("cty")?,
+            epk: json::get(parameters, "epk")
+                .map(|epk| EphemeralKey::from_jwk(epk.as_object()?))
+                .transpose()?,
+            apu: optional_base64url("apu")?.unwrap_or_default(),
+            apv: optional_base64url("apv")?.unwrap_or_default(),
+        };
+
+        if header.alg == Algorithm::EcdhEs && header.epk.is_none() {
+            return Err(JweError::Malformed);
+        }
+
+        Ok(header)
+    }
+
+    /// Serialize the header as a JSON object.
+    fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        write_member(&mut out, "alg", self.alg.name());
+
+        if let Some(kid) = &self.kid {
+            out.push(',');
+            write_member(&mut out, "kid", kid);
+        }
+
+        out.push(',');
+        write_member(&mut out, "enc", self.enc.name());
+
+        for (name, value) in [("typ", &self.typ), ("cty", &self.cty)] {
+            if let Some(value) = value {
+                out.push(',');
+                write_member(&mut out, name, value);
+            }
+        }
+
+        if let Some(epk) = &self.epk {
+            out.push_str(",\"epk\":{");
+            epk.write_jwk(&mut out);
+            out.push('}');
+        }
+
+        for (name, value) in [("apu", &self.apu), ("apv", &self.apv)] {
+            if !value.is_empty() {
+                out.push(',');
+                write_member(&mut out, name, &encode_base64url(value));
+            }
+        }
+
+        out.push('}');
+        out
+    }
+
+    /// Derive the content encryption key.
+    fn content_encryption_key(
+        &self,
+        key: DecryptionKey<'_>,
+        encrypted_key: &[u8],
+    ) -> Result<Vec<u8>, JweError> {
+        // Neither `dir` nor `ECDH-ES` use an encrypted key
+        if !encrypted_key.is_empty() {
+            return Err(JweError::Malformed);
+        }
+
+        let cek = match (self.alg, key) {
+            (Algorithm::Dir, DecryptionKey::Direct(cek)) => cek.to_vec(),
+            (Algorithm::EcdhEs, DecryptionKey::EcdhEs(private_key)) => {
+                let epk = self.epk.as_ref().ok_or(JweError::Malformed)?;
+
+                if private_key.algorithm() != epk.curve.agreement_algorithm() {
+                    return Err(JweError::InvalidKey);
+                }
+
+                let mut shared_secret = [0u8; MAX_SHARED_SECRET_LEN];
+                let shared_secret = &mut shared_secret[..epk.curve.coordinate_len()];
+                let result = private_key
+                    .diffie_hellman(&epk.public_key, shared_secret)
+                    .map_err(|_| JweError::InvalidKey)
+                    .and_then(|()| {
+                        if shared_secret.iter().fold(0, |acc, b| acc | b) == 0 {
+                            return Err(JweError::InvalidKey);
+                        }
+
+                        Ok(concat_kdf(shared_secret, self.enc, &self.apu, &self.apv))
+                    });
+
+                #[cfg(feature = "zeroize")]
+                shared_secret.zeroize();
+
+                result?
+            }
+            _ => return Err(JweError::InvalidKey),
+        };
+
+        if cek.len() != self.enc.key_size() {
+            return Err(JweError::InvalidKey);
+        }
+
+        Ok(cek)
+    }
+}
+
+/// Encrypts messages to a single recipient.
+#[derive(Clone, Debug)]
+pub struct Encryptor<'a> {
+    recipient: Recipient<'a>,
+    enc: Encryption,
+    kid: Option<&'a str>,
+    typ: Option<&'a str>,
+    cty: Option<&'a str>,
+    apu: &'a [u8],
+    apv: &'a [u8],
+}
+
+impl<'a> Encryptor<'a> {
+    /// Create an encryptor for the given recipient and content encryption
+    /// algorithm.
+    pub fn new(recipient: Recipient<'a>, enc: Encryption) -> Self {
+        Self {
+            recipient,
+            enc,
+            kid: None,
+            typ: None,
+            cty: None,
+            apu: b"",
+            apv: b"",
+        }
+    }
+
+    /// Set the key ID (`kid`) header parameter.
+    pub fn with_key_id(mut self, kid: &'a str) -> Self {
+        self.kid = Some(kid);
+        self
+    }
+
+    /// Set the type (`typ`) header parameter.
+    pub fn with_media_type(mut self, typ: &'a str) -> Self {
+        self.typ = Some(typ);
+        self
+    }
+
+    /// Set the content type (`cty`) header parameter, e.g. `JWT` for nested
+    /// JWTs.
+    pub fn with_content_type(mut self, cty: &'a str) -> Self {
+        self.cty = Some(cty);
+        self
+    }
+
+    /// Set the `ECDH-ES` agreement PartyUInfo (`apu`) and PartyVInfo (`apv`).
+    pub fn with_party_info(mut self, apu: &'a [u8], apv: &'a [u8]) -> Self {
+        self.apu = apu;
+        self.apv = apv;
+        self
+    }
+
+    /// Encrypt a message using the compact serialization.
+    pub fn encrypt_compact(&self, plaintext: &[u8]) -> Result<String, JweError> {
+        let parts = self.encrypt(plaintext, None)?;
+        let mut out = parts.protected;
+
+        for part in [&[][..], &parts.iv, &parts.ciphertext, &parts.tag] {
+            out.push('.');
+            out.push_str(&encode_base64url(part));
+        }
+
+        Ok(out)
+    }
+
+    /// Encrypt a message using the flattened JSON serialization, with
+    /// optional additional authenticated data (`aad`).
+    pub fn encrypt_json(&self, plaintext: &[u8], aad: &[u8]) -> Result<String, JweError> {
+        let aad = if aad.is_empty() {
+            None
+        } else {
+            Some(encode_base64url(aad))
+        };
+
+        let parts = self.encrypt(plaintext, aad.as_deref())?;
+        let mut out = String::from("{");
+        write_member(&mut out, "protected", &parts.protected);
+
+        if let Some(aad) = &aad {
+            out.push(',');
+            write_member(&mut out, "aad", aad);
+        }
+
+        for (name, part) in [
+            ("iv", &parts.iv),
+            ("ciphertext", &parts.ciphertext),
+            ("tag", &parts.tag),
+        ] {
+            out.push(',');
+            write_member(&mut out, name, &encode_base64url(part));
+        }
+
+        out.push('}');
+        Ok(out)
+    }
+
+    fn encrypt(&self, plaintext: &[u8], aad: Option<&str>) -> Result<Parts, JweError> {
+        let rng = SystemRandom::new();
+        let mut header = Header {
+            alg: Algorithm::Dir,
+            enc: self.enc,
+            kid: self.kid.map(String::from),
+            typ: self.typ.map(String::from),
+            cty: self.cty.map(String::from),
+            epk: None,
+            apu: Vec::new(),
+            apv: Vec::new(),
+        };
+
+        let cek = match self.recipient {
+            Recipient::Direct(cek) => {
+                if cek.len() != self.enc.key_size() {
+                    return Err(JweError::InvalidKey);
+                }
+
+                cek.to_vec()
+            }
+            Recipient::EcdhEs(curve, public_key) => {
+                let algorithm = curve.agreement_algorithm();
+                let private_key = agreement::EphemeralPrivateKey::generate(algorithm, &rng)
+                    .map_err(|_| JweError::Rng)?;
+                let ephemeral_public_key = private_key
+                    .compute_public_key()
+                    .map_err(|_| JweError::Rng)?;
+
+                header.alg = Algorithm::EcdhEs;
+                header.epk = Some(EphemeralKey {
+                    curve,
+                    public_key: ephemeral_public_key.as_ref().to_vec(),
+                });
+                header.apu = self.apu.to_vec();
+                header.apv = self.apv.to_vec();
+
+                agreement::agree_ephemeral(
+                    private_key,
+                    &agreement::UnparsedPublicKey::new(algorithm, public_key),
+                    |shared_secret| {
+                        if shared_secret.iter().fold(0, |acc, b| acc | b) == 0 {
+                            return Err(JweError::InvalidKey);
+                        }
+
+                        Ok(concat_kdf(shared_secret, self.enc, self.apu, self.apv))
+                    },
+                )
+                .map_err(|_| JweError::InvalidKey)??
+            }
+        };
+
+        let protected = encode_base64url(header.to_json().as_bytes());
+        let mut iv = [0u8; IV_SIZE];
+        rng.fill(&mut iv).map_err(|_| JweError::Rng)?;
+
+        let mut ciphertext = plaintext.to_vec();
+        let tag = self.enc.encrypt(
+            &cek,
+            &iv,
+            &additional_data(&protected, aad),
+            &mut ciphertext,
+        )?;
+
+        Ok(Parts {
+            protected,
+            iv: iv.to_vec(),
+            ciphertext,
+            tag: tag.to_vec(),
+        })
+    }
+}
+
+/// Encrypted parts of a JWE
+struct Parts {
+    /// Encoded protected header
+    protected: String,
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+/// Decrypted JWE
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decrypted {
+    /// JOSE header
+    pub header: Header,
+
+    /// Decrypted content
+    pub plaintext: Vec<u8>,
+
+    /// Additional authenticated data (`aad`) of the JSON serialization
+    pub aad: Vec<u8>,
+}
+
+/// Decrypt a JWE in either the compact or the JSON serialization.
+///
+/// A JWE in the general JSON serialization is decrypted with the first
+/// recipient for which `key` succeeds.
+pub fn decrypt(key: DecryptionKey<'_>, jwe: &str) -> Result<Decrypted, JweError> {
+    let jwe = Jwe::parse(jwe)?;
+    let mut result = Err(JweError::Decryption);
+
+    for recipient in &jwe.recipients {
+        result = jwe.decrypt(key, recipient);
+
+        if result.is_ok() {
+            break;
+        }
+    }
+
+    result
+}
+
+/// Decode the JOSE header of a JWE without decrypting it, e.g. to select a
+/// key by its ID.
+///
+/// For the general JSON serialization, this is the header of the first
+/// recipient.
+pub fn decode_header(jwe: &str) -> Result<Header, JweError> {
+    let jwe = Jwe::parse(jwe)?;
+    let recipient = jwe.recipients.first().ok_or(JweError::Malformed)?;
+    Ok(recipient.header.clone())
+}
+
+/// Parsed JWE
+struct Jwe {
+    /// Encoded protected header
+    protected: String,
+
+    /// Encoded additional authenticated data
+    aad: Option<String>,
+
+    recipients: Vec<JweRecipient>,
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+/// Per-recipient part of a parsed JWE
+struct JweRecipient {
+    header: Header,
+    encrypted_key: Vec<u8>,
+}
+
+impl Jwe {
+    fn parse(jwe: &str) -> Result<Self, JweError> {
+        if jwe.trim_start().starts_with('{') {
+            Self::parse_json(jwe)
+        } else {
+            Self::parse_compact(jwe)
+        }
+    }
+
+    fn parse_compact(jwe: &str) -> Result<Self, JweError> {
+        let parts = jwe.split('.').collect::<Vec<_>>();
+
+        let [protected, encrypted_key, iv, ciphertext, tag] = parts[..] else {
+            return Err(JweError::Malformed);
+        };
+
+        let header = Header::from_parameters(&decode_protected(protected)?)?;
+
+        Self {
+            protected: String::from(protected),
+            aad: None,
+            recipients: alloc::vec![JweRecipient {
+                header,
+                encrypted_key: decode_base64url(encrypted_key)?,
+            }],
+            iv: decode_base64url(iv)?,
+            ciphertext: decode_base64url(ciphertext)?,
+            tag: decode_base64url(tag)?,
+        }
+        .check()
+    }
+
+    fn parse_json(jwe: &str) -> Result<Self, JweError> {
+        let value = json::parse(jwe)?;
+        let object = value.as_object()?;
+
+        let protected = match json::get(object, "protected") {
+            Some(value) => value.as_str()?,
+            None => "",
+        };
+
+        let mut shared = decode_protected(protected)?;
+
+        if let Some(unprotected) = json::get(object, "unprotected") {
+            merge_parameters(&mut shared, unprotected.as_object()?)?;
+        }
+
+        let recipients = match json::get(object, "recipients") {
+            Some(recipients) => {
+                if json::get(object, "header").is_some()
+                    || json::get(object, "encrypted_key").is_some()
+                {
+                    return Err(JweError::Malformed);
+                }
+
+                match recipients {
+                    Value::Array(recipients) if !recipients.is_empty() => recipients
+                        .iter()
+                        .map(|recipient| JweRecipient::from_json(&shared, recipient.as_object()?))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    _ => return Err(JweError::Malformed),
+                }
+            }
+            None => alloc::vec![JweRecipient::from_json(&shared, object)?],
+        };
+
+        let optional_part = |name| -> Result<Vec<u8>, JweError> {
+            json::get(object, name).map_or(Ok(Vec::new()), |v| decode_base64url(v.as_str()?))
+        };
+
+        let aad = match json::get(object, "aad") {
+            Some(aad) => {
+                let aad = aad.as_str()?;
+                decode_base64url(aad)?;
+                Some(String::from(aad))
+            }
+            None => None,
+        };
+
+        Self {
+            protected: String::from(protected),
+            aad,
+            recipients,
+            iv: optional_part("iv")?,
+            ciphertext: decode_base64url(member_str(object, "ciphertext")?)?,
+            tag: optional_part("tag")?,
+        }
+        .check()
+    }
+
+    fn check(self) -> Result<Self, JweError> {
+        if self.iv.len() != IV_SIZE || self.tag.len() != TAG_SIZE {
+            return Err(JweError::Malformed);
+        }
+
+        Ok(self)
+    }
+
+    fn decrypt(
+        &self,
+        key: DecryptionKey<'_>,
+        recipient: &JweRecipient,
+    ) -> Result<Decrypted, JweError> {
+        let header = &recipient.header;
+        let cek = header.content_encryption_key(key, &recipient.encrypted_key)?;
+        let aad = additional_data(&self.protected, self.aad.as_deref());
+
+        let mut plaintext = self.ciphertext.clone();
+        header
+            .enc
+            .decrypt(&cek, &self.iv, &aad, &mut plaintext, &self.tag)?;
+
+        Ok(Decrypted {
+            header: header.clone(),
+            plaintext,
+            aad: match &self.aad {
+                Some(aad) => decode_base64url(aad)?,
+                None => Vec::new(),
+            },
+        })
+    }
+}
+
+impl JweRecipient {
+    fn from_json(shared: &Object, recipient: &Object) -> Result<Self, JweError> {
+        let mut parameters = shared.clone();
+
+        if let Some(header) = json::get(recipient, "header") {
+            merge_parameters(&mut parameters, header.as_object()?)?;
+        }
+
+        let encrypted_key = match json::get(recipient, "encrypted_key") {
+            Some(encrypted_key) => decode_base64url(encrypted_key.as_str()?)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            header: Header::from_parameters(&parameters)?,
+            encrypted_key,
+        })
+    }
+}
+
+/// Decode an encoded protected header, which is empty if absent.
+fn decode_protected(protected: &str) -> Result<Object, JweError> {
+    if protected.is_empty() {
+        return Ok(Object::new());
+    }
+
+    let header =
+        String::from_utf8(decode_base64url(protected)?).map_err(|_| JweError::Malformed)?;
+
+    match json::parse(&header)? {
+        Value::Object(object) => Ok(object),
+        _ => Err(JweError::Malformed),
+    }
+}
+
+/// Add header parameters, which must be disjoint from the existing ones.
+fn merge_parameters(parameters: &mut Object, other: &Object) -> Result<(), JweError> {
+    for (name, value) in other {
+        if json::get(parameters, name).is_some() {
+            return Err(JweError::Malformed);
+        }
+
+        parameters.push((name.clone(), value.clone()));
+    }
+
+    Ok(())
+}
+
+/// Additional authenticated data: `ASCII(Encoded Protected Header)`, followed
+/// by `'.' || BASE64URL(JWE AAD)` if present.
+fn additional_data(protected: &str, aad: Option<&str>) -> Vec<u8> {
+    let mut out = Vec::from(protected.as_bytes());
+
+    if let Some(aad) = aad {
+        out.push(b'.');
+        out.extend_from_slice(aad.as_bytes());
+    }
+
+    out
+}
+
+/// Concat KDF from NIST SP 800-56A as profiled by RFC 7518 section 4.6.2,
+/// deriving a content encryption key for `enc`.
+fn concat_kdf(shared_secret: &[u8], enc: Encryption, apu: &[u8], apv: &[u8]) -> Vec<u8> {
+    let key_size = enc.key_size();
+    let algorithm_id = enc.name().as_bytes();
+    let key_data_len = (key_size as u32 * 8).to_be_bytes();
+    let mut out = [0u8; MAX_KEY_SIZE];
+
+    for (counter, chunk) in (1u32..).zip(out[..key_size].chunks_mut(32)) {
+        let mut hasher = Sha256::default();
+        hasher.update(&counter.to_be_bytes());
+        hasher.update(shared_secret);
+
+        for info in [algorithm_id, apu, apv] {
+            hasher.update(&(info.len() as u32).to_be_bytes());
+            hasher.update(info);
+        }
+
+        hasher.update(&key_data_len);
+        chunk.copy_from_slice(&hasher.finalize_fixed()[..chunk.len()]);
+    }
+
+    out[..key_size].to_vec()
+}
+
+/// Look up a string member of an object.
+fn member_str<'a>(object: &'a Object, name: &str) -> Result<&'a str, JweError> {
+    json::get(object, name).ok_or(JweError::Malformed)?.as_str()
+}
+
+/// Append `"name":"value"` to a JSON object.
+fn write_member(out: &mut String, name: &str, value: &str) {
+    json::write_string(out, name);
+    out.push(':');
+    json::write_string(out, value);
+}
+
+/// Encode as URL-safe base64 without padding.
+fn encode_base64url(data: &[u8]) -> String {
+    base64::encode(base64::URL_SAFE, data)
+}
+
+/// Decode URL-safe base64 without padding, rejecting non-canonical
+/// encodings.
+fn decode_base64url(encoded: &str) -> Result<Vec<u8>, JweError> {
+    base64::decode(base64::URL_SAFE, encoded.as_bytes()).ok_or(JweError::Malformed)
+}
@@ -0,0 +1,314 @@
+//! Minimal JSON support for JOSE headers and the JWE JSON serialization
+
+use super::JweError;
+use alloc::{string::String, vec::Vec};
+
+/// Maximum nesting depth of arrays and objects
+const MAX_DEPTH: usize = 16;
+
+/// JSON object: members in the order in which they appear
+pub(super) type Object = Vec<(String, Value)>;
+
+/// JSON value. Numbers are validated but their values are discarded, as
+/// none of the members understood by this crate are numeric.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) enum Value {
+    Null,
+    Bool(bool),
+    Number,
+    String(String),
+    Array(Vec<Value>),
+    Object(Object),
+}
+
+impl Value {
+    /// Get this value as a string.
+    pub(super) fn as_str(&self) -> Result<&str, JweError> {
+        match self {
+            Self::String(s) => Ok(s),
+            _ => Err(JweError::Malformed),
+        }
+    }
+
+    /// Get this value as an object.
+    pub(super) fn as_object(&self) -> Result<&Object, JweError> {
+        match self {
+            Self::Object(object) => Ok(object),
+            _ => Err(JweError::Malformed),
+        }
+    }
+}
+
+/// Look up a member of an object.
+pub(super) fn get<'a>(object: &'a Object, name: &str) -> Option<&'a Value> {
+    object.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+}
+
+/// Parse a JSON text, rejecting objects with duplicate member names.
+pub(super) fn parse(input: &str) -> Result<Value, JweError> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        pos: 0,
+    };
+
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+
+    if parser.pos != parser.input.len() {
+        return Err(JweError::Malformed);
+    }
+
+    Ok(value)
+}
+
+/// Append a string to `out` as a JSON string literal.
+pub(super) fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                const HEX: &[u8; 16] = b"0123456789abcdef";
+                let c = u32::from(c) as usize;
+                out.push_str("\\u00");
+                out.push(char::from(HEX[c >> 4]));
+                out.push(char::from(HEX[c & 0xf]));
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self, depth: usize) -> Result<Value, JweError> {
+        if depth > MAX_DEPTH {
+            return Err(JweError::Malformed);
+        }
+
+        self.skip_whitespace();
+
+        match self.peek()? {
+            b'{' => self.object(depth).map(Value::Object),
+            b'[' => self.array(depth).map(Value::Array),
+            b'"' => self.string().map(Value::String),
+            b't' => self.literal(b"true").map(|_| Value::Bool(true)),
+            b'f' => self.literal(b"false").map(|_| Value::Bool(false)),
+            b'n' => self.literal(b"null").map(|_| Value::Null),
+            b'-' | b'0'..=b'9' => self.number().map(|_| Value::Number),
+            _ => Err(JweError::Malformed),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Object, JweError> {
+        let mut object = Object::new();
+        self.expect(b'{')?;
+        self.skip_whitespace();
+
+        if self.peek()? == b'}' {
+            self.pos += 1;
+            return Ok(object);
+        }
+
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+
+            if get(&object, &name).is_some() {
+                return Err(JweError::Malformed);
+            }
+
+            self.skip_whitespace();
+            self.expect(b':')?;
+            let value = self.value(depth + 1)?;
+            object.push((name, value));
+
+            self.skip_whitespace();
+            match self.next()? {
+                b',' => continue,
+                b'}' => return Ok(object),
+                _ => return Err(JweError::Malformed),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Vec<Value>, JweError> {
+        let mut array = Vec::new();
+        self.expect(b'[')?;
+        self.skip_whitespace();
+
+        if self.peek()? == b']' {
+            self.pos += 1;
+            return Ok(array);
+        }
+
+        loop {
+            array.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+
+            match self.next()? {
+                b',' => continue,
+                b']' => return Ok(array),
+                _ => return Err(JweError::Malformed),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JweError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+
+        loop {
+            let start = self.pos;
+
+            while !matches!(self.peek()?, b'"' | b'\\' | 0x00..=0x1f) {
+                self.pos += 1;
+            }
+
+            // Input is a `str` and the delimiters are ASCII
+            out.push_str(core::str::from_utf8(&self.input[start..self.pos]).unwrap());
+
+            match self.next()? {
+                b'"' => return Ok(out),
+                b'\\' => out.push(self.escape()?),
+                _ => return Err(JweError::Malformed),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JweError> {
+        let c = match self.next()? {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.hex4()?;
+
+                let code_point = if (0xd800..0xdc00).contains(&high) {
+                    self.literal(b"\\u")?;
+                    let low = self.hex4()?;
+
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(JweError::Malformed);
+                    }
+
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+
+                char::from_u32(code_point).ok_or(JweError::Malformed)?
+            }
+            _ => return Err(JweError::Malformed),
+        };
+
+        Ok(c)
+    }
+
+    fn hex4(&mut self) -> Result<u32, JweError> {
+        let mut value = 0;
+
+        for _ in 0..4 {
+            let digit = char::from(self.next()?)
+                .to_digit(16)
+                .ok_or(JweError::Malformed)?;
+
+            value = (value << 4) | digit;
+        }
+
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<(), JweError> {
+        if self.peek()? == b'-' {
+            self.pos += 1;
+        }
+
+        match self.next()? {
+            b'0' => (),
+            b'1'..=b'9' => self.digits(),
+            _ => return Err(JweError::Malformed),
+        }
+
+        if self.input.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            self.expect_digit()?;
+            self.digits();
+        }
+
+        if matches!(self.input.get(self.pos), Some(b'e' | b'E')) {
+            self.pos += 1;
+
+            if matches!(self.input.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+
+            self.expect_digit()?;
+            self.digits();
+        }
+
+        Ok(())
+    }
+
+    fn expect_digit(&mut self) -> Result<(), JweError> {
+        match self.next()? {
+            b'0'..=b'9' => Ok(()),
+            _ => Err(JweError::Malformed),
+        }
+    }
+
+    fn digits(&mut self) {
+        while matches!(self.input.get(self.pos), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> Result<(), JweError> {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(JweError::Malformed)
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.input.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JweError> {
+        if self.next()? == byte {
+            Ok(())
+        } else {
+            Err(JweError::Malformed)
+        }
+    }
+
+    fn peek(&self) -> Result<u8, JweError> {
+        self.input.get(self.pos).copied().ok_or(JweError::Malformed)
+    }
+
+    fn next(&mut self) -> Result<u8, JweError> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Ok(byte)
+    }
+}
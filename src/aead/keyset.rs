@@ -0,0 +1,437 @@
+//! Keysets: collections of AEAD keys which support key rotation
+//!
+//! A [`Keyset`] holds several keys, each with a [`KeyId`] and a [`KeyStatus`].
+//! New messages are encrypted with the primary key, and every ciphertext is
+//! prefixed with the ID of the key that produced it, so messages encrypted
+//! with keys which have since been rotated out remain readable for as long as
+//! those keys are enabled.
+//!
+//! # Ciphertext format
+//!
+//! Ciphertexts use the same layout as Tink's `TINK` output prefix:
+//!
+//! ```text
+//! 0x01 || key ID (4 bytes, big endian) || nonce (12 bytes) || ciphertext || tag (16 bytes)
+//! ```
+//!
+//! Nonces are generated randomly for each message.
+//!
+//! # Serialization format
+//!
+//! [`Keyset::to_bytes`] produces the following binary encoding, where all
+//! integers are big endian:
+//!
+//! ```text
+//! magic "RCKS" || version (1 byte, 0x01) || key count (2 bytes)
+//!   || for each key:
+//!        key ID (4 bytes) || status (1 byte) || algorithm (2 bytes)
+//!        || key length (1 byte) || key
+//! ```
+//!
+//! Statuses are encoded as `0x01` (enabled), `0x02` (disabled) and
+//! `0x03` (primary), and algorithms with their [`AeadAlgorithm::id`].
+//! The encoding contains secret key material and must be stored accordingly.
+//! The key count limits keysets to [`MAX_KEYS`] keys.
+
+use super::{AeadAlgorithm, AeadInPlace, AnyAead, Error};
+use aead::generic_array::GenericArray;
+use alloc::vec::Vec;
+use core::fmt;
+use ring::rand::{SecureRandom, SystemRandom};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
+
+/// Key identifier
+pub type KeyId = u32;
+
+/// Serialized keyset, which is wiped when dropped if the `zeroize` feature
+/// is enabled
+#[cfg(feature = "zeroize")]
+pub type SerializedKeyset = Zeroizing<Vec<u8>>;
+
+/// Serialized keyset, which is wiped when dropped if the `zeroize` feature
+/// is enabled
+#[cfg(not(feature = "zeroize"))]
+pub type SerializedKeyset = Vec<u8>;
+
+/// Maximum number of keys in a keyset
+pub const MAX_KEYS: usize = u16::MAX as usize;
+
+/// Version byte which starts every ciphertext
+const CIPHERTEXT_VERSION: u8 = 0x01;
+
+/// Size of the ciphertext prefix: version and key ID
+const PREFIX_SIZE: usize = 5;
+
+/// Size of a nonce
+const NONCE_SIZE: usize = 12;
+
+/// Size of a tag
+const TAG_SIZE: usize = 16;
+
+/// Magic bytes which start a serialized keyset
+const MAGIC: &[u8; 4] = b"RCKS";
+
+/// Version of the serialization format
+const SERIALIZATION_VERSION: u8 = 0x01;
+
+/// Status of a key within a [`Keyset`].
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum KeyStatus {
+    /// Key can be used to decrypt
+    Enabled,
+
+    /// Key is retained but can't be used
+    Disabled,
+
+    /// Key is used to encrypt new messages, and can be used to decrypt.
+    ///
+    /// A keyset has at most one primary key.
+    Primary,
+}
+
+impl KeyStatus {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Enabled => 0x01,
+            Self::Disabled => 0x02,
+            Self::Primary => 0x03,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, Error> {
+        match byte {
+            0x01 => Ok(Self::Enabled),
+            0x02 => Ok(Self::Disabled),
+            0x03 => Ok(Self::Primary),
+            _ => Err(Error),
+        }
+    }
+}
+
+/// Key within a [`Keyset`]
+struct Entry {
+    id: KeyId,
+    status: KeyStatus,
+    key: Vec<u8>,
+    cipher: AnyAead,
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Entry {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// Information about a key within a [`Keyset`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct KeyInfo {
+    /// Key identifier
+    pub id: KeyId,
+
+    /// Key status
+    pub status: KeyStatus,
+
+    /// Key algorithm
+    pub algorithm: AeadAlgorithm,
+}
+
+/// Set of AEAD keys supporting rotation.
+#[derive(Default)]
+pub struct Keyset {
+    entries: Vec<Entry>,
+}
+
+impl Keyset {
+    /// Create a new empty keyset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate a new random key with a random unused ID and add it to the
+    /// keyset as [`KeyStatus::Enabled`], returning its ID.
+    ///
+    /// Make it the primary key with [`Keyset::set_primary`] once it has been
+    /// distributed to every party which needs to decrypt with it. Fails if
+    /// the keyset already has [`MAX_KEYS`] keys.
+    pub fn generate_key(&mut self, algorithm: AeadAlgorithm) -> Result<KeyId, Error> {
+        if self.entries.len() >= MAX_KEYS {
+            return Err(Error);
+        }
+
+        let rng = SystemRandom::new();
+        let mut key = alloc::vec![0u8; algorithm.key_size()];
+        rng.fill(&mut key).map_err(|_| Error)?;
+
+        #[cfg(feature = "zeroize")]
+        let key = Zeroizing::new(key);
+
+        let id = loop {
+            let mut id = [0u8; 4];
+            rng.fill(&mut id).map_err(|_| Error)?;
+            let id = KeyId::from_be_bytes(id);
+
+            if self.find(id).is_none() {
+                break id;
+            }
+        };
+
+        self.add_key(id, algorithm, &key, KeyStatus::Enabled)?;
+        Ok(id)
+    }
+
+    /// Add an existing key to the keyset.
+    ///
+    /// Fails if the ID is already in use, the key is the wrong size for the
+    /// algorithm, a primary key is added to a keyset which already has one,
+    /// or the keyset already has [`MAX_KEYS`] keys.
+    pub fn add_key(
+        &mut self,
+        id: KeyId,
+        algorithm: AeadAlgorithm,
+        key: &[u8],
+        status: KeyStatus,
+    ) -> Result<(), Error> {
+        if self.entries.len() >= MAX_KEYS
+            || self.find(id).is_some()
+            || (status == KeyStatus::Primary && self.primary_id().is_some())
+        {
+            return Err(Error);
+        }
+
+        self.entries.push(Entry {
+            id,
+            status,
+            key: key.to_vec(),
+            cipher: AnyAead::new(algorithm, key)?,
+        });
+
+        Ok(())
+    }
+
+    /// Make the given key the primary key. The previous primary key, if any,
+    /// remains enabled.
+    pub fn set_primary(&mut self, id: KeyId) -> Result<(), Error> {
+        self.find(id).ok_or(Error)?;
+
+        for entry in &mut self.entries {
+            if entry.id == id {
+                entry.status = KeyStatus::Primary;
+            } else if entry.status == KeyStatus::Primary {
+                entry.status = KeyStatus::Enabled;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enable a disabled key.
+    pub fn enable(&mut self, id: KeyId) -> Result<(), Error> {
+        let entry = self.find_mut(id).ok_or(Error)?;
+
+        if entry.status == KeyStatus::Disabled {
+            entry.status = KeyStatus::Enabled;
+        }
+
+        Ok(())
+    }
+
+    /// Disable a key. The primary key can't be disabled.
+    pub fn disable(&mut self, id: KeyId) -> Result<(), Error> {
+        let entry = self.find_mut(id).ok_or(Error)?;
+
+        if entry.status == KeyStatus::Primary {
+            return Err(Error);
+        }
+
+        entry.status = KeyStatus::Disabled;
+        Ok(())
+    }
+
+    /// Remove a key from the keyset. The primary key can't be removed.
+    pub fn remove(&mut self, id: KeyId) -> Result<(), Error> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or(Error)?;
+
+        if self.entries[index].status == KeyStatus::Primary {
+            return Err(Error);
+        }
+
+        self.entries.remove(index);
+        Ok(())
+    }
+
+    /// ID of the primary key, if there is one.
+    pub fn primary_id(&self) -> Option<KeyId> {
+        self.primary().map(|entry| entry.id)
+    }
+
+    /// Iterate over information about the keys in this keyset.
+    pub fn keys(&self) -> impl Iterator<Item = KeyInfo> + '_ {
+        self.entries.iter().map(|entry| KeyInfo {
+            id: entry.id,
+            status: entry.status,
+            algorithm: entry.cipher.algorithm(),
+        })
+    }
+
+    /// Encrypt a message with the primary key.
+    pub fn encrypt(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, Error> {
+        let entry = self.primary().ok_or(Error)?;
+
+        let mut out = Vec::with_capacity(PREFIX_SIZE + NONCE_SIZE + plaintext.len() + TAG_SIZE);
+        out.push(CIPHERTEXT_VERSION);
+        out.extend_from_slice(&entry.id.to_be_bytes());
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        SystemRandom::new().fill(&mut nonce).map_err(|_| Error)?;
+        out.extend_from_slice(&nonce);
+
+        let msg_start = out.len();
+        out.extend_from_slice(plaintext);
+
+        let tag = entry.cipher.encrypt_in_place_detached(
+            &nonce.into(),
+            associated_data,
+            &mut out[msg_start..],
+        )?;
+
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// Decrypt a message with the key identified by its prefix, which must be
+    /// enabled.
+    pub fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, Error> {
+        let id = Self::key_id(ciphertext)?;
+        let entry = self
+            .find(id)
+            .filter(|entry| entry.status != KeyStatus::Disabled)
+            .ok_or(Error)?;
+
+        let body = &ciphertext[PREFIX_SIZE..];
+
+        if body.len() < NONCE_SIZE + TAG_SIZE {
+            return Err(Error);
+        }
+
+        let (nonce, rest) = body.split_at(NONCE_SIZE);
+        let (msg, tag) = rest.split_at(rest.len() - TAG_SIZE);
+        let mut buffer = msg.to_vec();
+
+        entry.cipher.decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            associated_data,
+            &mut buffer,
+            GenericArray::from_slice(tag),
+        )?;
+
+        Ok(buffer)
+    }
+
+    /// Get the ID of the key which was used to produce a ciphertext.
+    pub fn key_id(ciphertext: &[u8]) -> Result<KeyId, Error> {
+        match ciphertext {
+            [CIPHERTEXT_VERSION, a, b, c, d, ..] => Ok(KeyId::from_be_bytes([*a, *b, *c, *d])),
+            _ => Err(Error),
+        }
+    }
+
+    /// Serialize this keyset, including its secret key material.
+    pub fn to_bytes(&self) -> SerializedKeyset {
+        let len = self
+            .entries
+            .iter()
+            .map(|entry| 8 + entry.key.len())
+            .sum::<usize>();
+
+        // Allocated upfront so that no copies of the keys are left behind
+        let mut out = SerializedKeyset::from(Vec::with_capacity(MAGIC.len() + 3 + len));
+        out.extend_from_slice(MAGIC);
+        out.push(SERIALIZATION_VERSION);
+        out.extend_from_slice(&(self.entries.len() as u16).to_be_bytes());
+
+        for entry in &self.entries {
+            out.extend_from_slice(&entry.id.to_be_bytes());
+            out.push(entry.status.to_byte());
+            out.extend_from_slice(&entry.cipher.algorithm().id().to_be_bytes());
+            out.push(entry.key.len() as u8);
+            out.extend_from_slice(&entry.key);
+        }
+
+        out
+    }
+
+    /// Deserialize a keyset produced by [`Keyset::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC || reader.take(1)? != [SERIALIZATION_VERSION] {
+            return Err(Error);
+        }
+
+        let count = reader.u16()?;
+        let mut keyset = Self::new();
+
+        for _ in 0..count {
+            let id = KeyId::from_be_bytes(reader.take(4)?.try_into().unwrap());
+            let status = KeyStatus::from_byte(reader.take(1)?[0])?;
+            let algorithm = AeadAlgorithm::from_id(reader.u16()?)?;
+            let key_len = reader.take(1)?[0];
+            let key = reader.take(key_len.into())?;
+            keyset.add_key(id, algorithm, key, status)?;
+        }
+
+        if reader.0.is_empty() {
+            Ok(keyset)
+        } else {
+            Err(Error)
+        }
+    }
+
+    fn primary(&self) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.status == KeyStatus::Primary)
+    }
+
+    fn find(&self, id: KeyId) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    fn find_mut(&mut self, id: KeyId) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|entry| entry.id == id)
+    }
+}
+
+impl fmt::Debug for Keyset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyset")
+            .field("primary_id", &self.primary_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reader for the serialization format
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error);
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,345 @@
+//! Invocation limits for AEAD keys
+//!
+//! The security of AES-GCM and ChaCha20Poly1305 degrades with the number of
+//! messages protected by a key and the number of forgery attempts made against
+//! it. [`Limited`] counts encryptions, bytes, and failed decryptions, and
+//! refuses to keep using a key once its limits are reached. A rekey callback
+//! can obtain a fresh key when the confidentiality limit is reached, but the
+//! integrity limit always fails, as [RFC 9001 Section 6.6] requires the
+//! connection to be closed.
+//!
+//! Default limits are the confidentiality and integrity limits from
+//! [RFC 9001 Section 6.6], which assume messages of at most 2^16 bytes, so
+//! they also cap the message size at 2^16 bytes. Protocols which use larger
+//! messages must use custom [`Limits`] with fewer encryptions and failed
+//! decryptions.
+//!
+//! [RFC 9001 Section 6.6]: https://www.rfc-editor.org/rfc/rfc9001#section-6.6
+
+use super::{
+    AeadAlgorithm, AeadInPlace, Aes128Gcm, Aes256Gcm, AnyAead, Buffer, ChaCha20Poly1305,
+    XChaCha20Poly1305,
+};
+use aead::{generic_array::GenericArray, Nonce};
+use core::fmt;
+
+/// Maximum message size assumed by the default limits
+const DEFAULT_MAX_MESSAGE_SIZE: u64 = 1 << 16;
+
+/// Usage limits for a single key.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// Maximum number of messages which may be encrypted
+    pub max_encryptions: u64,
+
+    /// Maximum number of failed decryptions which may be tolerated
+    pub max_failed_decryptions: u64,
+
+    /// Maximum size of a single message in bytes
+    pub max_message_size: u64,
+}
+
+impl Limits {
+    /// Limits for AES-GCM: 2^23 encryptions and 2^52 failed decryptions of
+    /// messages of up to 2^16 bytes.
+    pub const AES_GCM: Self = Self {
+        max_encryptions: 1 << 23,
+        max_failed_decryptions: 1 << 52,
+        max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+    };
+
+    /// Limits for ChaCha20Poly1305: 2^62 encryptions and 2^36 failed
+    /// decryptions of messages of up to 2^16 bytes.
+    pub const CHACHA20_POLY1305: Self = Self {
+        max_encryptions: 1 << 62,
+        max_failed_decryptions: 1 << 36,
+        max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+    };
+
+    /// Get the default limits for the given algorithm.
+    pub fn for_algorithm(algorithm: AeadAlgorithm) -> Self {
+        match algorithm {
+            AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm => Self::AES_GCM,
+            AeadAlgorithm::ChaCha20Poly1305 => Self::CHACHA20_POLY1305,
+        }
+    }
+}
+
+/// Ciphers with known invocation limits.
+pub trait InvocationLimits {
+    /// Get the default limits for this cipher.
+    fn limits(&self) -> Limits;
+}
+
+impl InvocationLimits for Aes128Gcm {
+    fn limits(&self) -> Limits {
+        Limits::AES_GCM
+    }
+}
+
+impl InvocationLimits for Aes256Gcm {
+    fn limits(&self) -> Limits {
+        Limits::AES_GCM
+    }
+}
+
+impl InvocationLimits for ChaCha20Poly1305 {
+    fn limits(&self) -> Limits {
+        Limits::CHACHA20_POLY1305
+    }
+}
+
+impl InvocationLimits for XChaCha20Poly1305 {
+    fn limits(&self) -> Limits {
+        Limits::CHACHA20_POLY1305
+    }
+}
+
+impl InvocationLimits for AnyAead {
+    fn limits(&self) -> Limits {
+        Limits::for_algorithm(self.algorithm())
+    }
+}
+
+/// Usage of a key so far.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Usage {
+    /// Number of messages encrypted
+    pub encryptions: u64,
+
+    /// Number of plaintext bytes encrypted
+    pub bytes_encrypted: u64,
+
+    /// Number of decryptions which failed to authenticate
+    pub failed_decryptions: u64,
+}
+
+/// Errors returned by [`Limited`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LimitError {
+    /// Encryption or decryption failed
+    Aead,
+
+    /// Key has encrypted the maximum number of messages
+    ConfidentialityLimit,
+
+    /// Key has reached the maximum number of failed decryptions
+    IntegrityLimit,
+
+    /// Message exceeds the maximum size for the algorithm
+    MessageTooLarge,
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Aead => "AEAD error",
+            Self::ConfidentialityLimit => "AEAD key confidentiality limit reached",
+            Self::IntegrityLimit => "AEAD key integrity limit reached",
+            Self::MessageTooLarge => "message too large for AEAD algorithm",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LimitError {}
+
+impl From<aead::Error> for LimitError {
+    fn from(_: aead::Error) -> LimitError {
+        LimitError::Aead
+    }
+}
+
+impl From<LimitError> for aead::Error {
+    fn from(_: LimitError) -> aead::Error {
+        aead::Error
+    }
+}
+
+/// AEAD cipher which tracks its usage against a set of [`Limits`].
+///
+/// When the confidentiality limit is reached, the rekey callback (if any) is
+/// invoked to obtain a new cipher and the usage counters are reset. Without a
+/// callback, encryption fails with [`LimitError::ConfidentialityLimit`] and the
+/// cipher must be replaced with [`Limited::rekey`].
+///
+/// Reaching the integrity limit makes every decryption fail with
+/// [`LimitError::IntegrityLimit`], as rekeying only one side would stop the
+/// peer's messages from decrypting. The key should be discarded, e.g. by
+/// closing the connection.
+pub struct Limited<A, F = fn() -> A> {
+    aead: A,
+    limits: Limits,
+    usage: Usage,
+    rekey: Option<F>,
+}
+
+impl<A> Limited<A>
+where
+    A: AeadInPlace + InvocationLimits,
+{
+    /// Wrap a cipher, applying its default limits.
+    pub fn new(aead: A) -> Self {
+        let limits = aead.limits();
+        Self::with_limits(aead, limits)
+    }
+}
+
+impl<A> Limited<A>
+where
+    A: AeadInPlace,
+{
+    /// Wrap a cipher, applying custom limits.
+    pub fn with_limits(aead: A, limits: Limits) -> Self {
+        Self {
+            aead,
+            limits,
+            usage: Usage::default(),
+            rekey: None,
+        }
+    }
+}
+
+impl<A, F> Limited<A, F>
+where
+    A: AeadInPlace,
+    F: FnMut() -> A,
+{
+    /// Wrap a cipher, invoking `rekey` to obtain a new one whenever a limit
+    /// is reached.
+    pub fn with_rekey(aead: A, limits: Limits, rekey: F) -> Self {
+        Self {
+            aead,
+            limits,
+            usage: Usage::default(),
+            rekey: Some(rekey),
+        }
+    }
+
+    /// Get the limits which are being enforced.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Get the usage of the current key.
+    pub fn usage(&self) -> &Usage {
+        &self.usage
+    }
+
+    /// Replace the cipher with one using a fresh key, resetting the usage
+    /// counters.
+    pub fn rekey(&mut self, aead: A) {
+        self.aead = aead;
+        self.usage = Usage::default();
+    }
+
+    /// Encrypt the given buffer in place, returning the tag.
+    pub fn encrypt_in_place_detached(
+        &mut self,
+        nonce: &Nonce<A>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<GenericArray<u8, A::TagSize>, LimitError> {
+        self.check_encryption(buffer.len())?;
+        let tag = self
+            .aead
+            .encrypt_in_place_detached(nonce, associated_data, buffer)?;
+        self.record_encryption(buffer.len());
+        Ok(tag)
+    }
+
+    /// Encrypt the given buffer in place, appending the tag.
+    pub fn encrypt_in_place(
+        &mut self,
+        nonce: &Nonce<A>,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), LimitError> {
+        let len = buffer.len();
+        self.check_encryption(len)?;
+        self.aead.encrypt_in_place(nonce, associated_data, buffer)?;
+        self.record_encryption(len);
+        Ok(())
+    }
+
+    /// Decrypt the given buffer in place, checking it against the given tag.
+    pub fn decrypt_in_place_detached(
+        &mut self,
+        nonce: &Nonce<A>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &GenericArray<u8, A::TagSize>,
+    ) -> Result<(), LimitError> {
+        self.check_decryption()?;
+        let result = self
+            .aead
+            .decrypt_in_place_detached(nonce, associated_data, buffer, tag);
+        self.record_decryption(result)
+    }
+
+    /// Decrypt the given buffer in place, removing the trailing tag.
+    pub fn decrypt_in_place(
+        &mut self,
+        nonce: &Nonce<A>,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), LimitError> {
+        self.check_decryption()?;
+        let result = self.aead.decrypt_in_place(nonce, associated_data, buffer);
+        self.record_decryption(result)
+    }
+
+    /// Ensure a message of the given size can be encrypted, rekeying if needed
+    fn check_encryption(&mut self, len: usize) -> Result<(), LimitError> {
+        if len as u64 > self.limits.max_message_size {
+            return Err(LimitError::MessageTooLarge);
+        }
+
+        if self.usage.encryptions >= self.limits.max_encryptions {
+            self.try_rekey(LimitError::ConfidentialityLimit)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ensure a message can be decrypted
+    fn check_decryption(&self) -> Result<(), LimitError> {
+        if self.usage.failed_decryptions >= self.limits.max_failed_decryptions {
+            return Err(LimitError::IntegrityLimit);
+        }
+
+        Ok(())
+    }
+
+    fn record_encryption(&mut self, len: usize) {
+        self.usage.encryptions += 1;
+        self.usage.bytes_encrypted = self.usage.bytes_encrypted.saturating_add(len as u64);
+    }
+
+    fn record_decryption(&mut self, result: Result<(), aead::Error>) -> Result<(), LimitError> {
+        if result.is_err() {
+            self.usage.failed_decryptions += 1;
+        }
+
+        Ok(result?)
+    }
+
+    /// Invoke the rekey callback, or fail with the given error if there isn't
+    /// one
+    fn try_rekey(&mut self, error: LimitError) -> Result<(), LimitError> {
+        let aead = self.rekey.as_mut().map(|rekey| rekey()).ok_or(error)?;
+        self.rekey(aead);
+        Ok(())
+    }
+}
+
+impl<A: fmt::Debug, F> fmt::Debug for Limited<A, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Limited")
+            .field("aead", &self.aead)
+            .field("limits", &self.limits)
+            .field("usage", &self.usage)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,913 @@
+//! Noise Protocol Framework
+//!
+//! Implements revision 34 of the [Noise specification] with:
+//!
+//! - DH function: 25519
+//! - Cipher functions: `ChaChaPoly` ([`ChaCha20Poly1305`]) and `AESGCM`
+//!   ([`Aes256Gcm`])
+//! - Hash functions: `SHA256` ([`Sha256`]) and `SHA512` ([`Sha512`]), with
+//!   HMAC from *ring*
+//! - The fundamental interactive [`HandshakePattern`]s, without PSKs
+//!
+//! A [`HandshakeState`] runs the handshake and is then turned into a
+//! [`TransportState`] for exchanging transport messages.
+//!
+//! # X25519 keys
+//!
+//! Static keys are [`X25519`] [`PrivateKey`]s, as described in
+//! [`crate::agreement`]. Ephemeral keys used in a single Diffie-Hellman
+//! operation (the initiator's in `NN`, `XN`, `KN` and `IN`, and the
+//! responder's in `NN`, `NK` and `NX`) are generated by *ring*; in the other
+//! patterns, the party must provide a [`Dh`] which generates its ephemeral
+//! private keys.
+//!
+//! [Noise specification]: https://noiseprotocol.org/noise.html
+
+mod patterns;
+
+pub use self::patterns::HandshakePattern;
+
+use self::patterns::{Token, Tokens};
+use super::{AeadCore, AeadInPlace, Aes256Gcm, ChaCha20Poly1305, KeyInit, KeySizeUser, Tag};
+use crate::{
+    agreement::{PrivateKey, X25519},
+    digest::{Sha256, Sha512},
+};
+use aead::{
+    consts::{U12, U16, U32},
+    generic_array::GenericArray,
+};
+use alloc::{boxed::Box, format, vec::Vec};
+use core::{fmt, marker::PhantomData};
+use ring::{agreement, hmac, rand::SystemRandom};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
+
+/// Maximum size of a Noise message
+pub const MAX_MESSAGE_LEN: usize = 65535;
+
+/// Size of cipher keys
+pub const CIPHER_KEY_LEN: usize = 32;
+
+/// Size of X25519 keys and Diffie-Hellman outputs (`DHLEN`)
+pub const DH_LEN: usize = 32;
+
+/// Size of authentication tags
+const TAG_LEN: usize = 16;
+
+/// Maximum size of a hash output (SHA-512)
+const MAX_HASH_LEN: usize = 64;
+
+/// Errors returned by Noise operations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum NoiseError {
+    /// A static key, or a [`Dh`] for ephemeral keys, required by the
+    /// handshake pattern wasn't provided
+    MissingKey,
+
+    /// A public key is invalid, a private key isn't an [`X25519`] key, or
+    /// Diffie-Hellman failed or produced the all-zero value
+    InvalidKey,
+
+    /// The operation isn't allowed in the current state, e.g. a handshake
+    /// message was written out of turn or after an earlier failure
+    InvalidState,
+
+    /// A message is longer than [`MAX_MESSAGE_LEN`]
+    MessageTooLong,
+
+    /// A handshake message is too short
+    Malformed,
+
+    /// Decryption failed
+    Decryption,
+
+    /// The nonce can't be incremented any further
+    NonceExhausted,
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for NoiseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingKey => f.write_str("key required by Noise pattern is missing"),
+            Self::InvalidKey => f.write_str("invalid Noise public key"),
+            Self::InvalidState => f.write_str("invalid Noise state for this operation"),
+            Self::MessageTooLong => f.write_str("Noise message too long"),
+            Self::Malformed => f.write_str("malformed Noise handshake message"),
+            Self::Decryption => f.write_str("Noise decryption failed"),
+            Self::NonceExhausted => f.write_str("Noise nonce exhausted"),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NoiseError {}
+
+/// Generator of ephemeral X25519 key pairs (`GENERATE_KEYPAIR`).
+pub trait Dh: Send + Sync {
+    /// Generate a new [`X25519`] private key.
+    fn generate_key_pair(&mut self) -> Result<Box<dyn PrivateKey + Send + Sync>, NoiseError>;
+}
+
+/// Cipher functions.
+pub trait NoiseCipher:
+    AeadInPlace
+    + AeadCore<NonceSize = U12, TagSize = U16>
+    + KeyInit
+    + KeySizeUser<KeySize = U32>
+    + sealed::Sealed
+{
+    /// Name of the cipher as used in protocol names
+    const NAME: &'static str;
+
+    /// Encode a nonce counter.
+    fn nonce(n: u64) -> GenericArray<u8, U12>;
+}
+
+impl NoiseCipher for ChaCha20Poly1305 {
+    const NAME: &'static str = "ChaChaPoly";
+
+    fn nonce(n: u64) -> GenericArray<u8, U12> {
+        let mut nonce = GenericArray::default();
+        nonce[4..].copy_from_slice(&n.to_le_bytes());
+        nonce
+    }
+}
+
+impl NoiseCipher for Aes256Gcm {
+    const NAME: &'static str = "AESGCM";
+
+    fn nonce(n: u64) -> GenericArray<u8, U12> {
+        let mut nonce = GenericArray::default();
+        nonce[4..].copy_from_slice(&n.to_be_bytes());
+        nonce
+    }
+}
+
+/// Hash functions.
+pub trait NoiseHash: sealed::Sealed {
+    /// Name of the hash function as used in protocol names
+    const NAME: &'static str;
+
+    /// Size of the hash output
+    const HASH_LEN: usize;
+
+    /// *ring* digest algorithm
+    fn digest_algorithm() -> &'static ring::digest::Algorithm;
+
+    /// *ring* HMAC algorithm
+    fn hmac_algorithm() -> hmac::Algorithm;
+}
+
+impl NoiseHash for Sha256 {
+    const NAME: &'static str = "SHA256";
+    const HASH_LEN: usize = 32;
+
+    fn digest_algorithm() -> &'static ring::digest::Algorithm {
+        &ring::digest::SHA256
+    }
+
+    fn hmac_algorithm() -> hmac::Algorithm {
+        hmac::HMAC_SHA256
+    }
+}
+
+impl NoiseHash for Sha512 {
+    const NAME: &'static str = "SHA512";
+    const HASH_LEN: usize = 64;
+
+    fn digest_algorithm() -> &'static ring::digest::Algorithm {
+        &ring::digest::SHA512
+    }
+
+    fn hmac_algorithm() -> hmac::Algorithm {
+        hmac::HMAC_SHA512
+    }
+}
+
+/// Role of a party in a handshake
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Role {
+    /// The party sending the first handshake message
+    Initiator,
+
+    /// The party receiving the first handshake message
+    Responder,
+}
+
+/// Cipher key and nonce (`CipherState`)
+pub struct CipherState<C> {
+    cipher: Option<C>,
+    n: u64,
+}
+
+impl<C: NoiseCipher> CipherState<C> {
+    /// Create a cipher state with the given key and a zero nonce
+    /// (`InitializeKey`).
+    pub fn new(key: [u8; CIPHER_KEY_LEN]) -> Self {
+        #[cfg(feature = "zeroize")]
+        let key = Zeroizing::new(key);
+
+        Self {
+            cipher: Some(C::new(GenericArray::from_slice(&key[..]))),
+            n: 0,
+        }
+    }
+
+    /// Create a cipher state without a key, which passes messages through
+    /// unencrypted.
+    fn empty() -> Self {
+        Self { cipher: None, n: 0 }
+    }
+
+    /// Does this cipher state have a key?
+    pub fn has_key(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// Nonce used for the next message.
+    pub fn nonce(&self) -> u64 {
+        self.n
+    }
+
+    /// Set the nonce used for the next message (`SetNonce`), e.g. for
+    /// out-of-order transport messages.
+    pub fn set_nonce(&mut self, n: u64) {
+        self.n = n;
+    }
+
+    /// Encrypt a message, returning the ciphertext with the tag appended
+    /// (`EncryptWithAd`).
+    pub fn encrypt_with_ad(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let mut buffer = Vec::with_capacity(plaintext.len() + TAG_LEN);
+        buffer.extend_from_slice(plaintext);
+
+        if let Some(cipher) = &self.cipher {
+            let nonce = self.next_nonce()?;
+            let tag = cipher
+                .encrypt_in_place_detached(&C::nonce(nonce), ad, &mut buffer)
+                .map_err(|_| NoiseError::MessageTooLong)?;
+
+            buffer.extend_from_slice(&tag);
+            self.n += 1;
+        }
+
+        Ok(buffer)
+    }
+
+    /// Decrypt a ciphertext with the tag appended (`DecryptWithAd`).
+    ///
+    /// The nonce is only incremented if decryption succeeds.
+    pub fn decrypt_with_ad(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let Some(cipher) = &self.cipher else {
+            return Ok(ciphertext.to_vec());
+        };
+
+        let nonce = self.next_nonce()?;
+        let tag_offset = ciphertext
+            .len()
+            .checked_sub(TAG_LEN)
+            .ok_or(NoiseError::Decryption)?;
+
+        let (ciphertext, tag) = ciphertext.split_at(tag_offset);
+        let mut buffer = ciphertext.to_vec();
+        cipher
+            .decrypt_in_place_detached(&C::nonce(nonce), ad, &mut buffer, Tag::from_slice(tag))
+            .map_err(|_| NoiseError::Decryption)?;
+
+        self.n += 1;
+        Ok(buffer)
+    }
+
+    /// Replace the key with one derived from it (`Rekey`), without changing
+    /// the nonce.
+    pub fn rekey(&mut self) {
+        if let Some(cipher) = &self.cipher {
+            // The first 32 bytes of the encryption of zeros with the maximum
+            // nonce, which is reserved for this purpose
+            let mut key = [0u8; CIPHER_KEY_LEN];
+            cipher
+                .encrypt_in_place_detached(&C::nonce(u64::MAX), &[], &mut key)
+                .expect("key should be encryptable");
+
+            self.cipher = Some(C::new(GenericArray::from_slice(&key)));
+
+            #[cfg(feature = "zeroize")]
+            key.zeroize();
+        }
+    }
+
+    /// Get the current nonce, failing if it's reserved.
+    fn next_nonce(&self) -> Result<u64, NoiseError> {
+        if self.n == u64::MAX {
+            return Err(NoiseError::NonceExhausted);
+        }
+
+        Ok(self.n)
+    }
+}
+
+impl<C> fmt::Debug for CipherState<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CipherState")
+            .field("has_key", &self.cipher.is_some())
+            .field("nonce", &self.n)
+            .finish()
+    }
+}
+
+/// Chaining key, handshake hash and cipher state of a handshake
+/// (`SymmetricState`)
+pub struct SymmetricState<C, H> {
+    cipher: CipherState<C>,
+    ck: [u8; MAX_HASH_LEN],
+    h: [u8; MAX_HASH_LEN],
+    hash: PhantomData<H>,
+}
+
+impl<C: NoiseCipher, H: NoiseHash> SymmetricState<C, H> {
+    /// Initialize the state from a protocol name (`InitializeSymmetric`).
+    pub fn new(protocol_name: &str) -> Self {
+        let protocol_name = protocol_name.as_bytes();
+        let mut h = [0u8; MAX_HASH_LEN];
+
+        if protocol_name.len() <= H::HASH_LEN {
+            h[..protocol_name.len()].copy_from_slice(protocol_name);
+        } else {
+            h[..H::HASH_LEN].copy_from_slice(
+                ring::digest::digest(H::digest_algorithm(), protocol_name).as_ref(),
+            );
+        }
+
+        Self {
+            cipher: CipherState::empty(),
+            ck: h,
+            h,
+            hash: PhantomData,
+        }
+    }
+
+    /// Mix input key material into the chaining key and replace the cipher
+    /// key (`MixKey`).
+    pub fn mix_key(&mut self, input_key_material: &[u8]) {
+        let outputs = hkdf::<H, 2>(&self.ck[..H::HASH_LEN], input_key_material);
+
+        #[cfg(feature = "zeroize")]
+        let outputs = Zeroizing::new(outputs);
+
+        self.ck = outputs[0];
+        self.cipher = CipherState::new(cipher_key(&outputs[1]));
+    }
+
+    /// Mix data into the handshake hash (`MixHash`).
+    pub fn mix_hash(&mut self, data: &[u8]) {
+        let mut context = ring::digest::Context::new(H::digest_algorithm());
+        context.update(self.handshake_hash());
+        context.update(data);
+        self.h[..H::HASH_LEN].copy_from_slice(context.finish().as_ref());
+    }
+
+    /// Mix input key material into the chaining key, the handshake hash and
+    /// the cipher key (`MixKeyAndHash`).
+    pub fn mix_key_and_hash(&mut self, input_key_material: &[u8]) {
+        let outputs = hkdf::<H, 3>(&self.ck[..H::HASH_LEN], input_key_material);
+
+        #[cfg(feature = "zeroize")]
+        let outputs = Zeroizing::new(outputs);
+
+        self.ck = outputs[0];
+        self.mix_hash(&outputs[1][..H::HASH_LEN]);
+        self.cipher = CipherState::new(cipher_key(&outputs[2]));
+    }
+
+    /// Current handshake hash (`GetHandshakeHash`).
+    pub fn handshake_hash(&self) -> &[u8] {
+        &self.h[..H::HASH_LEN]
+    }
+
+    /// Does the cipher state have a key?
+    pub fn has_key(&self) -> bool {
+        self.cipher.has_key()
+    }
+
+    /// Encrypt a message with the handshake hash as associated data and mix
+    /// the ciphertext into the hash (`EncryptAndHash`).
+    pub fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let ciphertext = self
+            .cipher
+            .encrypt_with_ad(&self.h[..H::HASH_LEN], plaintext)?;
+
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    /// Decrypt a message with the handshake hash as associated data and mix
+    /// the ciphertext into the hash (`DecryptAndHash`).
+    pub fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let plaintext = self
+            .cipher
+            .decrypt_with_ad(&self.h[..H::HASH_LEN], ciphertext)?;
+
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// Derive the cipher states for transport messages sent by the initiator
+    /// and by the responder (`Split`).
+    pub fn split(&self) -> (CipherState<C>, CipherState<C>) {
+        let outputs = hkdf::<H, 2>(&self.ck[..H::HASH_LEN], &[]);
+
+        #[cfg(feature = "zeroize")]
+        let outputs = Zeroizing::new(outputs);
+
+        (
+            CipherState::new(cipher_key(&outputs[0])),
+            CipherState::new(cipher_key(&outputs[1])),
+        )
+    }
+}
+
+impl<C, H> fmt::Debug for SymmetricState<C, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SymmetricState")
+            .field("cipher", &self.cipher)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C, H> Drop for SymmetricState<C, H> {
+    fn drop(&mut self) {
+        self.ck.zeroize();
+    }
+}
+
+/// State of one party during a handshake (`HandshakeState`)
+pub struct HandshakeState<C, H> {
+    symmetric: SymmetricState<C, H>,
+    tokens: Tokens,
+    initiator: bool,
+    dh: Option<Box<dyn Dh>>,
+    s: Option<Box<dyn PrivateKey + Send + Sync>>,
+    e: Option<Ephemeral>,
+    rs: Option<[u8; DH_LEN]>,
+    re: Option<[u8; DH_LEN]>,
+    message_index: usize,
+    failed: bool,
+}
+
+impl<C: NoiseCipher, H: NoiseHash> HandshakeState<C, H> {
+    /// Initialize a handshake (`Initialize`).
+    ///
+    /// `local_static` and `remote_static` are required if the pattern uses a
+    /// static key of this party, or has a pre-message with the other party's
+    /// static key, respectively. `dh` is required if the pattern uses this
+    /// party's ephemeral key in more than one Diffie-Hellman operation, and
+    /// otherwise replaces *ring* for generating it.
+    pub fn new(
+        pattern: HandshakePattern,
+        role: Role,
+        prologue: &[u8],
+        local_static: Option<Box<dyn PrivateKey + Send + Sync>>,
+        remote_static: Option<&[u8]>,
+        dh: Option<Box<dyn Dh>>,
+    ) -> Result<Self, NoiseError> {
+        let initiator = role == Role::Initiator;
+        let tokens = pattern.tokens();
+
+        if (tokens.needs_local_static(initiator) && local_static.is_none())
+            || (tokens.needs_remote_static(initiator) && remote_static.is_none())
+            || (tokens.ephemeral_uses(initiator) > 1 && dh.is_none())
+        {
+            return Err(NoiseError::MissingKey);
+        }
+
+        if let Some(s) = &local_static {
+            check_private_key(s.as_ref())?;
+        }
+
+        let rs: Option<[u8; DH_LEN]> = remote_static
+            .map(|key| key.try_into().map_err(|_| NoiseError::InvalidKey))
+            .transpose()?;
+
+        let protocol_name = format!("Noise_{}_25519_{}_{}", pattern.name(), C::NAME, H::NAME);
+
+        let mut symmetric = SymmetricState::new(&protocol_name);
+        symmetric.mix_hash(prologue);
+
+        // Pre-messages of the fundamental patterns only contain static keys
+        for (pre_message, local) in [
+            (tokens.initiator_pre, initiator),
+            (tokens.responder_pre, !initiator),
+        ] {
+            if pre_message.contains(&Token::S) {
+                let public_key = match (local, &local_static, &rs) {
+                    (true, Some(s), _) => s.public_key(),
+                    (false, _, Some(rs)) => &rs[..],
+                    _ => return Err(NoiseError::MissingKey),
+                };
+
+                symmetric.mix_hash(public_key);
+            }
+        }
+
+        Ok(Self {
+            symmetric,
+            tokens,
+            initiator,
+            dh,
+            s: local_static,
+            e: None,
+            rs,
+            re: None,
+            message_index: 0,
+            failed: false,
+        })
+    }
+
+    /// Write the next handshake message with the given payload
+    /// (`WriteMessage`).
+    ///
+    /// After an error, the handshake can't be continued.
+    pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        if !self.is_my_turn() {
+            return Err(NoiseError::InvalidState);
+        }
+
+        let result = self.write_tokens(payload);
+        self.failed = result.is_err();
+        result
+    }
+
+    /// Read the next handshake message, returning its payload
+    /// (`ReadMessage`).
+    ///
+    /// After an error, the handshake can't be continued.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        if self.failed || self.is_finished() || self.is_my_turn() {
+            return Err(NoiseError::InvalidState);
+        }
+
+        let result = self.read_tokens(message);
+        self.failed = result.is_err();
+        result
+    }
+
+    /// Is it this party's turn to write a handshake message?
+    pub fn is_my_turn(&self) -> bool {
+        !self.failed && !self.is_finished() && (self.message_index % 2 == 0) == self.initiator
+    }
+
+    /// Have all handshake messages been written and read?
+    pub fn is_finished(&self) -> bool {
+        self.message_index == self.tokens.messages.len()
+    }
+
+    /// Current handshake hash.
+    ///
+    /// Once the handshake has finished, this uniquely identifies the session
+    /// and can be used for channel binding.
+    pub fn handshake_hash(&self) -> &[u8] {
+        self.symmetric.handshake_hash()
+    }
+
+    /// The other party's static public key, if known.
+    pub fn remote_static(&self) -> Option<&[u8; DH_LEN]> {
+        self.rs.as_ref()
+    }
+
+    /// Finish the handshake, deriving the cipher states for transport
+    /// messages.
+    pub fn into_transport(self) -> Result<TransportState<C>, NoiseError> {
+        if self.failed || !self.is_finished() {
+            return Err(NoiseError::InvalidState);
+        }
+
+        let (initiator_cipher, responder_cipher) = self.symmetric.split();
+        let (send, receive) = if self.initiator {
+            (initiator_cipher, responder_cipher)
+        } else {
+            (responder_cipher, initiator_cipher)
+        };
+
+        Ok(TransportState {
+            send,
+            receive,
+            handshake_hash: self.symmetric.handshake_hash().to_vec(),
+            remote_static: self.rs,
+        })
+    }
+
+    fn write_tokens(&mut self, payload: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        let mut message = Vec::new();
+
+        for &token in self.tokens.messages[self.message_index] {
+            match token {
+                Token::E => {
+                    let e = match &mut self.dh {
+                        Some(dh) => {
+                            let private_key = dh.generate_key_pair()?;
+                            check_private_key(private_key.as_ref())?;
+                            Ephemeral::PrivateKey(private_key)
+                        }
+                        None => Ephemeral::generate()?,
+                    };
+
+                    let public_key = self.e.insert(e).public_key();
+                    message.extend_from_slice(public_key);
+                    self.symmetric.mix_hash(public_key);
+                }
+                Token::S => {
+                    let s = self.s.as_ref().ok_or(NoiseError::MissingKey)?;
+                    message.extend(self.symmetric.encrypt_and_hash(s.public_key())?);
+                }
+                dh => self.mix_dh(dh)?,
+            }
+        }
+
+        message.extend(self.symmetric.encrypt_and_hash(payload)?);
+
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(NoiseError::MessageTooLong);
+        }
+
+        self.message_index += 1;
+        Ok(message)
+    }
+
+    fn read_tokens(&mut self, mut message: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(NoiseError::MessageTooLong);
+        }
+
+        for &token in self.tokens.messages[self.message_index] {
+            match token {
+                Token::E => {
+                    let re = take(&mut message, DH_LEN)?;
+                    self.symmetric.mix_hash(re);
+                    self.re = Some(re.try_into().expect("ephemeral key size"));
+                }
+                Token::S => {
+                    let len = if self.symmetric.has_key() {
+                        DH_LEN + TAG_LEN
+                    } else {
+                        DH_LEN
+                    };
+
+                    let rs = self.symmetric.decrypt_and_hash(take(&mut message, len)?)?;
+                    self.rs = Some(rs.as_slice().try_into().expect("static key size"));
+                }
+                dh => self.mix_dh(dh)?,
+            }
+        }
+
+        let payload = self.symmetric.decrypt_and_hash(message)?;
+        self.message_index += 1;
+        Ok(payload)
+    }
+
+    /// Perform the Diffie-Hellman operation of a token and mix the result
+    /// into the chaining key.
+    fn mix_dh(&mut self, token: Token) -> Result<(), NoiseError> {
+        // Whether this party's key, and the other party's key, is ephemeral
+        let (local_ephemeral, remote_ephemeral) = match token {
+            Token::Ee => (true, true),
+            Token::Es => (self.initiator, !self.initiator),
+            Token::Se => (!self.initiator, self.initiator),
+            Token::Ss => (false, false),
+            Token::E | Token::S => unreachable!("not a Diffie-Hellman token"),
+        };
+
+        let remote = if remote_ephemeral { self.re } else { self.rs };
+        let remote = remote.ok_or(NoiseError::InvalidState)?;
+
+        let shared_secret = if local_ephemeral {
+            let e = self.e.as_mut().ok_or(NoiseError::InvalidState)?;
+            e.diffie_hellman(&remote)?
+        } else {
+            let s = self.s.as_ref().ok_or(NoiseError::MissingKey)?;
+            diffie_hellman(s.as_ref(), &remote)?
+        };
+
+        #[cfg(feature = "zeroize")]
+        let shared_secret = Zeroizing::new(shared_secret);
+
+        self.symmetric.mix_key(&shared_secret[..]);
+        Ok(())
+    }
+}
+
+impl<C, H> fmt::Debug for HandshakeState<C, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandshakeState")
+            .field("initiator", &self.initiator)
+            .field("message_index", &self.message_index)
+            .field("failed", &self.failed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Cipher states for transport messages after a handshake
+pub struct TransportState<C> {
+    send: CipherState<C>,
+    receive: CipherState<C>,
+    handshake_hash: Vec<u8>,
+    remote_static: Option<[u8; DH_LEN]>,
+}
+
+impl<C: NoiseCipher> TransportState<C> {
+    /// Encrypt a transport message.
+    pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        if payload.len() > MAX_MESSAGE_LEN - TAG_LEN {
+            return Err(NoiseError::MessageTooLong);
+        }
+
+        self.send.encrypt_with_ad(&[], payload)
+    }
+
+    /// Decrypt a transport message.
+    ///
+    /// Messages must be read in the order in which they were written.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(NoiseError::MessageTooLong);
+        }
+
+        self.receive.decrypt_with_ad(&[], message)
+    }
+
+    /// Rekey the cipher state for sent messages.
+    pub fn rekey_outgoing(&mut self) {
+        self.send.rekey();
+    }
+
+    /// Rekey the cipher state for received messages.
+    pub fn rekey_incoming(&mut self) {
+        self.receive.rekey();
+    }
+
+    /// Handshake hash at the end of the handshake.
+    pub fn handshake_hash(&self) -> &[u8] {
+        &self.handshake_hash
+    }
+
+    /// The other party's static public key, if the pattern has one.
+    pub fn remote_static(&self) -> Option<&[u8; DH_LEN]> {
+        self.remote_static.as_ref()
+    }
+
+    /// Split into the cipher states for sent and received messages.
+    pub fn into_cipher_states(self) -> (CipherState<C>, CipherState<C>) {
+        (self.send, self.receive)
+    }
+}
+
+impl<C> fmt::Debug for TransportState<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransportState")
+            .field("send", &self.send)
+            .field("receive", &self.receive)
+            .field("remote_static", &self.remote_static)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Local ephemeral key pair
+enum Ephemeral {
+    /// *ring* private key, which is consumed by its single Diffie-Hellman
+    /// operation, and its public key
+    Ring(Option<agreement::EphemeralPrivateKey>, [u8; DH_LEN]),
+
+    /// Private key generated by a [`Dh`]
+    PrivateKey(Box<dyn PrivateKey + Send + Sync>),
+}
+
+impl Ephemeral {
+    /// Generate a *ring* key pair, which can be used in a single
+    /// Diffie-Hellman operation.
+    fn generate() -> Result<Self, NoiseError> {
+        let private_key =
+            agreement::EphemeralPrivateKey::generate(&agreement::X25519, &SystemRandom::new())
+                .map_err(|_| NoiseError::Rng)?;
+
+        let public_key = private_key
+            .compute_public_key()
+            .map_err(|_| NoiseError::Rng)?
+            .as_ref()
+            .try_into()
+            .expect("X25519 public key size");
+
+        Ok(Self::Ring(Some(private_key), public_key))
+    }
+
+    fn public_key(&self) -> &[u8] {
+        match self {
+            Self::Ring(_, public_key) => public_key,
+            Self::PrivateKey(private_key) => private_key.public_key(),
+        }
+    }
+
+    fn diffie_hellman(&mut self, public_key: &[u8; DH_LEN]) -> Result<[u8; DH_LEN], NoiseError> {
+        match self {
+            Self::Ring(private_key, _) => {
+                let private_key = private_key.take().ok_or(NoiseError::InvalidState)?;
+                let public_key = agreement::UnparsedPublicKey::new(&agreement::X25519, public_key);
+
+                agreement::agree_ephemeral(private_key, &public_key, |shared_secret| {
+                    let mut out = [0u8; DH_LEN];
+                    out.copy_from_slice(shared_secret);
+                    out
+                })
+                .map_err(|_| NoiseError::InvalidKey)
+            }
+            Self::PrivateKey(private_key) => diffie_hellman(private_key.as_ref(), public_key),
+        }
+    }
+}
+
+/// Check that a private key is an X25519 key.
+fn check_private_key(private_key: &dyn PrivateKey) -> Result<(), NoiseError> {
+    if private_key.algorithm() != &X25519 || private_key.public_key().len() != DH_LEN {
+        return Err(NoiseError::InvalidKey);
+    }
+
+    Ok(())
+}
+
+/// Compute the shared secret of a private key (`DH`), rejecting the all-zero
+/// output of a low-order public key as *ring* does.
+fn diffie_hellman(
+    private_key: &dyn PrivateKey,
+    public_key: &[u8; DH_LEN],
+) -> Result<[u8; DH_LEN], NoiseError> {
+    let mut shared_secret = [0u8; DH_LEN];
+    private_key
+        .diffie_hellman(public_key, &mut shared_secret)
+        .map_err(|_| NoiseError::InvalidKey)?;
+
+    if shared_secret.iter().fold(0, |acc, b| acc | b) == 0 {
+        return Err(NoiseError::InvalidKey);
+    }
+
+    Ok(shared_secret)
+}
+
+/// `HKDF` as defined by Noise, returning `N` outputs of `H::HASH_LEN` bytes.
+fn hkdf<H: NoiseHash, const N: usize>(
+    chaining_key: &[u8],
+    input_key_material: &[u8],
+) -> [[u8; MAX_HASH_LEN]; N] {
+    let temp_key = hmac::sign(
+        &hmac::Key::new(H::hmac_algorithm(), chaining_key),
+        input_key_material,
+    );
+
+    let temp_key = hmac::Key::new(H::hmac_algorithm(), temp_key.as_ref());
+    let mut outputs = [[0u8; MAX_HASH_LEN]; N];
+
+    for i in 0..N {
+        let mut context = hmac::Context::with_key(&temp_key);
+
+        if i > 0 {
+            context.update(&outputs[i - 1][..H::HASH_LEN]);
+        }
+
+        context.update(&[i as u8 + 1]);
+        outputs[i][..H::HASH_LEN].copy_from_slice(context.sign().as_ref());
+    }
+
+    outputs
+}
+
+/// Truncate an `HKDF` output to a cipher key.
+fn cipher_key(output: &[u8; MAX_HASH_LEN]) -> [u8; CIPHER_KEY_LEN] {
+    output[..CIPHER_KEY_LEN]
+        .try_into()
+        .expect("hash output should be at least the cipher key size")
+}
+
+/// Take `len` bytes from the front of a message.
+fn take<'a>(message: &mut &'a [u8], len: usize) -> Result<&'a [u8], NoiseError> {
+    if message.len() < len {
+        return Err(NoiseError::Malformed);
+    }
+
+    let (taken, rest) = message.split_at(len);
+    *message = rest;
+    Ok(taken)
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::ChaCha20Poly1305 {}
+    impl Sealed for super::Aes256Gcm {}
+    impl Sealed for crate::digest::Sha256 {}
+    impl Sealed for crate::digest::Sha512 {}
+}
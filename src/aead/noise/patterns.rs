@@ -0,0 +1,153 @@
+//! Fundamental interactive handshake patterns (Noise specification section 7.4)
+
+/// Handshake pattern token
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) enum Token {
+    E,
+    S,
+    Ee,
+    Es,
+    Se,
+    Ss,
+}
+
+use self::Token::*;
+
+/// Tokens of a handshake pattern
+pub(super) struct Tokens {
+    /// Pre-message of the initiator
+    pub(super) initiator_pre: &'static [Token],
+
+    /// Pre-message of the responder
+    pub(super) responder_pre: &'static [Token],
+
+    /// Messages, alternating between the initiator and the responder
+    pub(super) messages: &'static [&'static [Token]],
+}
+
+/// Handshake patterns.
+///
+/// The first letter describes the initiator's static key and the second the
+/// responder's: `N` for no static key, `K` for a key known to the other party
+/// in advance, `X` for a key transmitted during the handshake and `I` for a
+/// key transmitted immediately in the first message.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+#[allow(clippy::upper_case_acronyms)]
+pub enum HandshakePattern {
+    /// `-> e` `<- e, ee`
+    NN,
+    /// `<- s` `...` `-> e, es` `<- e, ee`
+    NK,
+    /// `-> e` `<- e, ee, s, es`
+    NX,
+    /// `-> e` `<- e, ee` `-> s, se`
+    XN,
+    /// `<- s` `...` `-> e, es` `<- e, ee` `-> s, se`
+    XK,
+    /// `-> e` `<- e, ee, s, es` `-> s, se`
+    XX,
+    /// `-> s` `...` `-> e` `<- e, ee, se`
+    KN,
+    /// `-> s` `<- s` `...` `-> e, es, ss` `<- e, ee, se`
+    KK,
+    /// `-> s` `...` `-> e` `<- e, ee, se, s, es`
+    KX,
+    /// `-> e, s` `<- e, ee, se`
+    IN,
+    /// `<- s` `...` `-> e, es, s, ss` `<- e, ee, se`
+    IK,
+    /// `-> e, s` `<- e, ee, se, s, es`
+    IX,
+}
+
+impl HandshakePattern {
+    /// Name of the pattern as used in protocol names
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NN => "NN",
+            Self::NK => "NK",
+            Self::NX => "NX",
+            Self::XN => "XN",
+            Self::XK => "XK",
+            Self::XX => "XX",
+            Self::KN => "KN",
+            Self::KK => "KK",
+            Self::KX => "KX",
+            Self::IN => "IN",
+            Self::IK => "IK",
+            Self::IX => "IX",
+        }
+    }
+
+    pub(super) fn tokens(self) -> Tokens {
+        let (initiator_pre, responder_pre, messages): (_, _, &[&[Token]]) = match self {
+            Self::NN => (&[][..], &[][..], &[&[E], &[E, Ee]]),
+            Self::NK => (&[], &[S], &[&[E, Es], &[E, Ee]]),
+            Self::NX => (&[], &[], &[&[E], &[E, Ee, S, Es]]),
+            Self::XN => (&[], &[], &[&[E], &[E, Ee], &[S, Se]]),
+            Self::XK => (&[], &[S], &[&[E, Es], &[E, Ee], &[S, Se]]),
+            Self::XX => (&[], &[], &[&[E], &[E, Ee, S, Es], &[S, Se]]),
+            Self::KN => (&[S], &[], &[&[E], &[E, Ee, Se]]),
+            Self::KK => (&[S], &[S], &[&[E, Es, Ss], &[E, Ee, Se]]),
+            Self::KX => (&[S], &[], &[&[E], &[E, Ee, Se, S, Es]]),
+            Self::IN => (&[], &[], &[&[E, S], &[E, Ee, Se]]),
+            Self::IK => (&[], &[S], &[&[E, Es, S, Ss], &[E, Ee, Se]]),
+            Self::IX => (&[], &[], &[&[E, S], &[E, Ee, Se, S, Es]]),
+        };
+
+        Tokens {
+            initiator_pre,
+            responder_pre,
+            messages,
+        }
+    }
+}
+
+impl Tokens {
+    /// Does the given party need a static key of its own?
+    pub(super) fn needs_local_static(&self, initiator: bool) -> bool {
+        let pre = if initiator {
+            self.initiator_pre
+        } else {
+            self.responder_pre
+        };
+
+        pre.contains(&S) || self.sent_by(initiator).any(|tokens| tokens.contains(&S))
+    }
+
+    /// Does the given party need to know the other party's static key before
+    /// the handshake?
+    pub(super) fn needs_remote_static(&self, initiator: bool) -> bool {
+        let pre = if initiator {
+            self.responder_pre
+        } else {
+            self.initiator_pre
+        };
+
+        pre.contains(&S)
+    }
+
+    /// Number of Diffie-Hellman operations using the given party's ephemeral
+    /// private key.
+    pub(super) fn ephemeral_uses(&self, initiator: bool) -> usize {
+        let uses_ephemeral = |token: &&Token| match token {
+            Ee => true,
+            Es => initiator,
+            Se => !initiator,
+            _ => false,
+        };
+
+        self.messages
+            .iter()
+            .flat_map(|tokens| tokens.iter())
+            .filter(uses_ephemeral)
+            .count()
+    }
+
+    /// Messages sent by the given party
+    fn sent_by(&self, initiator: bool) -> impl Iterator<Item = &'static [Token]> {
+        let first = usize::from(!initiator);
+        self.messages.iter().skip(first).step_by(2).copied()
+    }
+}
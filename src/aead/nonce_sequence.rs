@@ -0,0 +1,319 @@
+//! Keys bound to a nonce sequence, which make nonce reuse impossible
+//!
+//! Pairs a cipher with a counter-based [`NonceSequence`] so callers never
+//! supply nonces themselves. A [`SealingKey`] refuses to encrypt once its
+//! nonce sequence is exhausted. An [`OpeningKey`] expects messages in the
+//! order they were sealed, while a [`WindowedOpeningKey`] accepts messages
+//! which arrive out of order within a sliding window, rejecting any counter
+//! which has already been seen.
+
+pub use ring::aead::NonceSequence;
+
+use super::{replay_window::ReplayWindow, AeadCore, AeadInPlace, Buffer, Error};
+use aead::{consts::U12, generic_array::GenericArray};
+use ring::{aead::Nonce, error::Unspecified};
+
+/// Nonce sequence made of a fixed 64-bit prefix followed by a 32-bit big
+/// endian counter, allowing up to 2^32 messages.
+#[derive(Debug)]
+pub struct Counter32 {
+    prefix: [u8; 8],
+    next: Option<u32>,
+}
+
+impl Counter32 {
+    /// Create a new nonce sequence with the given prefix, starting at zero.
+    pub fn new(prefix: [u8; 8]) -> Self {
+        Self::starting_at(prefix, 0)
+    }
+
+    /// Create a nonce sequence which resumes at the given counter.
+    ///
+    /// The caller is responsible for ensuring no counter below `counter` is
+    /// still unused, i.e. this must only be used to resume a sequence.
+    pub fn starting_at(prefix: [u8; 8], counter: u32) -> Self {
+        Self {
+            prefix,
+            next: Some(counter),
+        }
+    }
+
+    /// Value of the counter which will be used for the next nonce, or `None`
+    /// if the sequence is exhausted.
+    pub fn next_counter(&self) -> Option<u32> {
+        self.next
+    }
+}
+
+impl NonceSequence for Counter32 {
+    fn advance(&mut self) -> Result<Nonce, Unspecified> {
+        let counter = self.next.ok_or(Unspecified)?;
+        self.next = counter.checked_add(1);
+
+        let mut nonce = [0u8; 12];
+        nonce[..8].copy_from_slice(&self.prefix);
+        nonce[8..].copy_from_slice(&counter.to_be_bytes());
+        Ok(Nonce::assume_unique_for_key(nonce))
+    }
+}
+
+/// Nonce sequence made of a fixed 32-bit prefix followed by a 64-bit big
+/// endian counter, allowing up to 2^64 messages.
+#[derive(Debug)]
+pub struct Counter64 {
+    prefix: [u8; 4],
+    next: Option<u64>,
+}
+
+impl Counter64 {
+    /// Create a new nonce sequence with the given prefix, starting at zero.
+    pub fn new(prefix: [u8; 4]) -> Self {
+        Self::starting_at(prefix, 0)
+    }
+
+    /// Create a nonce sequence which resumes at the given counter.
+    ///
+    /// The caller is responsible for ensuring no counter below `counter` is
+    /// still unused, i.e. this must only be used to resume a sequence.
+    pub fn starting_at(prefix: [u8; 4], counter: u64) -> Self {
+        Self {
+            prefix,
+            next: Some(counter),
+        }
+    }
+
+    /// Value of the counter which will be used for the next nonce, or `None`
+    /// if the sequence is exhausted.
+    pub fn next_counter(&self) -> Option<u64> {
+        self.next
+    }
+}
+
+impl NonceSequence for Counter64 {
+    fn advance(&mut self) -> Result<Nonce, Unspecified> {
+        let counter = self.next.ok_or(Unspecified)?;
+        self.next = counter.checked_add(1);
+        Ok(Nonce::assume_unique_for_key(counter64_nonce(
+            &self.prefix,
+            counter,
+        )))
+    }
+}
+
+/// AEAD key which seals messages under the nonces of a [`NonceSequence`].
+#[derive(Debug)]
+pub struct SealingKey<A, N> {
+    aead: A,
+    nonce_sequence: N,
+}
+
+impl<A, N> SealingKey<A, N>
+where
+    A: AeadInPlace + AeadCore<NonceSize = U12>,
+    N: NonceSequence,
+{
+    /// Bind a cipher to a nonce sequence.
+    pub fn new(aead: A, nonce_sequence: N) -> Self {
+        Self {
+            aead,
+            nonce_sequence,
+        }
+    }
+
+    /// Get the nonce sequence.
+    pub fn nonce_sequence(&self) -> &N {
+        &self.nonce_sequence
+    }
+
+    /// Encrypt the given buffer in place using the next nonce in the
+    /// sequence, returning the tag.
+    pub fn seal_in_place_detached(
+        &mut self,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<GenericArray<u8, A::TagSize>, Error> {
+        let nonce = self.advance()?;
+        self.aead
+            .encrypt_in_place_detached(&nonce, associated_data, buffer)
+    }
+
+    /// Encrypt the given buffer in place using the next nonce in the
+    /// sequence, appending the tag.
+    pub fn seal_in_place(
+        &mut self,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        let nonce = self.advance()?;
+        self.aead.encrypt_in_place(&nonce, associated_data, buffer)
+    }
+
+    fn advance(&mut self) -> Result<GenericArray<u8, U12>, Error> {
+        advance(&mut self.nonce_sequence)
+    }
+}
+
+/// AEAD key which opens messages under the nonces of a [`NonceSequence`],
+/// i.e. in the same order they were sealed.
+///
+/// A nonce is only consumed once a message has been opened under it, so a
+/// forged or corrupted message doesn't desynchronize the key.
+#[derive(Debug)]
+pub struct OpeningKey<A, N> {
+    aead: A,
+    nonce_sequence: N,
+
+    /// Nonce taken from the sequence whose message failed to open
+    pending: Option<GenericArray<u8, U12>>,
+}
+
+impl<A, N> OpeningKey<A, N>
+where
+    A: AeadInPlace + AeadCore<NonceSize = U12>,
+    N: NonceSequence,
+{
+    /// Bind a cipher to a nonce sequence.
+    pub fn new(aead: A, nonce_sequence: N) -> Self {
+        Self {
+            aead,
+            nonce_sequence,
+            pending: None,
+        }
+    }
+
+    /// Get the nonce sequence.
+    ///
+    /// After a message fails to open, the sequence is one nonce ahead of the
+    /// nonce which will be used to open the next message.
+    pub fn nonce_sequence(&self) -> &N {
+        &self.nonce_sequence
+    }
+
+    /// Decrypt the given buffer in place using the next nonce in the
+    /// sequence, checking it against the given tag.
+    pub fn open_in_place_detached(
+        &mut self,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &GenericArray<u8, A::TagSize>,
+    ) -> Result<(), Error> {
+        let nonce = self.next_nonce()?;
+        let result = self
+            .aead
+            .decrypt_in_place_detached(&nonce, associated_data, buffer, tag);
+
+        if result.is_err() {
+            self.pending = Some(nonce);
+        }
+
+        result
+    }
+
+    /// Decrypt the given buffer in place using the next nonce in the
+    /// sequence, removing the trailing tag.
+    pub fn open_in_place(
+        &mut self,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        let nonce = self.next_nonce()?;
+        let result = self.aead.decrypt_in_place(&nonce, associated_data, buffer);
+
+        if result.is_err() {
+            self.pending = Some(nonce);
+        }
+
+        result
+    }
+
+    /// Get the nonce of the next message, which is the one of the last
+    /// message if it failed to open.
+    fn next_nonce(&mut self) -> Result<GenericArray<u8, U12>, Error> {
+        match self.pending.take() {
+            Some(nonce) => Ok(nonce),
+            None => advance(&mut self.nonce_sequence),
+        }
+    }
+}
+
+/// AEAD key which opens messages sealed with a [`Counter64`] nonce sequence
+/// in any order, as long as their counter is within a sliding window behind
+/// the highest counter seen so far.
+///
+/// Counters are only recorded once a message has been authenticated, and each
+/// counter can only be used to open a single message.
+#[derive(Debug)]
+pub struct WindowedOpeningKey<A> {
+    aead: A,
+    prefix: [u8; 4],
+    window: ReplayWindow,
+}
+
+impl<A> WindowedOpeningKey<A>
+where
+    A: AeadInPlace + AeadCore<NonceSize = U12>,
+{
+    /// Number of counters behind the highest one seen which are accepted
+    pub const WINDOW_SIZE: u64 = ReplayWindow::SIZE;
+
+    /// Create a new opening key for messages sealed under the given
+    /// [`Counter64`] prefix.
+    pub fn new(aead: A, prefix: [u8; 4]) -> Self {
+        Self {
+            aead,
+            prefix,
+            window: ReplayWindow::default(),
+        }
+    }
+
+    /// Decrypt the message sealed under the given counter in place, checking
+    /// it against the given tag.
+    pub fn open_in_place_detached(
+        &mut self,
+        counter: u64,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &GenericArray<u8, A::TagSize>,
+    ) -> Result<(), Error> {
+        self.window.check(counter).map_err(|_| Error)?;
+
+        let nonce = GenericArray::from(counter64_nonce(&self.prefix, counter));
+        self.aead
+            .decrypt_in_place_detached(&nonce, associated_data, buffer, tag)?;
+
+        self.window.update(counter).map_err(|_| Error)
+    }
+
+    /// Decrypt the message sealed under the given counter in place, removing
+    /// the trailing tag.
+    pub fn open_in_place(
+        &mut self,
+        counter: u64,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        self.window.check(counter).map_err(|_| Error)?;
+
+        let nonce = GenericArray::from(counter64_nonce(&self.prefix, counter));
+        self.aead
+            .decrypt_in_place(&nonce, associated_data, buffer)?;
+
+        self.window.update(counter).map_err(|_| Error)
+    }
+}
+
+/// Get the next nonce from a nonce sequence
+fn advance(nonce_sequence: &mut impl NonceSequence) -> Result<GenericArray<u8, U12>, Error> {
+    nonce_sequence
+        .advance()
+        .map(|nonce| GenericArray::from(*nonce.as_ref()))
+        .map_err(|_| Error)
+}
+
+/// Compute the nonce for a given [`Counter64`] prefix and counter
+fn counter64_nonce(prefix: &[u8; 4], counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..4].copy_from_slice(prefix);
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
@@ -0,0 +1,283 @@
+//! Ciphertexts prefixed with a random nonce
+//!
+//! [`SealRandom`] encrypts messages under a nonce drawn from *ring*'s
+//! [`SystemRandom`], producing the layout:
+//!
+//! ```text
+//! [version (1 byte)] || [algorithm (2 bytes)] || nonce || ciphertext || tag
+//! ```
+//!
+//! The version and algorithm fields are optional and described by a
+//! [`Layout`], which must be the same when sealing and opening. The algorithm
+//! is the big endian [`AeadAlgorithm::id`] of the cipher, so it's only
+//! supported by ciphers with an [`AeadAlgorithm`]: [`Aes128Gcm`],
+//! [`Aes256Gcm`], [`ChaCha20Poly1305`] and [`AnyAead`]. When either field is
+//! present the fields are authenticated by prepending them to the associated
+//! data.
+//!
+//! Random 96-bit nonces should not be used for more than 2^32 messages under
+//! the same key. XChaCha20Poly1305's 192-bit nonces have no such limit.
+
+use super::{
+    committing::{CommittableAead, Committing},
+    derived_key::{DerivedKeyAes256Gcm, ExtendedNonceSize},
+    AeadAlgorithm, AeadInPlace, Aes128Gcm, Aes256Gcm, AnyAead, ChaCha20Poly1305, XChaCha20Poly1305,
+};
+use aead::{
+    generic_array::{typenum::Unsigned, GenericArray},
+    Nonce,
+};
+use alloc::vec::Vec;
+use core::fmt;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Optional fields which precede the nonce.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Layout {
+    /// Format version byte
+    pub version: Option<u8>,
+
+    /// Algorithm identifier of the cipher
+    pub algorithm: bool,
+}
+
+impl Layout {
+    /// Bare `nonce || ciphertext || tag` layout.
+    pub const BARE: Self = Self {
+        version: None,
+        algorithm: false,
+    };
+
+    /// Layout with a version byte.
+    pub fn versioned(version: u8) -> Self {
+        Self {
+            version: Some(version),
+            algorithm: false,
+        }
+    }
+
+    /// Add the cipher's algorithm identifier to this layout.
+    pub fn with_algorithm(mut self) -> Self {
+        self.algorithm = true;
+        self
+    }
+
+    /// Size of the fields which precede the nonce.
+    pub fn header_len(&self) -> usize {
+        usize::from(self.version.is_some()) + 2 * usize::from(self.algorithm)
+    }
+
+    /// Serialize the fields which precede the nonce for a cipher with the
+    /// given algorithm.
+    fn header(&self, algorithm: Option<AeadAlgorithm>) -> Result<Vec<u8>, PrefixedError> {
+        let mut header = Vec::with_capacity(self.header_len());
+        header.extend(self.version);
+
+        if self.algorithm {
+            let algorithm = algorithm.ok_or(PrefixedError::NoAlgorithm)?;
+            header.extend_from_slice(&algorithm.id().to_be_bytes());
+        }
+
+        Ok(header)
+    }
+
+    /// Check the fields which precede the nonce for a cipher with the given
+    /// algorithm.
+    fn check_header(
+        &self,
+        header: &[u8],
+        algorithm: Option<AeadAlgorithm>,
+    ) -> Result<(), PrefixedError> {
+        let mut header = header;
+
+        if let Some(version) = self.version {
+            if header[0] != version {
+                return Err(PrefixedError::UnsupportedVersion(header[0]));
+            }
+
+            header = &header[1..];
+        }
+
+        if self.algorithm {
+            let algorithm = algorithm.ok_or(PrefixedError::NoAlgorithm)?;
+            let id = u16::from_be_bytes([header[0], header[1]]);
+
+            if id != algorithm.id() {
+                return Err(PrefixedError::AlgorithmMismatch(id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors returned by [`SealRandom`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PrefixedError {
+    /// Encryption or decryption failed
+    Aead,
+
+    /// The system random number generator failed
+    Rng,
+
+    /// Input is too short to hold a header, nonce and tag
+    Truncated,
+
+    /// Version byte differs from the expected one
+    UnsupportedVersion(u8),
+
+    /// Algorithm identifier differs from the expected one
+    AlgorithmMismatch(u16),
+
+    /// The layout has an algorithm identifier, but the cipher has no
+    /// [`AeadAlgorithm`]
+    NoAlgorithm,
+}
+
+impl fmt::Display for PrefixedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Aead => f.write_str("AEAD error"),
+            Self::Rng => f.write_str("random nonce generation failed"),
+            Self::Truncated => f.write_str("ciphertext truncated"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported ciphertext version: {}", version)
+            }
+            Self::AlgorithmMismatch(id) => write!(f, "unexpected AEAD algorithm: {}", id),
+            Self::NoAlgorithm => f.write_str("cipher has no AEAD algorithm identifier"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PrefixedError {}
+
+impl From<aead::Error> for PrefixedError {
+    fn from(_: aead::Error) -> PrefixedError {
+        PrefixedError::Aead
+    }
+}
+
+impl From<PrefixedError> for aead::Error {
+    fn from(_: PrefixedError) -> aead::Error {
+        aead::Error
+    }
+}
+
+/// Encryption under random nonces which are prefixed to the ciphertext.
+///
+/// Implemented for this crate's AEAD ciphers.
+pub trait SealRandom: AeadInPlace {
+    /// Algorithm identified by the algorithm field of a [`Layout`], if any.
+    fn algorithm(&self) -> Option<AeadAlgorithm> {
+        None
+    }
+
+    /// Encrypt a message under a random nonce, returning
+    /// `nonce || ciphertext || tag`.
+    fn seal_random(
+        &self,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, PrefixedError> {
+        self.seal_random_with(&Layout::BARE, plaintext, associated_data)
+    }
+
+    /// Decrypt a message in the `nonce || ciphertext || tag` layout.
+    fn open_prefixed(
+        &self,
+        ciphertext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, PrefixedError> {
+        self.open_prefixed_with(&Layout::BARE, ciphertext, associated_data)
+    }
+
+    /// Encrypt a message under a random nonce using the given layout.
+    fn seal_random_with(
+        &self,
+        layout: &Layout,
+        plaintext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, PrefixedError> {
+        let mut nonce = Nonce::<Self>::default();
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| PrefixedError::Rng)?;
+
+        let mut out = layout.header(SealRandom::algorithm(self))?;
+        let aad = authenticated_data(&out, associated_data);
+        out.extend_from_slice(&nonce);
+
+        let offset = out.len();
+        out.extend_from_slice(plaintext);
+
+        let tag = self.encrypt_in_place_detached(&nonce, &aad, &mut out[offset..])?;
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// Decrypt a message which was encrypted with the given layout.
+    fn open_prefixed_with(
+        &self,
+        layout: &Layout,
+        ciphertext: &[u8],
+        associated_data: &[u8],
+    ) -> Result<Vec<u8>, PrefixedError> {
+        let header_len = layout.header_len();
+        let nonce_len = Self::NonceSize::USIZE;
+        let tag_len = Self::TagSize::USIZE;
+
+        if ciphertext.len() < header_len + nonce_len + tag_len {
+            return Err(PrefixedError::Truncated);
+        }
+
+        let (header, rest) = ciphertext.split_at(header_len);
+        layout.check_header(header, SealRandom::algorithm(self))?;
+
+        let (nonce, rest) = rest.split_at(nonce_len);
+        let (msg, tag) = rest.split_at(rest.len() - tag_len);
+        let aad = authenticated_data(header, associated_data);
+        let mut buffer = msg.to_vec();
+
+        self.decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            &aad,
+            &mut buffer,
+            GenericArray::from_slice(tag),
+        )?;
+
+        Ok(buffer)
+    }
+}
+
+macro_rules! impl_seal_random {
+    ($cipher:ty, $algorithm:expr) => {
+        impl SealRandom for $cipher {
+            fn algorithm(&self) -> Option<AeadAlgorithm> {
+                Some($algorithm)
+            }
+        }
+    };
+}
+
+impl_seal_random!(Aes128Gcm, AeadAlgorithm::Aes128Gcm);
+impl_seal_random!(Aes256Gcm, AeadAlgorithm::Aes256Gcm);
+impl_seal_random!(ChaCha20Poly1305, AeadAlgorithm::ChaCha20Poly1305);
+
+impl SealRandom for AnyAead {
+    fn algorithm(&self) -> Option<AeadAlgorithm> {
+        Some(AnyAead::algorithm(self))
+    }
+}
+
+impl SealRandom for XChaCha20Poly1305 {}
+
+impl<N: ExtendedNonceSize> SealRandom for DerivedKeyAes256Gcm<N> {}
+
+impl<A> SealRandom for Committing<A> where A: CommittableAead {}
+
+/// Associated data: the header (if any) followed by the caller's
+fn authenticated_data(header: &[u8], associated_data: &[u8]) -> Vec<u8> {
+    [header, associated_data].concat()
+}
@@ -0,0 +1,373 @@
+//! QUIC packet protection as described in [RFC 9001]
+//!
+//! Provides header protection keys, derivation of packet protection keys from
+//! QUIC secrets (including the Initial secrets derived from a client's
+//! Destination Connection ID), and per-packet nonce construction for use with
+//! the AEAD ciphers in this crate.
+//!
+//! [RFC 9001]: https://www.rfc-editor.org/rfc/rfc9001
+
+use super::{
+    tls13::{hkdf_expand_label, CipherSuite},
+    Aes128Gcm, Aes256Gcm, ChaCha20Poly1305, Error, KeyInit, KeySizeUser,
+};
+use aead::{
+    consts::{U12, U16, U32},
+    generic_array::GenericArray,
+};
+use core::fmt;
+use ring::{
+    aead::quic::{self, AES_128, AES_256, CHACHA20},
+    hkdf::{self, KeyType},
+};
+
+pub use super::tls13::Iv;
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Header protection sample: 16 bytes of packet ciphertext
+pub type Sample = [u8; 16];
+
+/// Header protection mask
+pub type Mask = [u8; 5];
+
+/// Per-packet AEAD nonce
+pub type Nonce = GenericArray<u8, U12>;
+
+/// Salt used to derive Initial secrets in QUIC version 1
+pub const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+
+/// Maximum size of a secret in bytes (SHA-384)
+const MAX_SECRET_LEN: usize = 48;
+
+/// Size of the packet number field as encoded in the first byte of a packet
+const PACKET_NUMBER_LEN_MASK: u8 = 0x03;
+
+/// Bit of the first byte of a packet which indicates a long header
+const LONG_HEADER_FORM: u8 = 0x80;
+
+/// Header protection: computes a mask from a sample of packet ciphertext and
+/// applies it to the first byte and packet number field of a packet.
+pub trait HeaderProtection {
+    /// Compute the header protection mask for the given sample.
+    fn new_mask(&self, sample: &Sample) -> Mask;
+
+    /// Apply header protection.
+    ///
+    /// `packet_number` must be the packet number field of the packet, whose
+    /// length is encoded in the (unprotected) `first_byte`.
+    fn protect(
+        &self,
+        sample: &Sample,
+        first_byte: &mut u8,
+        packet_number: &mut [u8],
+    ) -> Result<(), Error> {
+        let pn_len = packet_number_len(*first_byte);
+
+        if packet_number.len() != pn_len {
+            return Err(Error);
+        }
+
+        let mask = self.new_mask(sample);
+        *first_byte ^= mask[0] & first_byte_mask(*first_byte);
+        xor_in_place(packet_number, &mask[1..]);
+        Ok(())
+    }
+
+    /// Remove header protection, returning the length of the packet number.
+    ///
+    /// `packet_number` must start at the packet number field of the packet
+    /// and may extend past it, e.g. up to the maximum of 4 bytes. Only the
+    /// bytes which belong to the packet number are modified.
+    fn unprotect(
+        &self,
+        sample: &Sample,
+        first_byte: &mut u8,
+        packet_number: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mask = self.new_mask(sample);
+        let unprotected_first_byte = *first_byte ^ (mask[0] & first_byte_mask(*first_byte));
+        let pn_len = packet_number_len(unprotected_first_byte);
+
+        if packet_number.len() < pn_len {
+            return Err(Error);
+        }
+
+        *first_byte = unprotected_first_byte;
+        xor_in_place(&mut packet_number[..pn_len], &mask[1..]);
+        Ok(pn_len)
+    }
+}
+
+/// AES-128 header protection key
+pub struct Aes128HeaderProtectionKey(quic::HeaderProtectionKey);
+
+/// AES-256 header protection key
+pub struct Aes256HeaderProtectionKey(quic::HeaderProtectionKey);
+
+/// ChaCha20 header protection key
+pub struct ChaCha20HeaderProtectionKey(quic::HeaderProtectionKey);
+
+macro_rules! impl_header_protection {
+    ($key:ty, $algorithm:expr, $key_size:ty) => {
+        impl KeySizeUser for $key {
+            type KeySize = $key_size;
+        }
+
+        impl KeyInit for $key {
+            fn new(key: &GenericArray<u8, Self::KeySize>) -> Self {
+                Self(quic::HeaderProtectionKey::new(&$algorithm, key.as_slice()).unwrap())
+            }
+        }
+
+        impl HeaderProtection for $key {
+            fn new_mask(&self, sample: &Sample) -> Mask {
+                self.0
+                    .new_mask(sample)
+                    .expect("sample should be the correct length")
+            }
+        }
+
+        impl fmt::Debug for $key {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($key)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+impl_header_protection!(Aes128HeaderProtectionKey, AES_128, U16);
+impl_header_protection!(Aes256HeaderProtectionKey, AES_256, U32);
+impl_header_protection!(ChaCha20HeaderProtectionKey, CHACHA20, U32);
+
+/// AEAD algorithms which can be used for QUIC packet protection: those of
+/// the TLS 1.3 cipher suites.
+pub trait PacketProtection: CipherSuite {
+    /// Header protection key which is used alongside this AEAD
+    type HeaderProtectionKey: HeaderProtection + KeyInit + KeySizeUser<KeySize = Self::KeySize>;
+}
+
+impl PacketProtection for Aes128Gcm {
+    type HeaderProtectionKey = Aes128HeaderProtectionKey;
+}
+
+impl PacketProtection for Aes256Gcm {
+    type HeaderProtectionKey = Aes256HeaderProtectionKey;
+}
+
+impl PacketProtection for ChaCha20Poly1305 {
+    type HeaderProtectionKey = ChaCha20HeaderProtectionKey;
+}
+
+/// QUIC traffic secret, e.g. a client or server Initial secret
+#[derive(Clone)]
+pub struct Secret {
+    algorithm: hkdf::Algorithm,
+    bytes: [u8; MAX_SECRET_LEN],
+    len: usize,
+}
+
+impl Secret {
+    /// Create a secret from its raw bytes, which must be the output size of
+    /// the given HKDF algorithm's hash function.
+    pub fn new(algorithm: hkdf::Algorithm, secret: &[u8]) -> Result<Self, Error> {
+        let len = algorithm.len();
+
+        if secret.len() != len {
+            return Err(Error);
+        }
+
+        let mut bytes = [0u8; MAX_SECRET_LEN];
+        bytes[..len].copy_from_slice(secret);
+        Ok(Self {
+            algorithm,
+            bytes,
+            len,
+        })
+    }
+
+    /// HKDF algorithm used with this secret
+    pub fn algorithm(&self) -> hkdf::Algorithm {
+        self.algorithm
+    }
+
+    /// Derive the next secret for a key update (`quic ku`).
+    pub fn update(&self) -> Self {
+        let mut next = self.clone();
+        self.expand_label(b"quic ku", &mut next.bytes[..self.len])
+            .expect("key update label should fit");
+        next
+    }
+
+    /// Derive the packet protection keys for this secret.
+    ///
+    /// Fails if `A` belongs to a cipher suite with a different hash function.
+    pub fn keys<A: PacketProtection>(&self) -> Result<Keys<A>, Error> {
+        if A::hkdf_algorithm() != self.algorithm {
+            return Err(Error);
+        }
+
+        let mut key = GenericArray::<u8, A::KeySize>::default();
+        let mut iv = [0u8; 12];
+        let mut hp = GenericArray::<u8, A::KeySize>::default();
+
+        let result = self
+            .expand_label(b"quic key", &mut key)
+            .and_then(|()| self.expand_label(b"quic iv", &mut iv))
+            .and_then(|()| self.expand_label(b"quic hp", &mut hp))
+            .map(|()| Keys {
+                packet: A::new(&key),
+                iv: iv.into(),
+                header: A::HeaderProtectionKey::new(&hp),
+            });
+
+        #[cfg(feature = "zeroize")]
+        {
+            key.as_mut_slice().zeroize();
+            hp.as_mut_slice().zeroize();
+        }
+
+        result
+    }
+
+    fn expand_label(&self, label: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        let prk = hkdf::Prk::new_less_safe(self.algorithm, self.as_ref());
+        hkdf_expand_label(&prk, label, &[], out)
+    }
+}
+
+impl AsRef<[u8]> for Secret {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secret")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Secret {}
+
+/// Client and server Initial secrets
+#[derive(Debug)]
+pub struct InitialSecrets {
+    /// Secret used to protect packets sent by the client
+    pub client: Secret,
+
+    /// Secret used to protect packets sent by the server
+    pub server: Secret,
+}
+
+impl InitialSecrets {
+    /// Derive the QUIC version 1 Initial secrets from the Destination
+    /// Connection ID field of the client's first Initial packet.
+    pub fn new(connection_id: &[u8]) -> Self {
+        Self::with_salt(&INITIAL_SALT_V1, connection_id)
+    }
+
+    /// Derive Initial secrets with a version-specific salt.
+    pub fn with_salt(salt: &[u8], connection_id: &[u8]) -> Self {
+        let initial_secret = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(connection_id);
+        let mut client = [0u8; 32];
+        let mut server = [0u8; 32];
+
+        hkdf_expand_label(&initial_secret, b"client in", &[], &mut client)
+            .expect("client Initial label should fit");
+        hkdf_expand_label(&initial_secret, b"server in", &[], &mut server)
+            .expect("server Initial label should fit");
+
+        let secrets = Self {
+            client: Secret::new(hkdf::HKDF_SHA256, &client).unwrap(),
+            server: Secret::new(hkdf::HKDF_SHA256, &server).unwrap(),
+        };
+
+        #[cfg(feature = "zeroize")]
+        {
+            client.zeroize();
+            server.zeroize();
+        }
+
+        secrets
+    }
+}
+
+/// Packet protection keys for one direction of a connection
+pub struct Keys<A: PacketProtection> {
+    /// AEAD used to protect packet payloads
+    pub packet: A,
+
+    /// IV used to compute per-packet nonces
+    pub iv: Iv,
+
+    /// Header protection key
+    pub header: A::HeaderProtectionKey,
+}
+
+impl<A: PacketProtection> Keys<A> {
+    /// Encrypt a packet payload in place, authenticating the (unprotected)
+    /// `header`, and returning the tag.
+    pub fn encrypt_in_place_detached(
+        &self,
+        packet_number: u64,
+        header: &[u8],
+        payload: &mut [u8],
+    ) -> Result<GenericArray<u8, A::TagSize>, Error> {
+        self.packet
+            .encrypt_in_place_detached(&self.iv.nonce(packet_number), header, payload)
+    }
+
+    /// Decrypt a packet payload in place, authenticating the (unprotected)
+    /// `header`.
+    pub fn decrypt_in_place_detached(
+        &self,
+        packet_number: u64,
+        header: &[u8],
+        payload: &mut [u8],
+        tag: &GenericArray<u8, A::TagSize>,
+    ) -> Result<(), Error> {
+        self.packet
+            .decrypt_in_place_detached(&self.iv.nonce(packet_number), header, payload, tag)
+    }
+}
+
+impl<A: PacketProtection> fmt::Debug for Keys<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keys").finish_non_exhaustive()
+    }
+}
+
+/// Bits of the first byte which are protected
+fn first_byte_mask(first_byte: u8) -> u8 {
+    if first_byte & LONG_HEADER_FORM != 0 {
+        0x0f
+    } else {
+        0x1f
+    }
+}
+
+/// Length of the packet number field, as encoded in an unprotected first byte
+fn packet_number_len(first_byte: u8) -> usize {
+    usize::from(first_byte & PACKET_NUMBER_LEN_MASK) + 1
+}
+
+fn xor_in_place(out: &mut [u8], mask: &[u8]) {
+    for (a, b) in out.iter_mut().zip(mask) {
+        *a ^= b;
+    }
+}
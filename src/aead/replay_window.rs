@@ -0,0 +1,88 @@
+//! Sliding anti-replay window as described in [RFC 6479]
+//!
+//! [RFC 6479]: https://www.rfc-editor.org/rfc/rfc6479
+
+/// Number of bits in each block of the bitmap
+const BLOCK_BITS: u64 = u64::BITS as u64;
+
+/// Number of blocks in the bitmap
+const BLOCKS: usize = 4;
+
+/// Reasons a sequence number may be rejected by a [`ReplayWindow`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Rejected {
+    /// Sequence number has already been seen
+    Duplicate,
+
+    /// Sequence number is too far behind the highest one seen
+    Stale,
+}
+
+/// Anti-replay window which tracks which of the most recent sequence numbers
+/// have been seen.
+///
+/// The bitmap is made of several blocks, which are lazily cleared as the
+/// window advances, so that advancing the window never requires shifting bits.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ReplayWindow {
+    /// Bitmap of the sequence numbers seen within the window
+    bitmap: [u64; BLOCKS],
+
+    /// Highest sequence number seen
+    highest: u64,
+}
+
+impl ReplayWindow {
+    /// Number of sequence numbers behind the highest one which are tracked
+    pub const SIZE: u64 = (BLOCKS as u64 - 1) * BLOCK_BITS;
+
+    /// Check whether a sequence number is acceptable, without recording it.
+    ///
+    /// Sequence numbers must only be recorded with [`ReplayWindow::update`]
+    /// once the packet they belong to has been authenticated.
+    pub fn check(&self, sequence_number: u64) -> Result<(), Rejected> {
+        if sequence_number > self.highest {
+            return Ok(());
+        }
+
+        if self.highest - sequence_number >= Self::SIZE {
+            return Err(Rejected::Stale);
+        }
+
+        let (block, bit) = Self::position(sequence_number);
+
+        if self.bitmap[block] & bit != 0 {
+            Err(Rejected::Duplicate)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Record a sequence number as seen, advancing the window if needed.
+    pub fn update(&mut self, sequence_number: u64) -> Result<(), Rejected> {
+        self.check(sequence_number)?;
+
+        if sequence_number > self.highest {
+            let current_block = self.highest / BLOCK_BITS;
+            let new_block = sequence_number / BLOCK_BITS;
+            let stale_blocks = (new_block - current_block).min(BLOCKS as u64);
+
+            for i in 1..=stale_blocks {
+                self.bitmap[((current_block + i) % BLOCKS as u64) as usize] = 0;
+            }
+
+            self.highest = sequence_number;
+        }
+
+        let (block, bit) = Self::position(sequence_number);
+        self.bitmap[block] |= bit;
+        Ok(())
+    }
+
+    /// Position of a sequence number's bit within the bitmap
+    fn position(sequence_number: u64) -> (usize, u64) {
+        let block = (sequence_number / BLOCK_BITS) % BLOCKS as u64;
+        let bit = 1 << (sequence_number % BLOCK_BITS);
+        (block as usize, bit)
+    }
+}
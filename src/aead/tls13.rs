@@ -0,0 +1,462 @@
+//! TLS 1.3 record protection and key schedule as described in [RFC 8446]
+//!
+//! Provides the `HKDF-Expand-Label` based key schedule for the SHA-256 and
+//! SHA-384 cipher suites, derivation of traffic keys from traffic secrets,
+//! and protection of records (i.e. `TLSInnerPlaintext` with its content type
+//! and padding) using per-record nonces.
+//!
+//! [RFC 8446]: https://www.rfc-editor.org/rfc/rfc8446
+
+use super::{Aes128Gcm, Aes256Gcm, ChaCha20Poly1305, Error, KeyInit};
+use aead::{
+    consts::{U12, U16},
+    generic_array::GenericArray,
+    AeadCore, AeadInPlace,
+};
+use core::fmt;
+use ring::{
+    digest,
+    hkdf::{self, KeyType},
+    hmac,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Per-record AEAD nonce
+pub type Nonce = GenericArray<u8, U12>;
+
+/// Maximum size of a secret in bytes (SHA-384)
+const MAX_SECRET_LEN: usize = 48;
+
+/// Size of a record header
+pub const HEADER_LEN: usize = 5;
+
+/// Maximum length of the content of a record (2^14)
+pub const MAX_CONTENT_LEN: usize = 1 << 14;
+
+/// Maximum length of an encrypted record's payload (2^14 + 256)
+pub const MAX_CIPHERTEXT_LEN: usize = MAX_CONTENT_LEN + 256;
+
+/// Size of an authentication tag
+const TAG_LEN: usize = 16;
+
+/// `legacy_record_version` of protected records
+#[cfg(feature = "alloc")]
+const LEGACY_RECORD_VERSION: [u8; 2] = [0x03, 0x03];
+
+/// TLS record content type.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ContentType(pub u8);
+
+impl ContentType {
+    /// `change_cipher_spec`
+    pub const CHANGE_CIPHER_SPEC: Self = Self(20);
+
+    /// `alert`
+    pub const ALERT: Self = Self(21);
+
+    /// `handshake`
+    pub const HANDSHAKE: Self = Self(22);
+
+    /// `application_data`
+    pub const APPLICATION_DATA: Self = Self(23);
+}
+
+/// AEAD algorithms of the TLS 1.3 cipher suites.
+pub trait CipherSuite: AeadInPlace + AeadCore<NonceSize = U12, TagSize = U16> + KeyInit {
+    /// HKDF algorithm of the cipher suite
+    fn hkdf_algorithm() -> hkdf::Algorithm;
+}
+
+/// `TLS_AES_128_GCM_SHA256`
+impl CipherSuite for Aes128Gcm {
+    fn hkdf_algorithm() -> hkdf::Algorithm {
+        hkdf::HKDF_SHA256
+    }
+}
+
+/// `TLS_AES_256_GCM_SHA384`
+impl CipherSuite for Aes256Gcm {
+    fn hkdf_algorithm() -> hkdf::Algorithm {
+        hkdf::HKDF_SHA384
+    }
+}
+
+/// `TLS_CHACHA20_POLY1305_SHA256`
+impl CipherSuite for ChaCha20Poly1305 {
+    fn hkdf_algorithm() -> hkdf::Algorithm {
+        hkdf::HKDF_SHA256
+    }
+}
+
+/// Key schedule secret, e.g. the Handshake Secret or a traffic secret
+#[derive(Clone)]
+pub struct Secret {
+    algorithm: hkdf::Algorithm,
+    bytes: [u8; MAX_SECRET_LEN],
+    len: usize,
+}
+
+impl Secret {
+    /// Create a secret from its raw bytes, which must be the output size of
+    /// the given HKDF algorithm's hash function.
+    pub fn new(algorithm: hkdf::Algorithm, secret: &[u8]) -> Result<Self, Error> {
+        let len = algorithm.len();
+
+        if secret.len() != len {
+            return Err(Error);
+        }
+
+        let mut bytes = [0u8; MAX_SECRET_LEN];
+        bytes[..len].copy_from_slice(secret);
+        Ok(Self {
+            algorithm,
+            bytes,
+            len,
+        })
+    }
+
+    /// HKDF algorithm used with this secret
+    pub fn algorithm(&self) -> hkdf::Algorithm {
+        self.algorithm
+    }
+
+    /// `HKDF-Expand-Label(Secret, Label, Context, Length)`, where the length
+    /// is that of `out`.
+    ///
+    /// Fails if the label, context or output is too long.
+    pub fn expand_label(&self, label: &[u8], context: &[u8], out: &mut [u8]) -> Result<(), Error> {
+        let prk = hkdf::Prk::new_less_safe(self.algorithm, self.as_ref());
+        hkdf_expand_label(&prk, label, context, out)
+    }
+
+    /// `Derive-Secret(Secret, Label, Messages)`, given the transcript hash of
+    /// the messages.
+    ///
+    /// Fails if the label or transcript hash is too long.
+    pub fn derive_secret(&self, label: &[u8], transcript_hash: &[u8]) -> Result<Self, Error> {
+        let mut secret = self.clone();
+        self.expand_label(label, transcript_hash, &mut secret.bytes[..self.len])?;
+        Ok(secret)
+    }
+
+    /// Derive the next application traffic secret for a key update
+    /// (`traffic upd`).
+    pub fn update(&self) -> Self {
+        self.derive_secret(b"traffic upd", &[])
+            .expect("traffic update label should fit")
+    }
+
+    /// Derive the record protection keys for this traffic secret.
+    ///
+    /// Fails if `A` belongs to a cipher suite with a different hash function.
+    pub fn record_keys<A: CipherSuite>(&self) -> Result<RecordKeys<A>, Error> {
+        if A::hkdf_algorithm() != self.algorithm {
+            return Err(Error);
+        }
+
+        let mut key = GenericArray::<u8, A::KeySize>::default();
+        let mut iv = Iv::default();
+
+        let result = self
+            .expand_label(b"key", &[], &mut key)
+            .and_then(|()| self.expand_label(b"iv", &[], &mut iv.0))
+            .map(|()| A::new(&key));
+
+        #[cfg(feature = "zeroize")]
+        key.as_mut_slice().zeroize();
+
+        Ok(RecordKeys::new(result?, iv))
+    }
+
+    /// `HKDF-Extract(Salt, IKM)` using this secret as the salt.
+    fn extract(&self, ikm: &[u8]) -> Self {
+        let key = hmac::Key::new(self.algorithm.hmac_algorithm(), self.as_ref());
+        Self::new(self.algorithm, hmac::sign(&key, ikm).as_ref()).unwrap()
+    }
+}
+
+impl AsRef<[u8]> for Secret {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secret")
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Secret {}
+
+/// TLS 1.3 key schedule (RFC 8446 Section 7.1).
+///
+/// Starts out at the Early Secret and is advanced to the Handshake Secret and
+/// then the Master Secret, from which traffic secrets are derived using the
+/// transcript hash of the handshake so far.
+#[derive(Debug)]
+pub struct KeySchedule {
+    secret: Secret,
+}
+
+impl KeySchedule {
+    /// Compute the Early Secret from an optional pre-shared key.
+    pub fn new(algorithm: hkdf::Algorithm, psk: Option<&[u8]>) -> Self {
+        let zeros = [0u8; MAX_SECRET_LEN];
+        let salt = Secret::new(algorithm, &zeros[..algorithm.len()]).unwrap();
+
+        Self {
+            secret: salt.extract(psk.unwrap_or(salt.as_ref())),
+        }
+    }
+
+    /// Current secret of the key schedule.
+    pub fn secret(&self) -> &Secret {
+        &self.secret
+    }
+
+    /// Derive a secret from the current secret, e.g. `c hs traffic` from the
+    /// Handshake Secret.
+    ///
+    /// Fails if the label or transcript hash is too long.
+    pub fn derive_secret(&self, label: &[u8], transcript_hash: &[u8]) -> Result<Secret, Error> {
+        self.secret.derive_secret(label, transcript_hash)
+    }
+
+    /// Advance from the Early Secret to the Handshake Secret using the
+    /// (EC)DHE shared secret.
+    pub fn into_handshake(self, shared_secret: &[u8]) -> Self {
+        self.advance(shared_secret)
+    }
+
+    /// Advance from the Handshake Secret to the Master Secret.
+    pub fn into_master(self) -> Self {
+        let zeros = [0u8; MAX_SECRET_LEN];
+        let len = self.secret.len;
+        self.advance(&zeros[..len])
+    }
+
+    fn advance(self, ikm: &[u8]) -> Self {
+        let empty_hash = digest::digest(
+            self.secret.algorithm.hmac_algorithm().digest_algorithm(),
+            &[],
+        );
+
+        Self {
+            secret: self
+                .derive_secret(b"derived", empty_hash.as_ref())
+                .expect("derived label and hash should fit")
+                .extract(ikm),
+        }
+    }
+}
+
+/// Record protection keys for one direction of a connection.
+///
+/// Tracks the sequence number, which is used to compute each record's nonce.
+pub struct RecordKeys<A: CipherSuite> {
+    aead: A,
+    iv: Iv,
+    sequence_number: u64,
+}
+
+impl<A: CipherSuite> RecordKeys<A> {
+    /// Create record protection keys from an AEAD key and IV, starting at
+    /// sequence number 0.
+    pub fn new(aead: A, iv: Iv) -> Self {
+        Self {
+            aead,
+            iv,
+            sequence_number: 0,
+        }
+    }
+
+    /// Sequence number of the next record.
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// Protect a record, padding its `TLSInnerPlaintext` with `padding` zero
+    /// bytes, and return the complete `TLSCiphertext` including its header.
+    #[cfg(feature = "alloc")]
+    pub fn seal(
+        &mut self,
+        content_type: ContentType,
+        content: &[u8],
+        padding: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let inner_len = content.len() + 1 + padding;
+
+        if content.len() > MAX_CONTENT_LEN || inner_len + TAG_LEN > MAX_CIPHERTEXT_LEN {
+            return Err(Error);
+        }
+
+        let header = record_header(inner_len + TAG_LEN);
+        let mut record = Vec::with_capacity(HEADER_LEN + inner_len + TAG_LEN);
+        record.extend_from_slice(&header);
+        record.extend_from_slice(content);
+        record.push(content_type.0);
+        record.resize(HEADER_LEN + inner_len, 0);
+
+        let nonce = self.next_nonce()?;
+        let tag =
+            self.aead
+                .encrypt_in_place_detached(&nonce, &header, &mut record[HEADER_LEN..])?;
+
+        record.extend_from_slice(&tag);
+        Ok(record)
+    }
+
+    /// Remove protection from a complete `TLSCiphertext` in place, returning
+    /// its content type and content.
+    pub fn open_in_place<'a>(
+        &mut self,
+        record: &'a mut [u8],
+    ) -> Result<(ContentType, &'a [u8]), Error> {
+        if record.len() < HEADER_LEN + TAG_LEN || record.len() > HEADER_LEN + MAX_CIPHERTEXT_LEN {
+            return Err(Error);
+        }
+
+        let (header, payload) = record.split_at_mut(HEADER_LEN);
+
+        if header[0] != ContentType::APPLICATION_DATA.0
+            || usize::from(u16::from_be_bytes([header[3], header[4]])) != payload.len()
+        {
+            return Err(Error);
+        }
+
+        let (inner, tag) = payload.split_at_mut(payload.len() - TAG_LEN);
+        let nonce = self.next_nonce()?;
+        self.aead.decrypt_in_place_detached(
+            &nonce,
+            header,
+            inner,
+            GenericArray::from_slice(tag),
+        )?;
+
+        // The content type is the last non-zero byte, followed by padding
+        let content_len = inner.iter().rposition(|&b| b != 0).ok_or(Error)?;
+
+        if content_len > MAX_CONTENT_LEN {
+            return Err(Error);
+        }
+
+        Ok((ContentType(inner[content_len]), &inner[..content_len]))
+    }
+
+    /// Compute the nonce for the next record and advance the sequence number.
+    fn next_nonce(&mut self) -> Result<Nonce, Error> {
+        let nonce = self.iv.nonce(self.sequence_number);
+        self.sequence_number = self.sequence_number.checked_add(1).ok_or(Error)?;
+        Ok(nonce)
+    }
+}
+
+impl<A: CipherSuite> fmt::Debug for RecordKeys<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordKeys")
+            .field("sequence_number", &self.sequence_number)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Record protection IV
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Iv([u8; 12]);
+
+impl Iv {
+    /// Compute the nonce for the given sequence number by XORing its 64-bit
+    /// value, left-padded to the size of the IV, with the IV.
+    pub fn nonce(&self, sequence_number: u64) -> Nonce {
+        let mut nonce = Nonce::from(self.0);
+
+        for (a, b) in nonce[4..].iter_mut().zip(sequence_number.to_be_bytes()) {
+            *a ^= b;
+        }
+
+        nonce
+    }
+}
+
+impl fmt::Debug for Iv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iv").finish_non_exhaustive()
+    }
+}
+
+impl From<[u8; 12]> for Iv {
+    fn from(bytes: [u8; 12]) -> Self {
+        Iv(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Iv {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// `HKDF-Expand-Label` (RFC 8446 Section 7.1)
+///
+/// Fails if the label, context or output is too long to be encoded.
+pub(super) fn hkdf_expand_label(
+    prk: &hkdf::Prk,
+    label: &[u8],
+    context: &[u8],
+    out: &mut [u8],
+) -> Result<(), Error> {
+    const LABEL_PREFIX: &[u8] = b"tls13 ";
+
+    let output_len = u16::try_from(out.len()).map_err(|_| Error)?.to_be_bytes();
+    let label_len = [u8::try_from(LABEL_PREFIX.len() + label.len()).map_err(|_| Error)?];
+    let context_len = [u8::try_from(context.len()).map_err(|_| Error)?];
+    let info = [
+        &output_len[..],
+        &label_len[..],
+        LABEL_PREFIX,
+        label,
+        &context_len[..],
+        context,
+    ];
+
+    prk.expand(&info, OutputLen(out.len()))
+        .and_then(|okm| okm.fill(out))
+        .map_err(|_| Error)
+}
+
+/// Length of HKDF output
+pub(super) struct OutputLen(pub(super) usize);
+
+impl hkdf::KeyType for OutputLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// Header of a protected record with the given payload length
+#[cfg(feature = "alloc")]
+fn record_header(len: usize) -> [u8; HEADER_LEN] {
+    let len = (len as u16).to_be_bytes();
+    [
+        ContentType::APPLICATION_DATA.0,
+        LEGACY_RECORD_VERSION[0],
+        LEGACY_RECORD_VERSION[1],
+        len[0],
+        len[1],
+    ]
+}
@@ -0,0 +1,127 @@
+//! Vectored (scatter/gather) AEAD
+//!
+//! [`AeadVectored`] encrypts and decrypts messages which are split across
+//! several buffers, authenticating associated data which is likewise split,
+//! e.g. a packet held as a header followed by a list of payload fragments.
+//!
+//! *ring* only operates on contiguous buffers, so when the message spans
+//! more than one non-empty buffer it is gathered into a single bounce
+//! buffer, processed, and scattered back. Messages which occupy a single
+//! buffer, and associated data in a single slice, are processed without
+//! copying.
+
+use super::{AeadCore, Aes128Gcm, Aes256Gcm, AnyAead, ChaCha20Poly1305, Error, XChaCha20Poly1305};
+use aead::{Nonce, Tag};
+use alloc::{borrow::Cow, vec::Vec};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// AEAD algorithms which support messages split across multiple buffers.
+pub trait AeadVectored: AeadCore {
+    /// Encrypt the message held in `buffers` in place, authenticating the
+    /// concatenation of `associated_data`, and return the tag.
+    fn encrypt_vectored_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[&[u8]],
+        buffers: &mut [&mut [u8]],
+    ) -> Result<Tag<Self>, Error>;
+
+    /// Decrypt the message held in `buffers` in place, checking it against
+    /// the concatenation of `associated_data` and the given tag.
+    ///
+    /// The contents of `buffers` are unspecified if decryption fails.
+    fn decrypt_vectored_detached(
+        &self,
+        nonce: &Nonce<Self>,
+        associated_data: &[&[u8]],
+        buffers: &mut [&mut [u8]],
+        tag: &Tag<Self>,
+    ) -> Result<(), Error>;
+}
+
+macro_rules! impl_vectored {
+    ($cipher:ty, |$this:ident, $nonce:ident| $derive:expr) => {
+        impl AeadVectored for $cipher {
+            fn encrypt_vectored_detached(
+                &self,
+                nonce: &Nonce<Self>,
+                associated_data: &[&[u8]],
+                buffers: &mut [&mut [u8]],
+            ) -> Result<Tag<Self>, Error> {
+                let ($this, $nonce) = (self, nonce);
+                let (cipher, nonce) = $derive;
+                with_gathered(associated_data, buffers, |aad, buffer| {
+                    cipher.encrypt_in_place_detached(nonce.as_ref(), aad, buffer)
+                })
+            }
+
+            fn decrypt_vectored_detached(
+                &self,
+                nonce: &Nonce<Self>,
+                associated_data: &[&[u8]],
+                buffers: &mut [&mut [u8]],
+                tag: &Tag<Self>,
+            ) -> Result<(), Error> {
+                let ($this, $nonce) = (self, nonce);
+                let (cipher, nonce) = $derive;
+                with_gathered(associated_data, buffers, |aad, buffer| {
+                    cipher.decrypt_in_place_detached(nonce.as_ref(), aad, buffer, tag)
+                })
+            }
+        }
+    };
+}
+
+impl_vectored!(Aes128Gcm, |this, nonce| (&this.0, nonce.as_slice()));
+impl_vectored!(Aes256Gcm, |this, nonce| (&this.0, nonce.as_slice()));
+impl_vectored!(ChaCha20Poly1305, |this, nonce| (&this.0, nonce.as_slice()));
+impl_vectored!(AnyAead, |this, nonce| (&this.cipher, nonce.as_slice()));
+impl_vectored!(XChaCha20Poly1305, |this, nonce| this.derive(nonce));
+
+/// Invoke `f` with the associated data and message each as a contiguous
+/// slice, copying through a bounce buffer only when they are fragmented.
+fn with_gathered<T>(
+    associated_data: &[&[u8]],
+    buffers: &mut [&mut [u8]],
+    f: impl FnOnce(&[u8], &mut [u8]) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let aad = gather(associated_data);
+    let mut fragments = buffers.iter_mut().filter(|buffer| !buffer.is_empty());
+
+    match (fragments.next(), fragments.next()) {
+        (None, _) => f(&aad, &mut []),
+        (Some(buffer), None) => f(&aad, buffer),
+        _ => {
+            let mut bounce: Vec<u8> = buffers.iter().flat_map(|b| b.iter().copied()).collect();
+            let result = f(&aad, &mut bounce);
+
+            if result.is_ok() {
+                let mut remaining = &bounce[..];
+                for buffer in buffers.iter_mut() {
+                    let (chunk, rest) = remaining.split_at(buffer.len());
+                    buffer.copy_from_slice(chunk);
+                    remaining = rest;
+                }
+            }
+
+            #[cfg(feature = "zeroize")]
+            bounce.zeroize();
+
+            result
+        }
+    }
+}
+
+/// Concatenate slices, borrowing rather than copying if at most one is
+/// non-empty.
+fn gather<'a>(slices: &[&'a [u8]]) -> Cow<'a, [u8]> {
+    let mut non_empty = slices.iter().filter(|slice| !slice.is_empty());
+
+    match (non_empty.next(), non_empty.next()) {
+        (None, _) => Cow::Borrowed(&[]),
+        (Some(slice), None) => Cow::Borrowed(slice),
+        _ => Cow::Owned(slices.concat()),
+    }
+}
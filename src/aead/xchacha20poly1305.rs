@@ -0,0 +1,164 @@
+//! XChaCha20Poly1305: ChaCha20Poly1305 with an extended 192-bit nonce
+//!
+//! *ring* doesn't provide XChaCha20Poly1305, so the HChaCha20 subkey
+//! derivation is implemented here, and the bulk encryption is performed by
+//! *ring*'s ChaCha20Poly1305 under the derived subkey.
+//!
+//! <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha-03>
+
+use super::{AeadCore, AeadInPlace, Buffer, Cipher, Error, KeyInit, KeySizeUser, Tag};
+use aead::{
+    consts::{U0, U16, U24, U32},
+    generic_array::GenericArray,
+};
+use core::fmt;
+use ring::aead::{UnboundKey, CHACHA20_POLY1305};
+
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// XChaCha20Poly1305
+#[derive(Clone)]
+pub struct XChaCha20Poly1305 {
+    key: [u8; 32],
+}
+
+impl KeySizeUser for XChaCha20Poly1305 {
+    type KeySize = U32;
+}
+
+impl KeyInit for XChaCha20Poly1305 {
+    fn new(key: &GenericArray<u8, Self::KeySize>) -> Self {
+        Self { key: (*key).into() }
+    }
+}
+
+impl AeadCore for XChaCha20Poly1305 {
+    type NonceSize = U24;
+    type TagSize = U16;
+    type CiphertextOverhead = U0;
+}
+
+impl AeadInPlace for XChaCha20Poly1305 {
+    fn encrypt_in_place_detached(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<Tag, Error> {
+        let (cipher, nonce) = self.derive(nonce);
+        cipher.encrypt_in_place_detached(&nonce, associated_data, buffer)
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut dyn Buffer,
+    ) -> Result<(), Error> {
+        let (cipher, nonce) = self.derive(nonce);
+        cipher.decrypt_in_place(&nonce, associated_data, buffer)
+    }
+
+    fn decrypt_in_place_detached(
+        &self,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &Tag,
+    ) -> Result<(), Error> {
+        let (cipher, nonce) = self.derive(nonce);
+        cipher.decrypt_in_place_detached(&nonce, associated_data, buffer, tag)
+    }
+}
+
+impl XChaCha20Poly1305 {
+    /// Derive the ChaCha20Poly1305 cipher and 96-bit nonce for a given
+    /// extended nonce.
+    pub(super) fn derive(&self, nonce: &GenericArray<u8, U24>) -> (Cipher, [u8; 12]) {
+        let (hchacha_nonce, chacha_nonce) = nonce.split_at(16);
+        let subkey = hchacha20(&self.key, hchacha_nonce.try_into().unwrap());
+
+        #[cfg(feature = "zeroize")]
+        let subkey = Zeroizing::new(subkey);
+
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &subkey[..]).unwrap();
+
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(chacha_nonce);
+        (Cipher::new(key), nonce)
+    }
+}
+
+impl fmt::Debug for XChaCha20Poly1305 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XChaCha20Poly1305").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for XChaCha20Poly1305 {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for XChaCha20Poly1305 {}
+
+/// HChaCha20 as described in draft-irtf-cfrg-xchacha Section 2.2
+fn hchacha20(key: &[u8; 32], nonce: &[u8; 16]) -> [u8; 32] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+
+    for (word, chunk) in state[4..12].iter_mut().zip(key.chunks_exact(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+
+    for (word, chunk) in state[12..].iter_mut().zip(nonce.chunks_exact(4)) {
+        *word = u32::from_le_bytes(chunk.try_into().unwrap());
+    }
+
+    for _ in 0..10 {
+        // Column rounds
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+
+        // Diagonal rounds
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut output = [0u8; 32];
+
+    for (chunk, word) in output
+        .chunks_exact_mut(4)
+        .zip(state[..4].iter().chain(&state[12..]))
+    {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+
+    #[cfg(feature = "zeroize")]
+    state.zeroize();
+
+    output
+}
+
+/// ChaCha quarter round
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
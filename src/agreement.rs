@@ -0,0 +1,39 @@
+//! Key agreement with static private keys
+//!
+//! *ring*'s `agreement` module only supports ephemeral private keys, each of
+//! which can be used in a single key agreement. The protocols in this crate
+//! which decrypt to a long-term key therefore take it as a [`PrivateKey`],
+//! which is implemented for keys held elsewhere, e.g. by another X25519 or
+//! elliptic curve implementation or in a hardware token.
+
+pub use ring::{
+    agreement::{Algorithm, ECDH_P256, ECDH_P384, X25519},
+    error::Unspecified,
+};
+
+/// Static private key which can be used in any number of key agreements.
+pub trait PrivateKey {
+    /// Key agreement algorithm of this key: [`X25519`], [`ECDH_P256`] or
+    /// [`ECDH_P384`].
+    fn algorithm(&self) -> &'static Algorithm;
+
+    /// Public key: a raw X25519 public key or an uncompressed SEC1 point.
+    fn public_key(&self) -> &[u8];
+
+    /// Compute the shared secret with a peer's public key into
+    /// `shared_secret`.
+    ///
+    /// The peer's public key is a raw X25519 public key or a compressed or
+    /// uncompressed SEC1 point. The shared secret is the X25519 output or the
+    /// x-coordinate of the shared point, i.e. 32 bytes for X25519 and P-256
+    /// and 48 bytes for P-384.
+    ///
+    /// Implementations must reject invalid public keys and a `shared_secret`
+    /// of the wrong size, and X25519 implementations must reject the
+    /// all-zero output.
+    fn diffie_hellman(
+        &self,
+        peer_public_key: &[u8],
+        shared_secret: &mut [u8],
+    ) -> Result<(), Unspecified>;
+}
@@ -11,13 +11,22 @@
 //!
 //! Functionality in this crate is gated under the following features:
 //!
-//! - `aead`: Authenticated Encryption with Associated Data algorithms: AES-GCM, ChaCha20Poly1305
+//! - `aead`: Authenticated Encryption with Associated Data algorithms: AES-GCM, ChaCha20Poly1305,
+//!   XChaCha20Poly1305
 //! - `digest`: Cryptographic Hash Functions: SHA-1, SHA-256, SHA-384, SHA-512, SHA-512/256
+//! - `stream`: STREAM online AEAD for encrypting large messages in segments
+//! - `std`: [`std::io`] adapters for encrypting and decrypting streams
 //! - `signature`: Digital Signature Algorithms, gated under the following features:
 //!   - `ecdsa`: Elliptic Curve Digital Signature Algorithm
 //!   - `ed25519`: Edwards Digital Signature Algorithm instantiated over Curve25519
 //!   - `p256`: ECDSA/NIST P-256
 //!   - `p384`: ECDSA/NIST P-384
+//! - `zeroize`: wipe secret keys held by this crate when they're dropped. Key
+//!   schedules held by *ring* (AEAD keys, ECDSA and Ed25519 key pairs) can't
+//!   be wiped, as *ring* doesn't provide access to them
+
+#[cfg(all(feature = "aead", feature = "alloc"))]
+extern crate alloc;
 
 #[cfg(feature = "std")]
 extern crate std;
@@ -25,6 +34,8 @@ extern crate std;
 #[cfg(feature = "aead")]
 pub mod aead;
 
+pub mod agreement;
+
 #[cfg(feature = "digest")]
 pub mod digest;
 
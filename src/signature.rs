@@ -3,4 +3,10 @@
 pub mod ecdsa;
 pub mod ed25519;
 
+#[cfg(all(feature = "aead", feature = "alloc"))]
+pub mod encrypted_key;
+
+#[cfg(all(feature = "aead", feature = "alloc", feature = "digest"))]
+pub mod ecies;
+
 pub use ::signature::{Error, Keypair, SignatureEncoding, Signer, Verifier};
@@ -2,20 +2,26 @@
 
 use super::{CurveAlg, Signature, VerifyingKey};
 use crate::signature::{Error, Keypair, Signer};
-use core::marker::PhantomData;
+use core::{fmt, marker::PhantomData};
 use ecdsa::{
-    elliptic_curve::{sec1, FieldBytesSize},
+    elliptic_curve::{sec1, FieldBytes, FieldBytesSize},
     SignatureSize,
 };
-use generic_array::ArrayLength;
-use pkcs8::DecodePrivateKey;
+use generic_array::{typenum::Unsigned, ArrayLength};
+use pkcs8::{der::Decode, DecodePrivateKey};
 use ring::{
     self,
     rand::SystemRandom,
     signature::{EcdsaKeyPair, KeyPair as _},
 };
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 /// ECDSA signing key. Generic over elliptic curves.
+///
+/// With the `zeroize` feature, the retained copy of the secret scalar is
+/// wiped on drop. *ring*'s key pair, which holds its own copy, isn't.
 pub struct SigningKey<C>
 where
     C: CurveAlg,
@@ -24,6 +30,9 @@ where
     /// *ring* ECDSA keypair
     keypair: EcdsaKeyPair,
 
+    /// Secret scalar, retained for serialization and cloning
+    secret_key: FieldBytes<C>,
+
     /// Cryptographically secure random number generator
     csrng: SystemRandom,
 
@@ -38,6 +47,10 @@ where
 {
     /// Initialize a [`SigningKey`] from a raw keypair
     pub fn from_keypair_bytes(signing_key: &[u8], verifying_key: &[u8]) -> Result<Self, Error> {
+        if signing_key.len() != FieldBytesSize::<C>::USIZE {
+            return Err(Error::new());
+        }
+
         let csrng = SystemRandom::new();
 
         let keypair = EcdsaKeyPair::from_private_key_and_public_key(
@@ -50,6 +63,7 @@ where
 
         Ok(Self {
             keypair,
+            secret_key: FieldBytes::<C>::clone_from_slice(signing_key),
             csrng,
             curve: PhantomData,
         })
@@ -64,6 +78,38 @@ where
     }
 }
 
+impl<C> Clone for SigningKey<C>
+where
+    C: CurveAlg,
+    SignatureSize<C>: ArrayLength<u8>,
+{
+    fn clone(&self) -> Self {
+        Self::from_keypair_bytes(&self.secret_key, self.keypair.public_key().as_ref())
+            .expect("keypair should have been validated")
+    }
+}
+
+impl<C> fmt::Debug for SigningKey<C>
+where
+    C: CurveAlg,
+    SignatureSize<C>: ArrayLength<u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<C> Drop for SigningKey<C>
+where
+    C: CurveAlg,
+    SignatureSize<C>: ArrayLength<u8>,
+{
+    fn drop(&mut self) {
+        self.secret_key.as_mut_slice().zeroize();
+    }
+}
+
 impl<C> DecodePrivateKey for SigningKey<C>
 where
     C: CurveAlg,
@@ -75,14 +121,52 @@ where
         let keypair = EcdsaKeyPair::from_pkcs8(C::signing_alg(), pkcs8_bytes, &csrng)
             .map_err(|_| pkcs8::Error::KeyMalformed)?;
 
+        // *ring* has validated the document, so extract the secret scalar
+        let private_key_info = pkcs8::PrivateKeyInfo::try_from(pkcs8_bytes)?;
+        let ec_private_key = ::sec1::EcPrivateKey::from_der(private_key_info.private_key)
+            .map_err(|_| pkcs8::Error::KeyMalformed)?;
+
+        if ec_private_key.private_key.len() != FieldBytesSize::<C>::USIZE {
+            return Err(pkcs8::Error::KeyMalformed);
+        }
+
         Ok(Self {
             keypair,
+            secret_key: FieldBytes::<C>::clone_from_slice(ec_private_key.private_key),
             csrng,
             curve: PhantomData,
         })
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<C> pkcs8::EncodePrivateKey for SigningKey<C>
+where
+    C: CurveAlg + pkcs8::AssociatedOid,
+    SignatureSize<C>: ArrayLength<u8>,
+{
+    fn to_pkcs8_der(&self) -> pkcs8::Result<pkcs8::SecretDocument> {
+        use pkcs8::der::Encode;
+
+        let ec_private_key = ::sec1::EcPrivateKey {
+            private_key: &self.secret_key,
+            parameters: None,
+            public_key: Some(self.keypair.public_key().as_ref()),
+        }
+        .to_der()?;
+
+        #[cfg(feature = "zeroize")]
+        let ec_private_key = zeroize::Zeroizing::new(ec_private_key);
+
+        let algorithm = pkcs8::AlgorithmIdentifierRef {
+            oid: ecdsa::elliptic_curve::ALGORITHM_OID,
+            parameters: Some((&C::OID).into()),
+        };
+
+        pkcs8::PrivateKeyInfo::new(algorithm, &ec_private_key[..]).try_into()
+    }
+}
+
 impl<C> Keypair for SigningKey<C>
 where
     C: CurveAlg,
@@ -6,8 +6,7 @@ use ::ecdsa::{
     elliptic_curve::{sec1, FieldBytesSize},
     SignatureSize,
 };
-use core::convert::TryInto;
-use generic_array::{typenum::Unsigned, ArrayLength};
+use generic_array::{typenum::Unsigned, ArrayLength, GenericArray};
 use ring::signature::UnparsedPublicKey;
 
 /// ECDSA verifying key. Generic over elliptic curves.
@@ -28,7 +27,7 @@ where
     pub fn new(bytes: &[u8]) -> Result<Self, Error> {
         let point_result = if bytes.len() == C::FieldBytesSize::USIZE * 2 {
             Ok(sec1::EncodedPoint::<C>::from_untagged_bytes(
-                bytes.try_into().map_err(|_| Error::new())?,
+                GenericArray::from_slice(bytes),
             ))
         } else {
             sec1::EncodedPoint::<C>::from_bytes(bytes)
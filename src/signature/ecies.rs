@@ -0,0 +1,263 @@
+//! Elliptic Curve Integrated Encryption Scheme (ECIES) to ECDSA public keys
+//!
+//! Encrypts messages to the holder of a P-256 or P-384 [`VerifyingKey`] in
+//! the style of [SEC 1] section 5.1:
+//!
+//! 1. An ephemeral key is generated with *ring*'s `agreement` module and
+//!    combined with the recipient's public key using ECDH.
+//! 2. The shared secret is expanded into an AEAD key and nonce with the
+//!    ANSI X9.63 KDF, using the ephemeral public key as `SharedInfo`.
+//! 3. The message is encrypted with [`Aes128Gcm`] or [`Aes256Gcm`].
+//!
+//! The hash function used by the KDF is a type parameter, e.g. [`Sha256`] or
+//! [`Sha384`] from this crate.
+//!
+//! Decryption uses the recipient's static [`PrivateKey`], which must have the
+//! agreement algorithm of the curve.
+//!
+//! # Ciphertext format
+//!
+//! ```text
+//! ephemeral public key (compressed SEC1 point, 33 or 49 bytes) || ciphertext || tag (16 bytes)
+//! ```
+//!
+//! [SEC 1]: https://www.secg.org/sec1-v2.pdf
+//! [`Sha256`]: crate::digest::Sha256
+//! [`Sha384`]: crate::digest::Sha384
+
+use super::ecdsa::{p256::NistP256, p384::NistP384, CurveAlg, VerifyingKey};
+use crate::{
+    aead::{AeadCore, AeadInPlace, Aes128Gcm, Aes256Gcm, KeyInit, Tag},
+    agreement::PrivateKey,
+};
+use aead::consts::{U12, U16};
+use alloc::vec::Vec;
+use core::fmt;
+use digest::{FixedOutput, Update};
+use ecdsa::{
+    elliptic_curve::{sec1, FieldBytes, FieldBytesSize},
+    SignatureSize,
+};
+use generic_array::{typenum::Unsigned, ArrayLength, GenericArray};
+use ring::{agreement, rand::SystemRandom};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Size of a nonce
+const NONCE_SIZE: usize = 12;
+
+/// Size of a tag
+const TAG_SIZE: usize = 16;
+
+/// Maximum size of the derived key and nonce (AES-256 key and nonce)
+const MAX_KEY_MATERIAL_SIZE: usize = 32 + NONCE_SIZE;
+
+/// Errors returned by ECIES operations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EciesError {
+    /// The recipient's public key or the ephemeral public key is invalid, or
+    /// the private key isn't on the curve
+    InvalidKey,
+
+    /// Ciphertext is too short or its ephemeral public key isn't compressed
+    Malformed,
+
+    /// Encryption failed, e.g. because the message is too long
+    Encryption,
+
+    /// Decryption failed: the private key is wrong or the ciphertext is
+    /// corrupt
+    Decryption,
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for EciesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKey => f.write_str("invalid ECIES public key"),
+            Self::Malformed => f.write_str("malformed ECIES ciphertext"),
+            Self::Encryption => f.write_str("ECIES encryption failed"),
+            Self::Decryption => f.write_str("ECIES decryption failed"),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EciesError {}
+
+/// Elliptic curves which support ECDH with *ring*.
+pub trait EcdhCurve: CurveAlg {
+    /// *ring* key agreement algorithm of ephemeral and private keys
+    fn agreement_alg() -> &'static agreement::Algorithm;
+}
+
+impl EcdhCurve for NistP256 {
+    fn agreement_alg() -> &'static agreement::Algorithm {
+        &agreement::ECDH_P256
+    }
+}
+
+impl EcdhCurve for NistP384 {
+    fn agreement_alg() -> &'static agreement::Algorithm {
+        &agreement::ECDH_P384
+    }
+}
+
+/// AEAD algorithms which can be used with ECIES.
+pub trait EciesAead: AeadInPlace + AeadCore<NonceSize = U12, TagSize = U16> + KeyInit {}
+
+impl EciesAead for Aes128Gcm {}
+
+impl EciesAead for Aes256Gcm {}
+
+/// Encrypt a message to the holder of the given public key, using the hash
+/// function `D` for the KDF and the AEAD `A`.
+///
+/// The recipient's public key must be uncompressed.
+pub fn encrypt<C, D, A>(
+    recipient: &VerifyingKey<C>,
+    plaintext: &[u8],
+    associated_data: &[u8],
+) -> Result<Vec<u8>, EciesError>
+where
+    C: EcdhCurve,
+    D: Default + FixedOutput + Update,
+    A: EciesAead,
+    FieldBytesSize<C>: sec1::ModulusSize,
+    SignatureSize<C>: ArrayLength<u8>,
+{
+    let rng = SystemRandom::new();
+    let private_key = agreement::EphemeralPrivateKey::generate(C::agreement_alg(), &rng)
+        .map_err(|_| EciesError::Rng)?;
+    let public_key = private_key
+        .compute_public_key()
+        .map_err(|_| EciesError::Rng)?;
+
+    let mut out = compress(public_key.as_ref());
+    let peer_public_key =
+        agreement::UnparsedPublicKey::new(C::agreement_alg(), recipient.as_bytes());
+
+    let (cipher, nonce) =
+        agreement::agree_ephemeral(private_key, &peer_public_key, |shared_secret| {
+            derive_cipher::<D, A>(shared_secret, &out)
+        })
+        .map_err(|_| EciesError::InvalidKey)?;
+
+    let header_len = out.len();
+    out.extend_from_slice(plaintext);
+
+    let tag = cipher
+        .encrypt_in_place_detached(&nonce.into(), associated_data, &mut out[header_len..])
+        .map_err(|_| EciesError::Encryption)?;
+
+    out.extend_from_slice(&tag);
+    Ok(out)
+}
+
+/// Decrypt a message with the recipient's private key, using the hash
+/// function `D` for the KDF and the AEAD `A`.
+pub fn decrypt<C, D, A>(
+    private_key: &dyn PrivateKey,
+    ciphertext: &[u8],
+    associated_data: &[u8],
+) -> Result<Vec<u8>, EciesError>
+where
+    C: EcdhCurve,
+    D: Default + FixedOutput + Update,
+    A: EciesAead,
+{
+    let public_key_len = FieldBytesSize::<C>::USIZE + 1;
+
+    if ciphertext.len() < public_key_len + TAG_SIZE {
+        return Err(EciesError::Malformed);
+    }
+
+    let (public_key, ciphertext) = ciphertext.split_at(public_key_len);
+
+    if !matches!(public_key[0], 0x02 | 0x03) {
+        return Err(EciesError::Malformed);
+    }
+
+    if private_key.algorithm() != C::agreement_alg() {
+        return Err(EciesError::InvalidKey);
+    }
+
+    let mut shared_secret = FieldBytes::<C>::default();
+    let result = private_key
+        .diffie_hellman(public_key, &mut shared_secret)
+        .map(|()| derive_cipher::<D, A>(&shared_secret, public_key));
+
+    #[cfg(feature = "zeroize")]
+    shared_secret.as_mut_slice().zeroize();
+
+    let (cipher, nonce) = result.map_err(|_| EciesError::InvalidKey)?;
+
+    let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_SIZE);
+    let mut buffer = ciphertext.to_vec();
+
+    cipher
+        .decrypt_in_place_detached(
+            &nonce.into(),
+            associated_data,
+            &mut buffer,
+            Tag::from_slice(tag),
+        )
+        .map_err(|_| EciesError::Decryption)?;
+
+    Ok(buffer)
+}
+
+/// Compress an uncompressed SEC1 point.
+fn compress(public_key: &[u8]) -> Vec<u8> {
+    let coordinate_len = (public_key.len() - 1) / 2;
+    let (x, y) = public_key[1..].split_at(coordinate_len);
+
+    let mut out = Vec::with_capacity(coordinate_len + 1);
+    out.push(0x02 | (y[coordinate_len - 1] & 1));
+    out.extend_from_slice(x);
+    out
+}
+
+/// Derive the AEAD key and nonce from the shared secret.
+fn derive_cipher<D, A>(shared_secret: &[u8], shared_info: &[u8]) -> (A, [u8; NONCE_SIZE])
+where
+    D: Default + FixedOutput + Update,
+    A: EciesAead,
+{
+    let key_len = A::KeySize::USIZE;
+    let mut key_material = [0u8; MAX_KEY_MATERIAL_SIZE];
+    x963_kdf::<D>(
+        shared_secret,
+        shared_info,
+        &mut key_material[..key_len + NONCE_SIZE],
+    );
+
+    let (key, nonce) = key_material.split_at(key_len);
+    let mut nonce_bytes = [0u8; NONCE_SIZE];
+    nonce_bytes.copy_from_slice(&nonce[..NONCE_SIZE]);
+    let cipher = A::new(GenericArray::from_slice(key));
+
+    #[cfg(feature = "zeroize")]
+    key_material.zeroize();
+
+    (cipher, nonce_bytes)
+}
+
+/// ANSI X9.63 key derivation function, as specified in SEC 1 section 3.6.1.
+fn x963_kdf<D>(shared_secret: &[u8], shared_info: &[u8], out: &mut [u8])
+where
+    D: Default + FixedOutput + Update,
+{
+    for (counter, chunk) in (1u32..).zip(out.chunks_mut(D::output_size())) {
+        let mut hasher = D::default();
+        hasher.update(shared_secret);
+        hasher.update(&counter.to_be_bytes());
+        hasher.update(shared_info);
+        chunk.copy_from_slice(&hasher.finalize_fixed()[..chunk.len()]);
+    }
+}
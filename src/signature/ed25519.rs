@@ -6,7 +6,7 @@ pub use ed25519::Signature;
 
 use super::{Error, Signer, Verifier};
 use crate::signature::Keypair;
-use core::convert::TryInto;
+use core::{convert::TryInto, fmt};
 use ring::{
     self,
     signature::{Ed25519KeyPair, KeyPair as _, UnparsedPublicKey},
@@ -18,7 +18,13 @@ use ed25519::pkcs8;
 #[cfg(feature = "rand_core")]
 use rand_core::CryptoRngCore;
 
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
 /// Ed25519 signing key.
+///
+/// With the `zeroize` feature, the retained copy of the seed is wiped on
+/// drop. *ring*'s key pair, which holds the expanded private key, isn't.
 pub struct SigningKey {
     keypair: Ed25519KeyPair,
     seed: [u8; Self::SIZE],
@@ -82,6 +88,21 @@ impl Clone for SigningKey {
     }
 }
 
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("verifying_key", &self.verifying_key())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SigningKey {
+    fn drop(&mut self) {
+        self.seed.zeroize();
+    }
+}
+
 impl Keypair for SigningKey {
     type VerifyingKey = VerifyingKey;
 
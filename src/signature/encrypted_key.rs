@@ -0,0 +1,235 @@
+//! Password-protected private key storage
+//!
+//! Signing keys are serialized as PKCS#8 and encrypted with AES-256-GCM under
+//! a key derived from a password with PBKDF2-HMAC-SHA256.
+//!
+//! # Container format
+//!
+//! ```text
+//! "RCEK" || version (1 byte, 0x01) || iterations (4 bytes) || salt length (1 byte)
+//!   || salt || nonce (12 bytes) || ciphertext || tag (16 bytes)
+//! ```
+//!
+//! The iteration count is big endian. Everything which precedes the
+//! ciphertext is authenticated as associated data.
+
+use crate::aead::{AeadInPlace, Aes256Gcm, KeyInit};
+use alloc::vec::Vec;
+use core::{fmt, num::NonZeroU32};
+use generic_array::GenericArray;
+use pkcs8::{DecodePrivateKey, EncodePrivateKey, SecretDocument};
+use ring::{
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Default number of PBKDF2 iterations
+pub const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// Maximum number of PBKDF2 iterations accepted by [`decrypt`] and
+/// [`decrypt_pkcs8`]
+pub const DEFAULT_MAX_ITERATIONS: u32 = 10_000_000;
+
+/// Magic bytes which identify a container
+const MAGIC: &[u8; 4] = b"RCEK";
+
+/// Current container format version
+const VERSION: u8 = 0x01;
+
+/// Size of the random salt
+const SALT_SIZE: usize = 16;
+
+/// Size of a nonce
+const NONCE_SIZE: usize = 12;
+
+/// Size of a tag
+const TAG_SIZE: usize = 16;
+
+/// Errors returned when encrypting or decrypting keys.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EncryptedKeyError {
+    /// Container is malformed or has an unsupported version
+    Malformed,
+
+    /// Encryption failed
+    Encryption,
+
+    /// Decryption failed: the password is wrong or the container is corrupt
+    Decryption,
+
+    /// The container's iteration count exceeds the maximum
+    TooManyIterations,
+
+    /// Key could not be encoded or decoded as PKCS#8
+    Pkcs8(pkcs8::Error),
+
+    /// The system random number generator failed
+    Rng,
+}
+
+impl fmt::Display for EncryptedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed encrypted key"),
+            Self::Encryption => f.write_str("key encryption failed"),
+            Self::Decryption => f.write_str("wrong password or corrupted encrypted key"),
+            Self::TooManyIterations => f.write_str("too many PBKDF2 iterations"),
+            Self::Pkcs8(err) => write!(f, "PKCS#8 error: {}", err),
+            Self::Rng => f.write_str("random number generation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncryptedKeyError {}
+
+impl From<pkcs8::Error> for EncryptedKeyError {
+    fn from(err: pkcs8::Error) -> EncryptedKeyError {
+        EncryptedKeyError::Pkcs8(err)
+    }
+}
+
+/// Encrypt a signing key under a password using [`DEFAULT_ITERATIONS`].
+pub fn encrypt(key: &impl EncodePrivateKey, password: &[u8]) -> Result<Vec<u8>, EncryptedKeyError> {
+    let iterations = NonZeroU32::new(DEFAULT_ITERATIONS).unwrap();
+    encrypt_with_iterations(key, password, iterations)
+}
+
+/// Encrypt a signing key under a password using the given number of PBKDF2
+/// iterations.
+pub fn encrypt_with_iterations(
+    key: &impl EncodePrivateKey,
+    password: &[u8],
+    iterations: NonZeroU32,
+) -> Result<Vec<u8>, EncryptedKeyError> {
+    let document = key.to_pkcs8_der()?;
+    let rng = SystemRandom::new();
+
+    let mut salt = [0u8; SALT_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    rng.fill(&mut salt).map_err(|_| EncryptedKeyError::Rng)?;
+    rng.fill(&mut nonce).map_err(|_| EncryptedKeyError::Rng)?;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&iterations.get().to_be_bytes());
+    out.push(SALT_SIZE as u8);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+
+    let header_len = out.len();
+    out.extend_from_slice(document.as_bytes());
+
+    let (header, buffer) = out.split_at_mut(header_len);
+    let tag = derive_cipher(password, &salt, iterations)
+        .encrypt_in_place_detached(&nonce.into(), header, buffer)
+        .map_err(|_| EncryptedKeyError::Encryption)?;
+
+    out.extend_from_slice(&tag);
+    Ok(out)
+}
+
+/// Decrypt a signing key, e.g. an [`ed25519::SigningKey`] or
+/// [`ecdsa::SigningKey`], accepting at most [`DEFAULT_MAX_ITERATIONS`].
+///
+/// [`ed25519::SigningKey`]: super::ed25519::SigningKey
+/// [`ecdsa::SigningKey`]: super::ecdsa::SigningKey
+pub fn decrypt<K: DecodePrivateKey>(
+    container: &[u8],
+    password: &[u8],
+) -> Result<K, EncryptedKeyError> {
+    let max_iterations = NonZeroU32::new(DEFAULT_MAX_ITERATIONS).unwrap();
+    decrypt_with_max_iterations(container, password, max_iterations)
+}
+
+/// Decrypt a signing key, accepting at most the given number of PBKDF2
+/// iterations.
+pub fn decrypt_with_max_iterations<K: DecodePrivateKey>(
+    container: &[u8],
+    password: &[u8],
+    max_iterations: NonZeroU32,
+) -> Result<K, EncryptedKeyError> {
+    let document = decrypt_pkcs8_with_max_iterations(container, password, max_iterations)?;
+    Ok(K::from_pkcs8_der(document.as_bytes())?)
+}
+
+/// Decrypt the PKCS#8 document held in a container, accepting at most
+/// [`DEFAULT_MAX_ITERATIONS`].
+pub fn decrypt_pkcs8(
+    container: &[u8],
+    password: &[u8],
+) -> Result<SecretDocument, EncryptedKeyError> {
+    let max_iterations = NonZeroU32::new(DEFAULT_MAX_ITERATIONS).unwrap();
+    decrypt_pkcs8_with_max_iterations(container, password, max_iterations)
+}
+
+/// Decrypt the PKCS#8 document held in a container, accepting at most the
+/// given number of PBKDF2 iterations.
+///
+/// The iteration count is read from the container, so the maximum bounds
+/// the cost of opening containers from untrusted sources.
+pub fn decrypt_pkcs8_with_max_iterations(
+    container: &[u8],
+    password: &[u8],
+    max_iterations: NonZeroU32,
+) -> Result<SecretDocument, EncryptedKeyError> {
+    let (iterations, salt, rest) = match container {
+        [m0, m1, m2, m3, VERSION, i0, i1, i2, i3, salt_len, rest @ ..]
+            if [*m0, *m1, *m2, *m3] == *MAGIC && rest.len() >= usize::from(*salt_len) =>
+        {
+            let iterations = NonZeroU32::new(u32::from_be_bytes([*i0, *i1, *i2, *i3]))
+                .ok_or(EncryptedKeyError::Malformed)?;
+            let (salt, rest) = rest.split_at(usize::from(*salt_len));
+            (iterations, salt, rest)
+        }
+        _ => return Err(EncryptedKeyError::Malformed),
+    };
+
+    if rest.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(EncryptedKeyError::Malformed);
+    }
+
+    if iterations > max_iterations {
+        return Err(EncryptedKeyError::TooManyIterations);
+    }
+
+    let header_len = container.len() - rest.len() + NONCE_SIZE;
+    let (nonce, rest) = rest.split_at(NONCE_SIZE);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_SIZE);
+    let mut buffer = ciphertext.to_vec();
+
+    derive_cipher(password, salt, iterations)
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            &container[..header_len],
+            &mut buffer,
+            GenericArray::from_slice(tag),
+        )
+        .map_err(|_| EncryptedKeyError::Decryption)?;
+
+    SecretDocument::try_from(buffer).map_err(|err| EncryptedKeyError::Pkcs8(err.into()))
+}
+
+/// Derive the AES-256-GCM key-encryption key from a password.
+fn derive_cipher(password: &[u8], salt: &[u8], iterations: NonZeroU32) -> Aes256Gcm {
+    let mut key = GenericArray::default();
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        password,
+        &mut key,
+    );
+
+    let cipher = Aes256Gcm::new(&key);
+
+    #[cfg(feature = "zeroize")]
+    key.as_mut_slice().zeroize();
+
+    cipher
+}
@@ -0,0 +1,212 @@
+//! age tests
+//!
+//! The files in `data/age` are the X25519 and format vectors of the age
+//! testkit from C2SP CCTV: <https://github.com/C2SP/CCTV/tree/main/age>
+//!
+//! Each starts with a few `key: value` lines followed by an empty line and the
+//! age file. The ASCII armor and scrypt vectors are omitted, since neither is
+//! supported.
+
+use crate::agreement::{P256PrivateKey, X25519PrivateKey};
+use ring::digest::{digest, SHA256};
+use ring_compat::{
+    aead::age::{self, Error, Recipient},
+    agreement::PrivateKey,
+};
+
+/// Identity with the X25519 scalar 0x42 repeated
+const IDENTITY: &str = "AGE-SECRET-KEY-1GFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPYYSJZGFPQ4EGAEX";
+
+/// Recipient which corresponds to [`IDENTITY`]
+const RECIPIENT: &str = "age1zvkyg2lqzraa2lnjvqej32nkuu0ues2s82hzrye869xeexvn73equnujwj";
+
+/// Parse an identity as an `x25519-dalek` private key.
+fn parse_identity(s: &str) -> X25519PrivateKey {
+    X25519PrivateKey::new(age::decode_identity(s).unwrap())
+}
+
+/// Get the recipient which corresponds to an identity.
+fn to_public(identity: &X25519PrivateKey) -> Recipient {
+    Recipient::from_bytes(identity.public_key().try_into().unwrap())
+}
+
+/// Run a testkit vector, checking the outcome named by its `expect` line.
+fn run_vector(name: &str, vector: &[u8]) {
+    let separator = vector
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .expect("missing end of testkit header");
+    let (metadata, file) = (&vector[..separator], &vector[separator + 2..]);
+
+    let mut expect = None;
+    let mut payload_hash = None;
+    let mut identities = Vec::new();
+
+    for line in std::str::from_utf8(metadata).unwrap().lines() {
+        let (key, value) = line.split_once(": ").unwrap();
+
+        match key {
+            "expect" => expect = Some(value),
+            "payload" => payload_hash = Some(value),
+            "identity" => identities.push(parse_identity(value)),
+            _ => (),
+        }
+    }
+
+    let identities: Vec<&dyn PrivateKey> = identities.iter().map(|i| i as _).collect();
+    let result = age::decrypt(&identities, file);
+
+    match expect.unwrap() {
+        "success" => {
+            let plaintext = result.unwrap_or_else(|err| panic!("{}: {:?}", name, err));
+            let hash = digest(&SHA256, &plaintext);
+            let hex: String = hash.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(Some(hex.as_str()), payload_hash, "{}", name);
+        }
+        "no match" => assert_eq!(result, Err(Error::NoMatchingKeys), "{}", name),
+        "HMAC failure" => assert_eq!(result, Err(Error::HeaderMac), "{}", name),
+        "header failure" => assert_eq!(result, Err(Error::Header), "{}", name),
+        "payload failure" => assert_eq!(result, Err(Error::Payload), "{}", name),
+        other => panic!("{}: unknown expectation {}", name, other),
+    }
+}
+
+macro_rules! testkit {
+    ($($name:ident),+) => {
+        $(
+            #[test]
+            fn $name() {
+                run_vector(
+                    stringify!($name),
+                    include_bytes!(concat!("data/age/", stringify!($name))),
+                );
+            }
+        )+
+    };
+}
+
+testkit!(
+    header_crlf,
+    hmac_bad,
+    hmac_extra_space,
+    hmac_garbage,
+    hmac_missing,
+    hmac_no_space,
+    hmac_not_canonical,
+    hmac_trailing_space,
+    hmac_truncated,
+    stanza_bad_start,
+    stanza_base64_padding,
+    stanza_empty_argument,
+    stanza_empty_body,
+    stanza_empty_last_line,
+    stanza_invalid_character,
+    stanza_long_line,
+    stanza_missing_body,
+    stanza_missing_final_line,
+    stanza_multiple_short_lines,
+    stanza_no_arguments,
+    stanza_not_canonical,
+    stanza_spurious_cr,
+    stanza_valid_characters,
+    stream_bad_tag,
+    stream_bad_tag_second_chunk,
+    stream_bad_tag_second_chunk_full,
+    stream_empty_payload,
+    stream_last_chunk_empty,
+    stream_last_chunk_full,
+    stream_last_chunk_full_second,
+    stream_missing_tag,
+    stream_no_chunks,
+    stream_no_final,
+    stream_no_final_full,
+    stream_no_final_two_chunks,
+    stream_no_final_two_chunks_full,
+    stream_no_nonce,
+    stream_short_chunk,
+    stream_short_nonce,
+    stream_short_second_chunk,
+    stream_three_chunks,
+    stream_trailing_garbage_long,
+    stream_trailing_garbage_short,
+    stream_two_chunks,
+    stream_two_final_chunks,
+    version_unsupported,
+    x25519,
+    x25519_bad_tag,
+    x25519_extra_argument,
+    x25519_grease,
+    x25519_identity,
+    x25519_long_file_key,
+    x25519_long_share,
+    x25519_low_order,
+    x25519_lowercase,
+    x25519_multiple_recipients,
+    x25519_no_match,
+    x25519_not_canonical_body,
+    x25519_not_canonical_share,
+    x25519_short_share
+);
+
+#[test]
+fn identity_to_public() {
+    let identity = parse_identity(IDENTITY);
+    assert_eq!(to_public(&identity).to_string(), RECIPIENT);
+    assert_eq!(to_public(&identity), RECIPIENT.parse().unwrap());
+
+    let secret = age::decode_identity(IDENTITY).unwrap();
+    assert_eq!(age::encode_identity(&secret), IDENTITY);
+}
+
+#[test]
+fn invalid_keys() {
+    // Wrong case
+    assert!(RECIPIENT.to_uppercase().parse::<Recipient>().is_err());
+    assert!(age::decode_identity(&IDENTITY.to_lowercase()).is_err());
+
+    // Wrong human-readable part
+    assert!(IDENTITY.parse::<Recipient>().is_err());
+    assert!(age::decode_identity(&RECIPIENT.to_uppercase()).is_err());
+
+    // Bad checksum
+    let mut recipient = RECIPIENT.to_owned();
+    recipient.pop();
+    recipient.push('q');
+    assert_eq!(recipient.parse::<Recipient>(), Err(Error::InvalidKey));
+}
+
+#[test]
+fn round_trip() {
+    let identities = [X25519PrivateKey::generate(), X25519PrivateKey::generate()];
+    let recipients = [to_public(&identities[0]), to_public(&identities[1])];
+
+    for len in [
+        0,
+        1,
+        age::CHUNK_SIZE - 1,
+        age::CHUNK_SIZE,
+        2 * age::CHUNK_SIZE + 1,
+    ] {
+        let plaintext = vec![0xa5; len];
+        let ciphertext = age::encrypt(&recipients, &plaintext).unwrap();
+
+        for identity in &identities {
+            assert_eq!(age::decrypt(&[identity], &ciphertext).unwrap(), plaintext);
+        }
+    }
+}
+
+#[test]
+fn no_recipients() {
+    assert_eq!(age::encrypt(&[], b"age"), Err(Error::NoRecipients));
+}
+
+#[test]
+fn non_x25519_identity() {
+    let ciphertext = age::encrypt(&[RECIPIENT.parse().unwrap()], b"age").unwrap();
+    let identity = P256PrivateKey::new(&[1; 32]);
+    assert_eq!(
+        age::decrypt(&[&identity], &ciphertext),
+        Err(Error::InvalidKey)
+    );
+}
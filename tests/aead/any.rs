@@ -0,0 +1,112 @@
+//! Runtime-selectable AEAD tests
+
+use ring_compat::{
+    aead::{
+        Aead, AeadAlgorithm, AeadInPlace, Aes128Gcm, Aes256Gcm, AnyAead, ChaCha20Poly1305, KeyInit,
+        Payload,
+    },
+    generic_array::GenericArray,
+};
+
+const ALGORITHMS: &[AeadAlgorithm] = &[
+    AeadAlgorithm::Aes128Gcm,
+    AeadAlgorithm::Aes256Gcm,
+    AeadAlgorithm::ChaCha20Poly1305,
+];
+
+const NONCE: &[u8; 12] = &[0x24; 12];
+const AAD: &[u8] = b"runtime algorithm selection";
+const PLAINTEXT: &[u8] = b"the algorithm was read from a config file";
+
+/// Encrypt the test message with the statically typed cipher for `algorithm`
+fn encrypt_static(algorithm: AeadAlgorithm, key: &[u8]) -> Vec<u8> {
+    let nonce = GenericArray::from_slice(NONCE);
+    let payload = Payload {
+        msg: PLAINTEXT,
+        aad: AAD,
+    };
+
+    match algorithm {
+        AeadAlgorithm::Aes128Gcm => {
+            Aes128Gcm::new(GenericArray::from_slice(key)).encrypt(nonce, payload)
+        }
+        AeadAlgorithm::Aes256Gcm => {
+            Aes256Gcm::new(GenericArray::from_slice(key)).encrypt(nonce, payload)
+        }
+        AeadAlgorithm::ChaCha20Poly1305 => {
+            ChaCha20Poly1305::new(GenericArray::from_slice(key)).encrypt(nonce, payload)
+        }
+        _ => unreachable!(),
+    }
+    .unwrap()
+}
+
+#[test]
+fn sizes() {
+    assert_eq!(AeadAlgorithm::Aes128Gcm.key_size(), 16);
+    assert_eq!(AeadAlgorithm::Aes256Gcm.key_size(), 32);
+    assert_eq!(AeadAlgorithm::ChaCha20Poly1305.key_size(), 32);
+
+    for algorithm in ALGORITHMS {
+        assert_eq!(algorithm.nonce_size(), 12);
+        assert_eq!(algorithm.tag_size(), 16);
+    }
+}
+
+#[test]
+fn identifiers() {
+    for &algorithm in ALGORITHMS {
+        assert_eq!(AeadAlgorithm::from_id(algorithm.id()).unwrap(), algorithm);
+        assert_eq!(
+            algorithm.to_string().parse::<AeadAlgorithm>().unwrap(),
+            algorithm
+        );
+    }
+
+    assert_eq!(
+        "CHACHA20_POLY1305".parse::<AeadAlgorithm>().unwrap(),
+        AeadAlgorithm::ChaCha20Poly1305
+    );
+    assert_eq!(AeadAlgorithm::Aes256Gcm.to_string(), "AEAD_AES_256_GCM");
+    assert!("AES_192_GCM".parse::<AeadAlgorithm>().is_err());
+    assert!(AeadAlgorithm::from_id(3).is_err());
+}
+
+#[test]
+fn matches_static_types() {
+    for &algorithm in ALGORITHMS {
+        let key = vec![0x42; algorithm.key_size()];
+        let cipher = AnyAead::new(algorithm, &key).unwrap();
+        assert_eq!(cipher.algorithm(), algorithm);
+
+        let nonce = GenericArray::from_slice(NONCE);
+        let ciphertext = cipher
+            .encrypt(
+                nonce,
+                Payload {
+                    msg: PLAINTEXT,
+                    aad: AAD,
+                },
+            )
+            .unwrap();
+        assert_eq!(ciphertext, encrypt_static(algorithm, &key));
+
+        let (ct, tag) = ciphertext.split_at(PLAINTEXT.len());
+        let mut buffer = ct.to_vec();
+        cipher
+            .decrypt_in_place_detached(nonce, AAD, &mut buffer, GenericArray::from_slice(tag))
+            .unwrap();
+        assert_eq!(buffer, PLAINTEXT);
+
+        let mut buffer = ciphertext.clone();
+        cipher.decrypt_in_place(nonce, AAD, &mut buffer).unwrap();
+        assert_eq!(buffer, PLAINTEXT);
+    }
+}
+
+#[test]
+fn wrong_key_size() {
+    assert!(AnyAead::new(AeadAlgorithm::Aes128Gcm, &[0; 32]).is_err());
+    assert!(AnyAead::new(AeadAlgorithm::Aes256Gcm, &[0; 16]).is_err());
+    assert!(AnyAead::new(AeadAlgorithm::ChaCha20Poly1305, &[]).is_err());
+}
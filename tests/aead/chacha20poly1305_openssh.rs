@@ -0,0 +1,117 @@
+//! `chacha20-poly1305@openssh.com` tests
+//!
+//! Test vectors were computed independently from the raw ChaCha20 and Poly1305
+//! primitives as described in OpenSSH's `PROTOCOL.chacha20poly1305`.
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{chacha20poly1305_openssh::PACKET_LENGTH_LEN, ChaCha20Poly1305OpenSsh, KeyInit, Tag},
+    generic_array::GenericArray,
+};
+
+/// Test vector
+struct TestVector {
+    sequence_number: u32,
+    ciphertext: &'static [u8],
+    tag: &'static [u8; 16],
+}
+
+const KEY: &[u8; 64] = &hex!(
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+    "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f"
+);
+
+/// `packet_length || padding_length || payload || random padding`
+const PACKET: &[u8] = &hex!("0000001806050000000c7373682d7573657261757468a0a1a2a3a4a5");
+
+const TEST_VECTORS: &[TestVector] = &[
+    TestVector {
+        sequence_number: 0,
+        ciphertext: &hex!("94450e411ebd4231adead5a27b4c2912ca312f528cd95354430efff9"),
+        tag: &hex!("e92d24cbc48d08b18469f09da7c1261e"),
+    },
+    TestVector {
+        sequence_number: 7,
+        ciphertext: &hex!("a39afcb22e4315434e8f592d0440ce83b2fdb25940da9097dc409926"),
+        tag: &hex!("22aaa9c7d18e441d8bb1dcf49a56615e"),
+    },
+    TestVector {
+        sequence_number: u32::MAX,
+        ciphertext: &hex!("b90ee4a80f970c9bae08a791fe1bdec117f463db62d299b76d8c7fdd"),
+        tag: &hex!("d7dd006a25a29457526e4a9003d73506"),
+    },
+];
+
+fn cipher() -> ChaCha20Poly1305OpenSsh {
+    ChaCha20Poly1305OpenSsh::new(GenericArray::from_slice(KEY))
+}
+
+#[test]
+fn seal_in_place() {
+    for vector in TEST_VECTORS {
+        let mut buffer = PACKET.to_vec();
+        let tag = cipher()
+            .seal_in_place(vector.sequence_number, &mut buffer)
+            .unwrap();
+
+        assert_eq!(vector.ciphertext, buffer.as_slice());
+        assert_eq!(vector.tag, tag.as_slice());
+    }
+}
+
+#[test]
+fn decrypt_packet_length() {
+    for vector in TEST_VECTORS {
+        let mut encrypted_packet_length = [0u8; PACKET_LENGTH_LEN];
+        encrypted_packet_length.copy_from_slice(&vector.ciphertext[..PACKET_LENGTH_LEN]);
+
+        let packet_length =
+            cipher().decrypt_packet_length(vector.sequence_number, encrypted_packet_length);
+        assert_eq!(packet_length as usize, PACKET.len() - PACKET_LENGTH_LEN);
+    }
+}
+
+#[test]
+fn open_in_place() {
+    for vector in TEST_VECTORS {
+        let mut buffer = vector.ciphertext.to_vec();
+        let tag = Tag::clone_from_slice(vector.tag);
+        let plaintext = cipher()
+            .open_in_place(vector.sequence_number, &mut buffer, &tag)
+            .unwrap();
+
+        assert_eq!(&PACKET[PACKET_LENGTH_LEN..], plaintext);
+    }
+}
+
+#[test]
+fn open_wrong_sequence_number() {
+    let vector = &TEST_VECTORS[0];
+    let mut buffer = vector.ciphertext.to_vec();
+    let tag = Tag::clone_from_slice(vector.tag);
+
+    assert!(cipher().open_in_place(1, &mut buffer, &tag).is_err());
+}
+
+#[test]
+fn open_modified() {
+    let vector = &TEST_VECTORS[0];
+    let mut buffer = vector.ciphertext.to_vec();
+    let tag = Tag::clone_from_slice(vector.tag);
+
+    // Tweak the encrypted packet length
+    buffer[0] ^= 0xaa;
+
+    assert!(cipher()
+        .open_in_place(vector.sequence_number, &mut buffer, &tag)
+        .is_err());
+}
+
+#[test]
+fn truncated_packet() {
+    let mut buffer = [0u8; PACKET_LENGTH_LEN - 1];
+    assert!(cipher().seal_in_place(0, &mut buffer).is_err());
+    assert!(cipher()
+        .open_in_place(0, &mut buffer, &Tag::default())
+        .is_err());
+}
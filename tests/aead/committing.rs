@@ -0,0 +1,147 @@
+//! Key-committing AEAD tests
+//!
+//! Test vectors were computed independently from HMAC-SHA-256 and the
+//! underlying AEADs as described in the `committing` module documentation.
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{
+        committing::{CommittingAes128Gcm, CommittingAes256Gcm, CommittingChaCha20Poly1305},
+        Aead, AeadInPlace, KeyInit, Payload,
+    },
+    generic_array::GenericArray,
+};
+
+const NONCE: &[u8; 12] = &hex!("000102030405060708090a0b");
+const AAD: &[u8] = b"committing AEAD test vector";
+const PLAINTEXT: &[u8] = b"Attack at dawn";
+
+const KEY_128: &[u8; 16] = &hex!("808182838485868788898a8b8c8d8e8f");
+const KEY_256: &[u8; 32] =
+    &hex!("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+
+macro_rules! committing_tests {
+    ($name:ident, $aead:ty, $key:expr, $ciphertext:expr, $tag:expr, $commitment:expr) => {
+        mod $name {
+            use super::*;
+
+            const CIPHERTEXT: &[u8] = &hex!($ciphertext);
+            const TAG: &[u8] = &hex!($tag);
+            const COMMITMENT: &[u8] = &hex!($commitment);
+
+            fn cipher() -> $aead {
+                <$aead>::new(GenericArray::from_slice($key))
+            }
+
+            fn sealed() -> Vec<u8> {
+                [CIPHERTEXT, TAG, COMMITMENT].concat()
+            }
+
+            #[test]
+            fn encrypt() {
+                let payload = Payload {
+                    msg: PLAINTEXT,
+                    aad: AAD,
+                };
+                let ciphertext = cipher().encrypt(NONCE.into(), payload).unwrap();
+                assert_eq!(ciphertext, sealed());
+            }
+
+            #[test]
+            fn decrypt() {
+                let ciphertext = sealed();
+                let payload = Payload {
+                    msg: &ciphertext,
+                    aad: AAD,
+                };
+                let plaintext = cipher().decrypt(NONCE.into(), payload).unwrap();
+                assert_eq!(plaintext, PLAINTEXT);
+            }
+
+            #[test]
+            fn decrypt_in_place_detached() {
+                let mut buffer = CIPHERTEXT.to_vec();
+                let tag = GenericArray::clone_from_slice(&[TAG, COMMITMENT].concat());
+                cipher()
+                    .decrypt_in_place_detached(NONCE.into(), AAD, &mut buffer, &tag)
+                    .unwrap();
+                assert_eq!(buffer, PLAINTEXT);
+            }
+
+            #[test]
+            fn decrypt_modified_commitment() {
+                let mut ciphertext = sealed();
+                *ciphertext.last_mut().unwrap() ^= 0xaa;
+
+                let mut buffer = ciphertext.clone();
+                assert!(cipher()
+                    .decrypt_in_place(NONCE.into(), AAD, &mut buffer)
+                    .is_err());
+                assert_eq!(buffer, ciphertext);
+            }
+
+            #[test]
+            fn decrypt_with_other_key() {
+                let mut other_key = GenericArray::clone_from_slice($key);
+                other_key[0] ^= 1;
+                let other = <$aead>::new(&other_key);
+
+                // Substituting the commitment for the other key doesn't help
+                let mut buffer = CIPHERTEXT.to_vec();
+                let other_tag = other
+                    .encrypt_in_place_detached(NONCE.into(), AAD, &mut buffer)
+                    .unwrap();
+                let mut ciphertext = sealed();
+                let commitment_pos = ciphertext.len() - 32;
+                ciphertext[commitment_pos..].copy_from_slice(&other_tag[16..]);
+
+                assert!(other
+                    .decrypt_in_place(NONCE.into(), AAD, &mut sealed())
+                    .is_err());
+                assert!(other
+                    .decrypt_in_place(NONCE.into(), AAD, &mut ciphertext)
+                    .is_err());
+            }
+        }
+    };
+}
+
+committing_tests!(
+    aes128gcm,
+    CommittingAes128Gcm,
+    KEY_128,
+    "3d2d62d4d1d7f993a817d6eb349d",
+    "b2fe6933c380cad92c141961fb6cd7a5",
+    "d6f52b1024f9d9bb2c9ff76ab3133769d4bf4afc5f2833df0eda589ad1322e55"
+);
+
+committing_tests!(
+    aes256gcm,
+    CommittingAes256Gcm,
+    KEY_256,
+    "2225ce2871518a545a703284653f",
+    "f4430d63232bb3664c4f3c8d57cf52d5",
+    "1228df13a1b3c66f4b8bc00499fcb338ad9baf5523352af556d410db3348d245"
+);
+
+committing_tests!(
+    chacha20poly1305,
+    CommittingChaCha20Poly1305,
+    KEY_256,
+    "db5e6d6f18179d834e2754e06ea9",
+    "0ca8d86fc4a2038047c39e4944f55bf2",
+    "907365d1c0b3743377ea5725888e66a6da8c758da09b7eac84787751a772592f"
+);
+
+#[test]
+fn algorithms_are_bound() {
+    let key = GenericArray::from_slice(KEY_256);
+    let aes = CommittingAes256Gcm::new(key)
+        .encrypt_in_place_detached(NONCE.into(), AAD, &mut PLAINTEXT.to_vec())
+        .unwrap();
+    let chacha = CommittingChaCha20Poly1305::new(key)
+        .encrypt_in_place_detached(NONCE.into(), AAD, &mut PLAINTEXT.to_vec())
+        .unwrap();
+
+    assert_ne!(aes[16..], chacha[16..]);
+}
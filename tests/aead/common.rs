@@ -86,7 +86,6 @@ macro_rules! tests {
         }
 
         #[test]
-        #[should_panic] // not implemented
         fn decrypt_in_place_detached() {
             for vector in $vectors {
                 let key = GenericArray::from_slice(vector.key);
@@ -97,9 +96,28 @@ macro_rules! tests {
                 <$aead>::new(key)
                     .decrypt_in_place_detached(nonce, vector.aad, &mut buffer, &tag)
                     .unwrap();
+
+                assert_eq!(vector.plaintext, buffer.as_slice());
             }
         }
 
+        #[test]
+        fn decrypt_in_place_detached_modified() {
+            let vector = &$vectors[0];
+            let key = GenericArray::from_slice(vector.key);
+            let nonce = GenericArray::from_slice(vector.nonce);
+            let mut tag = GenericArray::clone_from_slice(vector.tag);
+            let mut buffer = vector.ciphertext.to_vec();
+
+            // Tweak the first byte of the tag
+            tag[0] ^= 0xaa;
+
+            let cipher = <$aead>::new(key);
+            assert!(cipher
+                .decrypt_in_place_detached(nonce, vector.aad, &mut buffer, &tag)
+                .is_err());
+        }
+
         #[test]
         fn decrypt_modified() {
             let vector = &$vectors[0];
@@ -122,5 +140,25 @@ macro_rules! tests {
 
             // TODO(tarcieri): test ciphertext is unmodified in in-place API
         }
+
+        #[test]
+        fn clone_and_debug() {
+            let vector = &$vectors[0];
+            let key = GenericArray::from_slice(vector.key);
+            let nonce = GenericArray::from_slice(vector.nonce);
+            let payload = Payload {
+                msg: vector.plaintext,
+                aad: vector.aad,
+            };
+
+            let cipher = <$aead>::new(key).clone();
+            let ciphertext = cipher.encrypt(nonce, payload).unwrap();
+            assert_eq!(vector.ciphertext, &ciphertext[..vector.ciphertext.len()]);
+
+            assert_eq!(
+                format!("{:?}", cipher),
+                concat!(stringify!($aead), " { .. }")
+            );
+        }
     };
 }
@@ -0,0 +1,264 @@
+//! COSE tests
+//!
+//! The vectors were generated with Python's `cryptography` package and a
+//! hand-written CBOR encoder. The A128GCM vector uses the key, IV and content
+//! of the COSE WG Examples `aes-gcm-01`, as a `COSE_Encrypt0` message.
+
+use hex_literal::hex;
+use ring_compat::aead::cose::{
+    self, CoseError, EncryptionAlgorithm, MacAlgorithm, ENCRYPT0_TAG, MAC0_TAG,
+};
+
+const CONTENT: &[u8] = b"This is the content.";
+
+const KEY_128: [u8; 16] = hex!("849b57219dae48de646d07dbb533566e");
+
+const KEY_256: [u8; 32] = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+
+const MAC_KEY_256: [u8; 32] =
+    hex!("849b57219dae48de646d07dbb533566e976686457c1491be3a76dcea6c427188");
+
+const MAC_KEY_384: [u8; 48] = hex!(
+    "849b57219dae48de646d07dbb533566e976686457c1491be3a76dcea6c427188"
+    "849b57219dae48de646d07dbb533566e"
+);
+
+const KEY_ID: &[u8] = b"our-secret";
+
+const EXTERNAL_AAD: &[u8] = &hex!("11aa22bb33cc44dd55006699");
+
+/// A128GCM, IV in the unprotected header
+const ENCRYPT0_A128GCM: &[u8] = &hex!(
+    "d08343a10101a1054c02d1f7e6f26c43d4868d87ce582460973a94bb2898009e"
+    "e52ecfd9ab1dd25867374b162e2c03568b41f57c3cc16f9166250a"
+);
+
+/// A256GCM with a key ID and external AAD
+const ENCRYPT0_A256GCM: &[u8] = &hex!(
+    "d08343a10103a2044a6f75722d736563726574054c89f52f65a1c580933b5261"
+    "a75824bcdb659944c4705fe256a04d6c6233da46ed70c81f582c54315515461c"
+    "dce37fe1712146"
+);
+
+/// ChaCha20/Poly1305, untagged
+const ENCRYPT0_CHACHA20POLY1305: &[u8] = &hex!(
+    "8344a1011818a1054c26682306d4fb28ca01b43b805824e4ba39ac7bb63ab6a7"
+    "546b2fa0bd0914167fdbdfec35181b55fa12b72b656d7f187914a4"
+);
+
+/// HMAC 256/256
+const MAC0_HMAC256: &[u8] = &hex!(
+    "d18443a10105a054546869732069732074686520636f6e74656e742e5820a1a8"
+    "48d3471f9d61ee49018d244c824772f223ad4f935293f1789fc3a08d8c58"
+);
+
+/// HMAC 384/384 with a key ID and external AAD
+const MAC0_HMAC384: &[u8] = &hex!(
+    "d18443a10106a1044a6f75722d73656372657454546869732069732074686520"
+    "636f6e74656e742e5830b2103cc4b494e500301c8037230da9b6631f5e14577a"
+    "c69dfd9c83766e871d26d2fd4376ceee5b1fd6b80e63c486a1f3"
+);
+
+#[test]
+fn open_encrypt0_a128gcm() {
+    let opened = cose::open_encrypt0(&KEY_128, ENCRYPT0_A128GCM, b"").unwrap();
+    assert_eq!(opened.algorithm, EncryptionAlgorithm::A128Gcm);
+    assert!(opened.key_id.is_empty());
+    assert_eq!(opened.plaintext, CONTENT);
+}
+
+#[test]
+fn open_encrypt0_a256gcm() {
+    let opened = cose::open_encrypt0(&KEY_256, ENCRYPT0_A256GCM, EXTERNAL_AAD).unwrap();
+    assert_eq!(opened.algorithm, EncryptionAlgorithm::A256Gcm);
+    assert_eq!(opened.key_id, KEY_ID);
+    assert_eq!(opened.plaintext, CONTENT);
+
+    assert_eq!(
+        cose::open_encrypt0(&KEY_256, ENCRYPT0_A256GCM, b""),
+        Err(CoseError::Decryption)
+    );
+}
+
+#[test]
+fn open_encrypt0_chacha20poly1305() {
+    let opened = cose::open_encrypt0(&KEY_256, ENCRYPT0_CHACHA20POLY1305, b"").unwrap();
+    assert_eq!(opened.algorithm, EncryptionAlgorithm::ChaCha20Poly1305);
+    assert_eq!(opened.plaintext, CONTENT);
+}
+
+#[test]
+fn encrypt0_round_trip() {
+    for (algorithm, key) in [
+        (EncryptionAlgorithm::A128Gcm, &KEY_128[..]),
+        (EncryptionAlgorithm::A256Gcm, &KEY_256[..]),
+        (EncryptionAlgorithm::ChaCha20Poly1305, &KEY_256[..]),
+    ] {
+        let message = cose::seal_encrypt0(algorithm, key, KEY_ID, CONTENT, EXTERNAL_AAD).unwrap();
+        assert_eq!(message[0], 0xc0 | ENCRYPT0_TAG as u8);
+
+        let opened = cose::open_encrypt0(key, &message, EXTERNAL_AAD).unwrap();
+        assert_eq!(opened.algorithm, algorithm);
+        assert_eq!(opened.key_id, KEY_ID);
+        assert_eq!(opened.plaintext, CONTENT);
+    }
+}
+
+#[test]
+fn encrypt0_protected_header_is_authenticated() {
+    // ENCRYPT0_A128GCM with the protected header {1: 1} in a longer encoding
+    let message = hex!(
+        "d08344a1011801a1054c02d1f7e6f26c43d4868d87ce582460973a94bb289800"
+        "9ee52ecfd9ab1dd25867374b162e2c03568b41f57c3cc16f9166250a"
+    );
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Decryption)
+    );
+}
+
+#[test]
+fn encrypt0_wrong_key() {
+    assert_eq!(
+        cose::open_encrypt0(&KEY_256, ENCRYPT0_A128GCM, b""),
+        Err(CoseError::InvalidKey)
+    );
+
+    let mut key = KEY_128;
+    key[0] ^= 1;
+    assert_eq!(
+        cose::open_encrypt0(&key, ENCRYPT0_A128GCM, b""),
+        Err(CoseError::Decryption)
+    );
+
+    assert_eq!(
+        cose::seal_encrypt0(EncryptionAlgorithm::A256Gcm, &KEY_128, b"", CONTENT, b""),
+        Err(CoseError::InvalidKey)
+    );
+}
+
+#[test]
+fn verify_mac0_hmac256() {
+    let verified = cose::verify_mac0(&MAC_KEY_256, MAC0_HMAC256, b"").unwrap();
+    assert_eq!(verified.algorithm, MacAlgorithm::Hmac256);
+    assert!(verified.key_id.is_empty());
+    assert_eq!(verified.payload, CONTENT);
+
+    assert_eq!(
+        cose::create_mac0(MacAlgorithm::Hmac256, &MAC_KEY_256, b"", CONTENT, b""),
+        MAC0_HMAC256
+    );
+}
+
+#[test]
+fn verify_mac0_hmac384() {
+    let verified = cose::verify_mac0(&MAC_KEY_384, MAC0_HMAC384, EXTERNAL_AAD).unwrap();
+    assert_eq!(verified.algorithm, MacAlgorithm::Hmac384);
+    assert_eq!(verified.key_id, KEY_ID);
+    assert_eq!(verified.payload, CONTENT);
+
+    assert_eq!(
+        cose::create_mac0(
+            MacAlgorithm::Hmac384,
+            &MAC_KEY_384,
+            KEY_ID,
+            CONTENT,
+            EXTERNAL_AAD
+        ),
+        MAC0_HMAC384
+    );
+}
+
+#[test]
+fn mac0_tampering() {
+    assert_eq!(
+        cose::verify_mac0(&MAC_KEY_384, MAC0_HMAC384, b""),
+        Err(CoseError::Verification)
+    );
+
+    let mut message = MAC0_HMAC256.to_vec();
+    let last = message.len() - 1;
+    message[last] ^= 1;
+    assert_eq!(
+        cose::verify_mac0(&MAC_KEY_256, &message, b""),
+        Err(CoseError::Verification)
+    );
+
+    // Payload byte
+    let mut message = MAC0_HMAC256.to_vec();
+    message[8] ^= 1;
+    assert_eq!(
+        cose::verify_mac0(&MAC_KEY_256, &message, b""),
+        Err(CoseError::Verification)
+    );
+}
+
+#[test]
+fn wrong_message_type() {
+    assert_eq!(
+        cose::verify_mac0(&KEY_128, ENCRYPT0_A128GCM, b""),
+        Err(CoseError::Malformed)
+    );
+    assert_eq!(
+        cose::open_encrypt0(&MAC_KEY_256, MAC0_HMAC256, b""),
+        Err(CoseError::Malformed)
+    );
+
+    // Encrypt0 array with a Mac0 tag
+    let mut message = ENCRYPT0_A128GCM.to_vec();
+    message[0] = 0xc0 | MAC0_TAG as u8;
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Malformed)
+    );
+}
+
+#[test]
+fn header_errors() {
+    // IV in both the protected and unprotected headers
+    let message = hex!(
+        "8349a2010105440000000000 a1054c000000000000000000000000 5010000000000000000000000000000000"
+    );
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Malformed)
+    );
+
+    // Critical header parameters
+    let message =
+        hex!("8346a2010102810a a1054c000000000000000000000000 5010000000000000000000000000000000");
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Unsupported)
+    );
+
+    // Unsupported algorithm (AES-CCM-16-64-128)
+    let message =
+        hex!("8344a101180aa1054c000000000000000000000000 5010000000000000000000000000000000");
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Unsupported)
+    );
+
+    // Missing algorithm
+    let message = hex!("8340a1054c000000000000000000000000 5010000000000000000000000000000000");
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Malformed)
+    );
+
+    // Detached ciphertext
+    let message = hex!("8343a10101a1054c000000000000000000000000 f6");
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Unsupported)
+    );
+
+    // Trailing data
+    let mut message = ENCRYPT0_A128GCM.to_vec();
+    message.push(0);
+    assert_eq!(
+        cose::open_encrypt0(&KEY_128, &message, b""),
+        Err(CoseError::Malformed)
+    );
+}
@@ -0,0 +1,237 @@
+//! Datagram session tests
+//!
+//! The vectors were generated with Python's `cryptography` package.
+
+use hex_literal::hex;
+use ring_compat::aead::{
+    datagram::{DatagramError, Session, HEADER_LEN, TAG_LEN, WINDOW_SIZE},
+    Aes256Gcm, ChaCha20Poly1305,
+};
+
+const SECRET_SHA256: [u8; 32] =
+    hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+
+const SECRET_SHA384: [u8; 48] = hex!(
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+    "202122232425262728292a2b2c2d2e2f"
+);
+
+const OTHER_SECRET: [u8; 32] = [0x42; 32];
+
+const AAD: &[u8] = b"telemetry";
+
+const PAYLOAD: &[u8] = b"temperature=21.5";
+
+/// ChaCha20/Poly1305, epoch 0, sequence number 0
+const DATAGRAM_CHACHA20POLY1305: &[u8] = &hex!(
+    "0000000000000000000093500c19701446804c5695fa095f6fb18bb603df619d"
+    "01544e5a1a4dc5788324"
+);
+
+/// ChaCha20/Poly1305, epoch 1, sequence number 7
+const DATAGRAM_CHACHA20POLY1305_EPOCH1: &[u8] = &hex!(
+    "000100000000000000074476e4b37e58273b6cc7657339519b35465041d7c13b"
+    "3277913f9869a9d1ceda"
+);
+
+/// AES-256-GCM, epoch 0, sequence number 3
+const DATAGRAM_AES256GCM: &[u8] = &hex!(
+    "00000000000000000003a8b53f328c49b689e297935bf883929e3e5d47de8e75"
+    "1b48f33582f46eb2c418"
+);
+
+/// Pair of sessions with swapped secrets
+fn sessions() -> (Session<ChaCha20Poly1305>, Session<ChaCha20Poly1305>) {
+    (
+        Session::new(&SECRET_SHA256, &OTHER_SECRET).unwrap(),
+        Session::new(&OTHER_SECRET, &SECRET_SHA256).unwrap(),
+    )
+}
+
+fn open(
+    session: &mut Session<ChaCha20Poly1305>,
+    datagram: &[u8],
+) -> Result<Vec<u8>, DatagramError> {
+    let mut datagram = datagram.to_vec();
+    session
+        .open_in_place(AAD, &mut datagram)
+        .map(|payload| payload.to_vec())
+}
+
+#[test]
+fn seal_vectors() {
+    let (mut sender, _) = sessions();
+    assert_eq!(
+        sender.seal(AAD, PAYLOAD).unwrap(),
+        DATAGRAM_CHACHA20POLY1305
+    );
+
+    sender.rekey().unwrap();
+    for _ in 0..7 {
+        sender.seal(AAD, PAYLOAD).unwrap();
+    }
+    assert_eq!(
+        sender.seal(AAD, PAYLOAD).unwrap(),
+        DATAGRAM_CHACHA20POLY1305_EPOCH1
+    );
+
+    let mut sender = Session::<Aes256Gcm>::new(&SECRET_SHA384, &SECRET_SHA384).unwrap();
+    for _ in 0..3 {
+        sender.seal(AAD, PAYLOAD).unwrap();
+    }
+    assert_eq!(sender.seal(AAD, PAYLOAD).unwrap(), DATAGRAM_AES256GCM);
+}
+
+#[test]
+fn open_vectors() {
+    let (_, mut receiver) = sessions();
+    assert_eq!(
+        open(&mut receiver, DATAGRAM_CHACHA20POLY1305).unwrap(),
+        PAYLOAD
+    );
+    assert_eq!(receiver.receive_epoch(), 0);
+
+    assert_eq!(
+        open(&mut receiver, DATAGRAM_CHACHA20POLY1305_EPOCH1).unwrap(),
+        PAYLOAD
+    );
+    assert_eq!(receiver.receive_epoch(), 1);
+
+    let mut receiver = Session::<Aes256Gcm>::new(&SECRET_SHA384, &SECRET_SHA384).unwrap();
+    let mut datagram = DATAGRAM_AES256GCM.to_vec();
+    assert_eq!(receiver.open_in_place(AAD, &mut datagram).unwrap(), PAYLOAD);
+}
+
+#[test]
+fn seal_in_place() {
+    let (mut sender, mut receiver) = sessions();
+
+    let mut datagram = vec![0u8; HEADER_LEN + PAYLOAD.len() + TAG_LEN];
+    datagram[HEADER_LEN..HEADER_LEN + PAYLOAD.len()].copy_from_slice(PAYLOAD);
+    sender.seal_in_place(AAD, &mut datagram).unwrap();
+
+    assert_eq!(datagram, DATAGRAM_CHACHA20POLY1305);
+    assert_eq!(sender.sequence_number(), 1);
+    assert_eq!(receiver.open_in_place(AAD, &mut datagram).unwrap(), PAYLOAD);
+
+    assert_eq!(
+        sender.seal_in_place(AAD, &mut [0u8; HEADER_LEN + TAG_LEN - 1]),
+        Err(DatagramError::Malformed)
+    );
+}
+
+#[test]
+fn out_of_order() {
+    let (mut sender, mut receiver) = sessions();
+    let datagrams: Vec<_> = (0..5u8).map(|i| sender.seal(AAD, &[i]).unwrap()).collect();
+
+    for i in [4, 0, 2, 1, 3] {
+        assert_eq!(open(&mut receiver, &datagrams[i]).unwrap(), [i as u8]);
+    }
+}
+
+#[test]
+fn duplicate() {
+    let (mut sender, mut receiver) = sessions();
+    let datagram = sender.seal(AAD, PAYLOAD).unwrap();
+
+    // Failed attempts aren't recorded
+    let mut tampered = datagram.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        open(&mut receiver, &tampered),
+        Err(DatagramError::Decryption)
+    );
+    assert_eq!(open(&mut receiver, &datagram).unwrap(), PAYLOAD);
+
+    assert_eq!(
+        open(&mut receiver, &datagram),
+        Err(DatagramError::Duplicate)
+    );
+}
+
+#[test]
+fn stale() {
+    let (mut sender, mut receiver) = sessions();
+    let first = sender.seal(AAD, PAYLOAD).unwrap();
+    let second = sender.seal(AAD, PAYLOAD).unwrap();
+
+    for _ in 2..WINDOW_SIZE {
+        sender.seal(AAD, PAYLOAD).unwrap();
+    }
+
+    let last = sender.seal(AAD, PAYLOAD).unwrap();
+    assert_eq!(open(&mut receiver, &last).unwrap(), PAYLOAD);
+
+    assert_eq!(open(&mut receiver, &first), Err(DatagramError::Stale));
+    assert_eq!(open(&mut receiver, &second).unwrap(), PAYLOAD);
+}
+
+#[test]
+fn rekey() {
+    let (mut sender, mut receiver) = sessions();
+    let epoch0 = sender.seal(AAD, b"epoch 0").unwrap();
+
+    sender.rekey().unwrap();
+    assert_eq!(sender.send_epoch(), 1);
+    assert_eq!(sender.sequence_number(), 0);
+    let epoch1 = sender.seal(AAD, b"epoch 1").unwrap();
+
+    sender.rekey().unwrap();
+    let epoch2 = sender.seal(AAD, b"epoch 2").unwrap();
+
+    // Epoch 2 is too far ahead until a datagram of epoch 1 has been opened
+    assert_eq!(
+        open(&mut receiver, &epoch2),
+        Err(DatagramError::UnknownEpoch)
+    );
+
+    // Datagrams of the previous epoch are still accepted
+    assert_eq!(open(&mut receiver, &epoch1).unwrap(), b"epoch 1");
+    assert_eq!(receiver.receive_epoch(), 1);
+    assert_eq!(open(&mut receiver, &epoch0).unwrap(), b"epoch 0");
+
+    // Sequence numbers are tracked per epoch
+    assert_eq!(open(&mut receiver, &epoch0), Err(DatagramError::Duplicate));
+
+    assert_eq!(open(&mut receiver, &epoch2).unwrap(), b"epoch 2");
+    assert_eq!(receiver.receive_epoch(), 2);
+    assert_eq!(open(&mut receiver, &epoch0), Err(DatagramError::Stale));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        Session::<Aes256Gcm>::new(&SECRET_SHA256, &SECRET_SHA384).unwrap_err(),
+        DatagramError::InvalidKey
+    );
+
+    let (mut sender, mut receiver) = sessions();
+    let datagram = sender.seal(AAD, PAYLOAD).unwrap();
+
+    assert_eq!(
+        open(&mut receiver, &datagram[..HEADER_LEN + TAG_LEN - 1]),
+        Err(DatagramError::Malformed)
+    );
+
+    let mut wrong_aad = datagram.clone();
+    assert_eq!(
+        receiver.open_in_place(b"other", &mut wrong_aad),
+        Err(DatagramError::Decryption)
+    );
+
+    // Header is bound to the nonce and key
+    let mut wrong_sequence_number = datagram.clone();
+    wrong_sequence_number[HEADER_LEN - 1] ^= 1;
+    assert_eq!(
+        open(&mut receiver, &wrong_sequence_number),
+        Err(DatagramError::Decryption)
+    );
+
+    let mut wrong_epoch = datagram;
+    wrong_epoch[1] ^= 1;
+    assert_eq!(
+        open(&mut receiver, &wrong_epoch),
+        Err(DatagramError::Decryption)
+    );
+}
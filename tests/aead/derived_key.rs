@@ -0,0 +1,111 @@
+//! Derived-key AES-256-GCM tests
+//!
+//! Vectors were generated with Python's `cryptography` package, using its
+//! HKDF and AESGCM implementations.
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{derived_key::DerivedKeyAes256Gcm, Aead, AeadInPlace, KeyInit, Payload},
+    generic_array::{typenum::U32, GenericArray},
+};
+
+const KEY: &[u8; 32] = &hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+
+const AAD: &[u8] = b"associated data";
+
+const PLAINTEXT: &[u8] = b"derived-key AES-256-GCM";
+
+const NONCE_192: &[u8; 24] = &hex!("404142434445464748494a4b4c4d4e4f5051525354555657");
+
+const CIPHERTEXT_192: &[u8] =
+    &hex!("7f7f2f1e41c78bac8f1c10afc2e7c83cb46d9350622c98b669040914ce76d3d1ab309e23ceeaf2");
+
+const NONCE_256: &[u8; 32] =
+    &hex!("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f");
+
+const CIPHERTEXT_256: &[u8] =
+    &hex!("11507205da9fe6f5c9131a7b94fa0362a570a3cdbca545308a1525c6d5df7bfcb4ecce1b67595f");
+
+fn payload(msg: &[u8]) -> Payload<'_, '_> {
+    Payload { msg, aad: AAD }
+}
+
+#[test]
+fn encrypt_192() {
+    let cipher: DerivedKeyAes256Gcm = DerivedKeyAes256Gcm::new(KEY.into());
+    let ciphertext = cipher
+        .encrypt(NONCE_192.into(), payload(PLAINTEXT))
+        .unwrap();
+    assert_eq!(ciphertext, CIPHERTEXT_192);
+}
+
+#[test]
+fn decrypt_192() {
+    let cipher: DerivedKeyAes256Gcm = DerivedKeyAes256Gcm::new(KEY.into());
+    let plaintext = cipher
+        .decrypt(NONCE_192.into(), payload(CIPHERTEXT_192))
+        .unwrap();
+    assert_eq!(plaintext, PLAINTEXT);
+}
+
+#[test]
+fn encrypt_256() {
+    let cipher = DerivedKeyAes256Gcm::<U32>::new(KEY.into());
+    let ciphertext = cipher
+        .encrypt(NONCE_256.into(), payload(PLAINTEXT))
+        .unwrap();
+    assert_eq!(ciphertext, CIPHERTEXT_256);
+}
+
+#[test]
+fn decrypt_256() {
+    let cipher = DerivedKeyAes256Gcm::<U32>::new(KEY.into());
+    let plaintext = cipher
+        .decrypt(NONCE_256.into(), payload(CIPHERTEXT_256))
+        .unwrap();
+    assert_eq!(plaintext, PLAINTEXT);
+}
+
+#[test]
+fn decrypt_in_place_detached() {
+    let cipher: DerivedKeyAes256Gcm = DerivedKeyAes256Gcm::new(KEY.into());
+    let (ciphertext, tag) = CIPHERTEXT_192.split_at(PLAINTEXT.len());
+    let mut buffer = ciphertext.to_vec();
+
+    cipher
+        .decrypt_in_place_detached(
+            NONCE_192.into(),
+            AAD,
+            &mut buffer,
+            GenericArray::from_slice(tag),
+        )
+        .unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+}
+
+#[test]
+fn derived_keys_differ() {
+    // Changing only the derivation part of the nonce changes the key
+    let cipher: DerivedKeyAes256Gcm = DerivedKeyAes256Gcm::new(KEY.into());
+    let mut nonce = *NONCE_192;
+    nonce[0] ^= 1;
+
+    let ciphertext = cipher.encrypt(&nonce.into(), payload(PLAINTEXT)).unwrap();
+    assert_ne!(ciphertext, CIPHERTEXT_192);
+    assert!(cipher
+        .decrypt(NONCE_192.into(), payload(&ciphertext))
+        .is_err());
+}
+
+#[test]
+fn decrypt_modified() {
+    let cipher: DerivedKeyAes256Gcm = DerivedKeyAes256Gcm::new(KEY.into());
+
+    for i in 0..CIPHERTEXT_192.len() {
+        let mut ciphertext = CIPHERTEXT_192.to_vec();
+        ciphertext[i] ^= 1;
+        assert!(cipher
+            .decrypt(NONCE_192.into(), payload(&ciphertext))
+            .is_err());
+    }
+}
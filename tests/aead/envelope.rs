@@ -0,0 +1,110 @@
+//! Envelope encryption tests
+
+use hex_literal::hex;
+use ring_compat::aead::{
+    envelope::{self, Envelope, KeyEncryptionKey, LocalKeyEncryptionKey},
+    AeadAlgorithm, Error,
+};
+
+const AAD: &[u8] = b"envelope test";
+const PLAINTEXT: &[u8] = b"sealed in an envelope";
+
+const KEK: [u8; 32] = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+
+/// Envelope generated with Python's `cryptography` package using the data key
+/// 0x20..0x3f, wrapping nonce a0..ab and payload nonce b0..bb
+const ENVELOPE: &[u8] = &hex!(
+    "010002003ca0a1a2a3a4a5a6a7a8a9aaabc6395e0e61ee24984a4cadf82b57eef1409d6b23a682745b"
+    "a4371cbd43964b3e70212a82b7d2f28cf63dd6677c071866b0b1b2b3b4b5b6b7b8b9babb459f4b2bf7"
+    "47e8675c2d7c942358f340f35fcdebeffab43b9a20060b8112f66cc4f7d5b767"
+);
+
+/// Key-encryption key which "wraps" keys by XORing them with a fixed byte
+struct XorKek(u8);
+
+impl KeyEncryptionKey for XorKek {
+    fn wrap_key(&self, data_key: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(data_key.iter().map(|b| b ^ self.0).collect())
+    }
+
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<Vec<u8>, Error> {
+        self.wrap_key(wrapped_key)
+    }
+}
+
+fn kek() -> LocalKeyEncryptionKey {
+    LocalKeyEncryptionKey::new(&KEK.into())
+}
+
+#[test]
+fn open_known_envelope() {
+    assert_eq!(envelope::open(&kek(), ENVELOPE, AAD).unwrap(), PLAINTEXT);
+}
+
+#[test]
+fn parse() {
+    let parsed = Envelope::parse(ENVELOPE).unwrap();
+    assert_eq!(parsed.algorithm, AeadAlgorithm::Aes256Gcm);
+    assert_eq!(parsed.wrapped_key.len(), 12 + 32 + 16);
+    assert_eq!(parsed.nonce, hex!("b0b1b2b3b4b5b6b7b8b9babb"));
+    assert_eq!(parsed.ciphertext.len(), PLAINTEXT.len());
+    assert_eq!(parsed.tag.len(), 16);
+}
+
+#[test]
+fn round_trip() {
+    let kek = LocalKeyEncryptionKey::generate().unwrap();
+    let sealed = envelope::seal(&kek, PLAINTEXT, AAD).unwrap();
+    assert_eq!(envelope::open(&kek, &sealed, AAD).unwrap(), PLAINTEXT);
+}
+
+#[test]
+fn fresh_data_key_per_envelope() {
+    let first = envelope::seal(&kek(), PLAINTEXT, AAD).unwrap();
+    let second = envelope::seal(&kek(), PLAINTEXT, AAD).unwrap();
+
+    let first = Envelope::parse(&first).unwrap();
+    let second = Envelope::parse(&second).unwrap();
+    assert_ne!(first.wrapped_key, second.wrapped_key);
+    assert_ne!(first.ciphertext, second.ciphertext);
+}
+
+#[test]
+fn custom_kek() {
+    let kek = XorKek(0x5c);
+    let sealed = envelope::seal(&kek, PLAINTEXT, AAD).unwrap();
+    assert_eq!(Envelope::parse(&sealed).unwrap().wrapped_key.len(), 32);
+    assert_eq!(envelope::open(&kek, &sealed, AAD).unwrap(), PLAINTEXT);
+    assert!(envelope::open(&XorKek(0x36), &sealed, AAD).is_err());
+}
+
+#[test]
+fn wrong_kek() {
+    let other = LocalKeyEncryptionKey::generate().unwrap();
+    assert!(envelope::open(&other, ENVELOPE, AAD).is_err());
+}
+
+#[test]
+fn wrong_aad() {
+    assert!(envelope::open(&kek(), ENVELOPE, b"other").is_err());
+}
+
+#[test]
+fn modified_envelope() {
+    for i in 0..ENVELOPE.len() {
+        let mut modified = ENVELOPE.to_vec();
+        modified[i] ^= 1;
+        assert!(
+            envelope::open(&kek(), &modified, AAD).is_err(),
+            "byte {}",
+            i
+        );
+    }
+}
+
+#[test]
+fn truncated_envelope() {
+    for len in 0..ENVELOPE.len() {
+        assert!(envelope::open(&kek(), &ENVELOPE[..len], AAD).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+//! HPKE tests
+//!
+//! The X25519 vectors are from RFC 9180 Appendix A, as distributed in
+//! BoringSSL's `hpke_test_vectors.txt`, and the P-256 vector is from RFC 9180
+//! Appendix A.3.1. The HKDF-SHA384 and HKDF-SHA512 vectors were generated
+//! with an independent Python implementation built on the `cryptography`
+//! package, which reproduces the RFC vectors.
+
+use crate::agreement::{P256PrivateKey, X25519PrivateKey};
+use hex_literal::hex;
+use ring_compat::{
+    aead::{
+        hpke::{
+            self, DhKemP256HkdfSha256, DhKemX25519HkdfSha256, HkdfSha256, HkdfSha384, HkdfSha512,
+            HpkeAead, HpkeError, Kdf, Kem, Mode,
+        },
+        Aes128Gcm, Aes256Gcm, ChaCha20Poly1305,
+    },
+    agreement::PrivateKey,
+};
+
+const INFO: &[u8] = b"Ode on a Grecian Urn";
+const PLAINTEXT: &[u8] = b"Beauty is truth, truth beauty";
+
+/// PSK from RFC 9180 Appendix A.1.2
+const PSK: &[u8] = &hex!("0247fd33b913760fa1fa51e1892d9f307fbe65eb171e8132c2af18555a738b82");
+const PSK_ID: &[u8] = b"Ennyn Durin aran Moria";
+
+/// Open consecutive ciphertexts with AAD `Count-<i>` and check exports.
+fn check_receiver<K: Kem, F: Kdf, A: HpkeAead>(
+    mode: Mode<'_>,
+    private_key: &dyn PrivateKey,
+    enc: &[u8],
+    ciphertexts: &[&[u8]],
+    exports: &[(&[u8], &[u8])],
+) {
+    let mut context = hpke::setup_receiver::<K, F, A>(mode, private_key, enc, INFO).unwrap();
+
+    for (i, ciphertext) in ciphertexts.iter().enumerate() {
+        assert_eq!(context.sequence_number(), i as u64);
+        let aad = format!("Count-{}", i);
+        assert_eq!(context.open(aad.as_bytes(), ciphertext).unwrap(), PLAINTEXT);
+    }
+
+    for (exporter_context, expected) in exports {
+        let mut exported = vec![0u8; expected.len()];
+        context.export(exporter_context, &mut exported).unwrap();
+        assert_eq!(&exported, expected);
+    }
+}
+
+/// RFC 9180 Appendix A.1.1: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256,
+/// AES-128-GCM, base mode
+#[test]
+fn x25519_sha256_aes128gcm_base() {
+    let private_key = X25519PrivateKey::new(hex!(
+        "4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d")
+    );
+
+    check_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        &private_key,
+        &hex!("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431"),
+        &[
+            &hex!("f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a96d8770ac83d07bea87e13c512a"),
+            &hex!("af2d7e9ac9ae7e270f46ba1f975be53c09f8d875bdc8535458c2494e8a6eab251c03d0c22a56b8ca42c2063b84"),
+            &hex!("498dfcabd92e8acedc281e85af1cb4e3e31c7dc394a1ca20e173cb72516491588d96a19ad4a683518973dcc180"),
+        ],
+        &[
+            (b"", &hex!("3853fe2b4035195a573ffc53856e77058e15d9ea064de3e59f4961d0095250ee")),
+            (&[0], &hex!("2e8f0b54673c7029649d4eb9d5e33bf1872cf76d623ff164ac185da9e88c21a5")),
+            (b"TestContext", &hex!("e9e43065102c3836401bed8c3c3c75ae46be1639869391d62c61f1ec7af54931")),
+        ],
+    );
+}
+
+/// RFC 9180 Appendix A.1.2: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256,
+/// AES-128-GCM, PSK mode
+#[test]
+fn x25519_sha256_aes128gcm_psk() {
+    let private_key = X25519PrivateKey::new(hex!(
+        "c5eb01eb457fe6c6f57577c5413b931550a162c71a03ac8d196babbd4e5ce0fd"
+    ));
+
+    check_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Psk {
+            psk: PSK,
+            psk_id: PSK_ID,
+        },
+        &private_key,
+        &hex!("0ad0950d9fb9588e59690b74f1237ecdf1d775cd60be2eca57af5a4b0471c91b"),
+        &[
+            &hex!("e52c6fed7f758d0cf7145689f21bc1be6ec9ea097fef4e959440012f4feb73fb611b946199e681f4cfc34db8ea"),
+            &hex!("49f3b19b28a9ea9f43e8c71204c00d4a490ee7f61387b6719db765e948123b45b61633ef059ba22cd62437c8ba"),
+            &hex!("257ca6a08473dc851fde45afd598cc83e326ddd0abe1ef23baa3baa4dd8cde99fce2c1e8ce687b0b47ead1adc9"),
+        ],
+        &[
+            (b"", &hex!("dff17af354c8b41673567db6259fd6029967b4e1aad13023c2ae5df8f4f43bf6")),
+            (&[0], &hex!("6a847261d8207fe596befb52928463881ab493da345b10e1dcc645e3b94e2d95")),
+            (b"TestContext", &hex!("8aff52b45a1be3a734bc7a41e20b4e055ad4c4d22104b0c20285a7c4302401cd")),
+        ],
+    );
+}
+
+/// DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-256-GCM, base mode
+#[test]
+fn x25519_sha256_aes256gcm_base() {
+    let private_key = X25519PrivateKey::new(hex!(
+        "497b4502664cfea5d5af0b39934dac72242a74f8480451e1aee7d6a53320333d"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!("430f4b9859665145a6b1ba274024487bd66f03a2dd577d7753c68d7d7d00c00c")
+    );
+
+    check_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes256Gcm>(
+        Mode::Base,
+        &private_key,
+        &hex!("6c93e09869df3402d7bf231bf540fadd35cd56be14f97178f0954db94b7fc256"),
+        &[
+            &hex!("e5d84cd531cfb583096e7cfa9641bd3079cf3a91cda813c52deb5f512be9931980a41de125a925cdad859d5b7a"),
+            &hex!("2c43aff25343fdbff864506f0818b9d87df84ea01b1a2144d23b4d40c26bf655fdf197fe40297a8aebeed5cc2d"),
+        ],
+        &[],
+    );
+}
+
+/// RFC 9180 Appendix A.2.1: DHKEM(X25519, HKDF-SHA256), HKDF-SHA256,
+/// ChaCha20Poly1305, base mode
+#[test]
+fn x25519_sha256_chacha20poly1305_base() {
+    let private_key = X25519PrivateKey::new(hex!(
+        "8057991eef8f1f1af18f4a9491d16a1ce333f695d4db8e38da75975c4478e0fb"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!("4310ee97d88cc1f088a5576c77ab0cf5c3ac797f3d95139c6c84b5429c59662a")
+    );
+
+    check_receiver::<DhKemX25519HkdfSha256, HkdfSha256, ChaCha20Poly1305>(
+        Mode::Base,
+        &private_key,
+        &hex!("1afa08d3dec047a643885163f1180476fa7ddb54c6a8029ea33f95796bf2ac4a"),
+        &[
+            &hex!("1c5250d8034ec2b784ba2cfd69dbdb8af406cfe3ff938e131f0def8c8b60b4db21993c62ce81883d2dd1b51a28"),
+            &hex!("6b53c051e4199c518de79594e1c4ab18b96f081549d45ce015be002090bb119e85285337cc95ba5f59992dc98c"),
+        ],
+        &[],
+    );
+}
+
+/// DHKEM(X25519, HKDF-SHA256), HKDF-SHA384, AES-256-GCM, base mode
+#[test]
+fn x25519_sha384_aes256gcm_base() {
+    let private_key = X25519PrivateKey::new(hex!(
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!("8f40c5adb68f25624ae5b214ea767a6ec94d829d3d7b5e1ad1ba6f3e2138285f")
+    );
+
+    check_receiver::<DhKemX25519HkdfSha256, HkdfSha384, Aes256Gcm>(
+        Mode::Base,
+        &private_key,
+        &hex!("358072d6365880d1aeea329adf9121383851ed21a28e3b75e965d0d2cd166254"),
+        &[
+            &hex!("25b58e8cabadfa71ca9b9c6f5181c358985fd9f04416998c230852bcf85a78bd4ece390c5fa01b6dd8ae5e4c66"),
+            &hex!("3f512673ba00ca3951c5ae5d45d12157b23ff5bd455c3b52e1fb00ed998979d541039491a99b3aba565db1409d"),
+        ],
+        &[
+            (b"TestContext", &hex!("ca535aaf2e439bb769d175126e052ac060e165eb6f04bc0b39d4374b4bdb3f94")),
+            (
+                b"TestContext",
+                &hex!(
+                    "62d279b911d4e999b9e0027d9745be2d1164dcf0ab0507a0d819a10181affed4"
+                    "7627ffcd2d85df3aa3ee3c4b85b586976c9e4040a8d61926eb93e89c303bebe5"
+                    "06fc93413fa4195a4b3b536d448ca161"
+                ),
+            ),
+        ],
+    );
+}
+
+/// RFC 9180 Appendix A.3.1: DHKEM(P-256, HKDF-SHA256), HKDF-SHA256,
+/// AES-128-GCM, base mode
+#[test]
+fn p256_sha256_aes128gcm_base() {
+    let private_key = P256PrivateKey::new(&hex!(
+        "f3ce7fdae57e1a310d87f1ebbde6f328be0a99cdbcadf4d6589cf29de4b8ffd2"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!(
+            "04fe8c19ce0905191ebc298a9245792531f26f0cece2460639e8bc39cb7f706a82"
+            "6a779b4cf969b8a0e539c7f62fb3d30ad6aa8f80e30f1d128aafd68a2ce72ea0"
+        )
+    );
+
+    check_receiver::<DhKemP256HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        &private_key,
+        &hex!(
+            "04a92719c6195d5085104f469a8b9814d5838ff72b60501e2c4466e5e67b325ac9"
+            "8536d7b61a1af4b78e5b7f951c0900be863c403ce65c9bfcb9382657222d18c4"
+        ),
+        &[&hex!(
+            "5ad590bb8baa577f8619db35a36311226a896e7342a6d836d8b7bcd2f20b6c7f9076ac232e3ab2523f39513434"
+        )],
+        &[(
+            b"TestContext",
+            &hex!("d8f1ea7942adbba7412c6d431c62d01371ea476b823eb697e1f6e6cae1dab85a"),
+        )],
+    );
+}
+
+/// DHKEM(P-256, HKDF-SHA256), HKDF-SHA512, ChaCha20Poly1305, base mode
+#[test]
+fn p256_sha512_chacha20poly1305_base() {
+    let private_key = P256PrivateKey::new(&hex!(
+        "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"
+    ));
+
+    check_receiver::<DhKemP256HkdfSha256, HkdfSha512, ChaCha20Poly1305>(
+        Mode::Base,
+        &private_key,
+        &hex!(
+            "041f140146bfb1b251f84f4ddbe0d4cdcfd77afd984a9520e35794021f8312bb9e"
+            "ec995a08b1fa7704df3dcc0b50a9665263fb7711f95f9f8a449c5096e47c892b"
+        ),
+        &[
+            &hex!("6ec5aaa271fdd4b222aa4f0309d9675f6f15f850d7efb10fc96b91ce6be73da4649ff40ef5af94e53f96aea14f"),
+            &hex!("10359dc0f326eaeb28fab8a10f20bde9ad90d8362654ccc2c56c587a215b801e4c1007511f13e9061fcdd98d33"),
+        ],
+        &[(
+            b"TestContext",
+            &hex!("16a19bc1a737fbaf73a5c02ef0726842cf436109d6d9d79160ff8b614350c64e"),
+        )],
+    );
+}
+
+#[test]
+fn x25519_round_trip() {
+    let private_key = X25519PrivateKey::generate();
+    let (enc, mut sender) = hpke::setup_sender::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        private_key.public_key(),
+        INFO,
+    )
+    .unwrap();
+
+    let mut receiver = hpke::setup_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        &private_key,
+        &enc,
+        INFO,
+    )
+    .unwrap();
+
+    for i in 0..3 {
+        let ciphertext = sender.seal(b"aad", PLAINTEXT).unwrap();
+        assert_eq!(receiver.open(b"aad", &ciphertext).unwrap(), PLAINTEXT);
+        assert_eq!(sender.sequence_number(), i + 1);
+        assert_eq!(receiver.sequence_number(), i + 1);
+    }
+
+    let mut sender_secret = [0u8; 64];
+    let mut receiver_secret = [0u8; 64];
+    sender.export(b"context", &mut sender_secret).unwrap();
+    receiver.export(b"context", &mut receiver_secret).unwrap();
+    assert_eq!(sender_secret, receiver_secret);
+}
+
+#[test]
+fn p256_single_shot() {
+    let private_key = P256PrivateKey::new(&[0x42; 32]);
+    let mode = Mode::Psk {
+        psk: PSK,
+        psk_id: PSK_ID,
+    };
+
+    let (enc, ciphertext) = hpke::seal::<DhKemP256HkdfSha256, HkdfSha384, Aes256Gcm>(
+        mode,
+        private_key.public_key(),
+        INFO,
+        b"aad",
+        PLAINTEXT,
+    )
+    .unwrap();
+
+    assert_eq!(enc.len(), 65);
+    assert_eq!(
+        hpke::open::<DhKemP256HkdfSha256, HkdfSha384, Aes256Gcm>(
+            mode,
+            &private_key,
+            &enc,
+            INFO,
+            b"aad",
+            &ciphertext
+        )
+        .unwrap(),
+        PLAINTEXT
+    );
+    assert_eq!(
+        hpke::open::<DhKemP256HkdfSha256, HkdfSha384, Aes256Gcm>(
+            Mode::Base,
+            &private_key,
+            &enc,
+            INFO,
+            b"aad",
+            &ciphertext
+        ),
+        Err(HpkeError::Open)
+    );
+}
+
+#[test]
+fn failed_open_keeps_sequence_number() {
+    let private_key = X25519PrivateKey::generate();
+    let (enc, mut sender) = hpke::setup_sender::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        private_key.public_key(),
+        INFO,
+    )
+    .unwrap();
+
+    let mut receiver = hpke::setup_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        &private_key,
+        &enc,
+        INFO,
+    )
+    .unwrap();
+
+    let ciphertext = sender.seal(b"aad", PLAINTEXT).unwrap();
+    assert_eq!(receiver.open(b"other", &ciphertext), Err(HpkeError::Open));
+    assert_eq!(receiver.sequence_number(), 0);
+    assert_eq!(receiver.open(b"aad", &ciphertext).unwrap(), PLAINTEXT);
+}
+
+#[test]
+fn invalid_inputs() {
+    let private_key = X25519PrivateKey::generate();
+
+    // PSK mode requires both a PSK and a PSK ID
+    let mode = Mode::Psk {
+        psk: PSK,
+        psk_id: b"",
+    };
+    assert!(matches!(
+        hpke::setup_sender::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+            mode,
+            private_key.public_key(),
+            INFO
+        ),
+        Err(HpkeError::InvalidPsk)
+    ));
+
+    // Low-order points produce an all-zero shared secret
+    assert!(matches!(
+        hpke::setup_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+            Mode::Base,
+            &private_key,
+            &[0; 32],
+            INFO
+        ),
+        Err(HpkeError::InvalidKey)
+    ));
+    assert!(matches!(
+        hpke::setup_sender::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+            Mode::Base,
+            &[0; 32],
+            INFO
+        ),
+        Err(HpkeError::InvalidKey)
+    ));
+
+    // Private keys must belong to the KEM
+    let (enc, _) = hpke::setup_sender::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        private_key.public_key(),
+        INFO,
+    )
+    .unwrap();
+    assert!(matches!(
+        hpke::setup_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+            Mode::Base,
+            &P256PrivateKey::new(&[0x42; 32]),
+            &enc,
+            INFO
+        ),
+        Err(HpkeError::InvalidKey)
+    ));
+
+    // Encapsulated keys must have the KEM's public key length
+    assert!(matches!(
+        hpke::setup_receiver::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+            Mode::Base,
+            &private_key,
+            &[9; 33],
+            INFO
+        ),
+        Err(HpkeError::InvalidKey)
+    ));
+
+    // Exports are limited to 255 hash outputs
+    let (_, sender) = hpke::setup_sender::<DhKemX25519HkdfSha256, HkdfSha256, Aes128Gcm>(
+        Mode::Base,
+        private_key.public_key(),
+        INFO,
+    )
+    .unwrap();
+
+    let mut secret = vec![0u8; 255 * 32 + 1];
+    assert_eq!(
+        sender.export(b"", &mut secret),
+        Err(HpkeError::ExportTooLong)
+    );
+    assert!(sender.export(b"", &mut secret[..255 * 32]).is_ok());
+}
@@ -0,0 +1,272 @@
+//! `std::io` adapter tests
+
+use ring_compat::aead::{
+    io::{DecryptingReader, EncryptingWriter},
+    stream::DecryptorBE32,
+    AeadAlgorithm, Aes128Gcm, AnyAead, ChaCha20Poly1305, KeyInit, XChaCha20Poly1305,
+};
+use std::io::{self, Read, Write};
+
+const KEY: [u8; 32] = [0x42; 32];
+
+/// Small chunk size to exercise chunk boundaries
+const CHUNK_SIZE: usize = 16;
+
+/// Size of a nonce prefix for 12-byte nonces
+const PREFIX_SIZE: usize = 7;
+
+/// Size of an encrypted chunk
+const SEALED_CHUNK_SIZE: usize = CHUNK_SIZE + 16;
+
+fn cipher() -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(&KEY.into())
+}
+
+fn message(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+}
+
+fn encrypt(plaintext: &[u8]) -> Vec<u8> {
+    let mut writer = EncryptingWriter::with_chunk_size(cipher(), Vec::new(), CHUNK_SIZE).unwrap();
+    io::copy(&mut &plaintext[..], &mut writer).unwrap();
+    writer.finish().unwrap()
+}
+
+fn decrypt(ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = DecryptingReader::with_chunk_size(cipher(), ciphertext, CHUNK_SIZE);
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+#[test]
+fn round_trip() {
+    for len in [
+        0,
+        1,
+        CHUNK_SIZE - 1,
+        CHUNK_SIZE,
+        CHUNK_SIZE + 1,
+        3 * CHUNK_SIZE,
+        100,
+    ] {
+        let plaintext = message(len);
+        let ciphertext = encrypt(&plaintext);
+
+        let chunks = len.saturating_sub(1) / CHUNK_SIZE + 1;
+        assert_eq!(ciphertext.len(), PREFIX_SIZE + len + chunks * 16);
+        assert_eq!(decrypt(&ciphertext).unwrap(), plaintext, "len {}", len);
+    }
+}
+
+#[test]
+fn default_chunk_size() {
+    let plaintext = message(200_000);
+
+    let mut writer = EncryptingWriter::new(cipher(), Vec::new()).unwrap();
+    writer.write_all(&plaintext).unwrap();
+    let ciphertext = writer.finish().unwrap();
+
+    let mut decrypted = Vec::new();
+    DecryptingReader::new(cipher(), &ciphertext[..])
+        .read_to_end(&mut decrypted)
+        .unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn other_ciphers() {
+    let plaintext = message(50);
+
+    let mut writer =
+        EncryptingWriter::with_chunk_size(Aes128Gcm::new(&[0x42; 16].into()), Vec::new(), 16)
+            .unwrap();
+    writer.write_all(&plaintext).unwrap();
+    let ciphertext = writer.finish().unwrap();
+    let mut decrypted = Vec::new();
+    DecryptingReader::with_chunk_size(
+        Aes128Gcm::new(&[0x42; 16].into()),
+        &ciphertext[..],
+        CHUNK_SIZE,
+    )
+    .read_to_end(&mut decrypted)
+    .unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // 24-byte nonces leave a 19-byte nonce prefix
+    let mut writer =
+        EncryptingWriter::with_chunk_size(XChaCha20Poly1305::new(&KEY.into()), Vec::new(), 16)
+            .unwrap();
+    writer.write_all(&plaintext).unwrap();
+    let ciphertext = writer.finish().unwrap();
+    assert_eq!(ciphertext.len(), 19 + 50 + 4 * 16);
+    let mut decrypted = Vec::new();
+    DecryptingReader::with_chunk_size(
+        XChaCha20Poly1305::new(&KEY.into()),
+        &ciphertext[..],
+        CHUNK_SIZE,
+    )
+    .read_to_end(&mut decrypted)
+    .unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    // Ciphers without `KeyInit`
+    let any_aead = || AnyAead::new(AeadAlgorithm::ChaCha20Poly1305, &KEY).unwrap();
+    let mut writer = EncryptingWriter::with_chunk_size(any_aead(), Vec::new(), 16).unwrap();
+    writer.write_all(&plaintext).unwrap();
+    let ciphertext = writer.finish().unwrap();
+    let mut decrypted = Vec::new();
+    DecryptingReader::with_chunk_size(any_aead(), &ciphertext[..], CHUNK_SIZE)
+        .read_to_end(&mut decrypted)
+        .unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn stream_be32_format() {
+    let plaintext = message(40);
+    let ciphertext = encrypt(&plaintext);
+    let (prefix, chunks) = ciphertext.split_at(PREFIX_SIZE);
+
+    let mut decryptor = DecryptorBE32::from_aead(cipher(), prefix.into());
+    let mut chunks = chunks.chunks(SEALED_CHUNK_SIZE);
+    let mut decrypted = decryptor.decrypt_next(chunks.next().unwrap()).unwrap();
+    decrypted.extend(decryptor.decrypt_next(chunks.next().unwrap()).unwrap());
+    decrypted.extend(decryptor.decrypt_last(chunks.next().unwrap()).unwrap());
+    assert!(chunks.next().is_none());
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn byte_at_a_time() {
+    let plaintext = message(50);
+
+    let mut writer = EncryptingWriter::with_chunk_size(cipher(), Vec::new(), CHUNK_SIZE).unwrap();
+    for byte in &plaintext {
+        writer.write_all(&[*byte]).unwrap();
+    }
+    let ciphertext = writer.finish().unwrap();
+
+    let mut reader = DecryptingReader::with_chunk_size(cipher(), &ciphertext[..], CHUNK_SIZE);
+    let mut decrypted = Vec::new();
+    let mut byte = [0u8];
+    while reader.read(&mut byte).unwrap() == 1 {
+        decrypted.push(byte[0]);
+    }
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn unfinished_stream() {
+    let mut writer = EncryptingWriter::with_chunk_size(cipher(), Vec::new(), CHUNK_SIZE).unwrap();
+    writer.write_all(&message(50)).unwrap();
+    let ciphertext = writer.get_ref().clone();
+
+    let err = decrypt(&ciphertext).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn truncated() {
+    let ciphertext = encrypt(&message(50));
+
+    for len in 0..ciphertext.len() {
+        assert!(decrypt(&ciphertext[..len]).is_err(), "len {}", len);
+    }
+}
+
+#[test]
+fn extended() {
+    let mut ciphertext = encrypt(&message(50));
+    let last = ciphertext[ciphertext.len() - SEALED_CHUNK_SIZE..].to_vec();
+    ciphertext.extend_from_slice(&last);
+    assert!(decrypt(&ciphertext).is_err());
+}
+
+#[test]
+fn reordered() {
+    let ciphertext = encrypt(&message(48));
+    let (prefix, chunks) = ciphertext.split_at(PREFIX_SIZE);
+    let (first, rest) = chunks.split_at(SEALED_CHUNK_SIZE);
+    let (second, last) = rest.split_at(SEALED_CHUNK_SIZE);
+
+    let reordered = [prefix, second, first, last].concat();
+    assert!(decrypt(&reordered).is_err());
+}
+
+#[test]
+fn modified() {
+    let ciphertext = encrypt(&message(40));
+
+    for i in 0..ciphertext.len() {
+        let mut modified = ciphertext.clone();
+        modified[i] ^= 1;
+        assert!(decrypt(&modified).is_err(), "byte {}", i);
+    }
+}
+
+#[test]
+fn errors_are_sticky() {
+    let mut ciphertext = encrypt(&message(40));
+    ciphertext[PREFIX_SIZE] ^= 1;
+
+    let mut reader = DecryptingReader::with_chunk_size(cipher(), &ciphertext[..], CHUNK_SIZE);
+    let mut buf = [0u8; 8];
+    assert!(reader.read(&mut buf).is_err());
+    assert!(reader.read(&mut buf).is_err());
+}
+
+#[test]
+fn wrong_key() {
+    let ciphertext = encrypt(&message(40));
+
+    let mut reader = DecryptingReader::with_chunk_size(
+        ChaCha20Poly1305::new(&[0x43; 32].into()),
+        &ciphertext[..],
+        CHUNK_SIZE,
+    );
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
+
+/// Reader which fails with [`io::ErrorKind::WouldBlock`] before every byte
+struct NonBlocking<'a> {
+    data: &'a [u8],
+    ready: bool,
+}
+
+impl Read for NonBlocking<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.ready = !self.ready;
+        if !self.ready {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let len = buf.len().min(self.data.len()).min(1);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn would_block() {
+    let plaintext = message(50);
+    let ciphertext = encrypt(&plaintext);
+
+    let inner = NonBlocking {
+        data: &ciphertext,
+        ready: true,
+    };
+    let mut reader = DecryptingReader::with_chunk_size(cipher(), inner, CHUNK_SIZE);
+    let mut decrypted = Vec::new();
+    let mut buf = [0u8; 64];
+
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => decrypted.extend_from_slice(&buf[..n]),
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+        }
+    }
+    assert_eq!(decrypted, plaintext);
+}
@@ -0,0 +1,322 @@
+//! JWE tests
+//!
+//! Besides the RFC 7520 example, the vectors were generated with Python's
+//! `cryptography` package: the RFC 7518 Appendix C vector uses the content
+//! encryption key derived in the RFC, and the X25519 vector uses the keys
+//! from RFC 7748 section 6.1.
+
+use crate::agreement::{P256PrivateKey, P384PrivateKey, X25519PrivateKey};
+use hex_literal::hex;
+use ring_compat::{
+    aead::jwe::{
+        self, Algorithm, Curve, DecryptionKey, Encryption, Encryptor, JweError, Recipient,
+    },
+    agreement::PrivateKey,
+};
+
+const PLAINTEXT: &[u8] = b"Live long and prosper.";
+
+/// RFC 7520 section 5.6: content encryption key
+const RFC7520_KEY: [u8; 16] = hex!("5dcb4e84902403ea43f4b87b6605bfd8");
+
+const RFC7520_KID: &str = "77c7e2b8-6e13-45cf-8672-617b5b45243a";
+
+/// RFC 7520 section 5.6: plaintext
+const RFC7520_PLAINTEXT: &str =
+    "You can trust us to stick with you through thick and thin\u{2013}to the \
+    bitter end. And you can trust us to keep any secret of yours\u{2013}closer than you keep it \
+    yourself. But you cannot trust us to let you face trouble alone, and go off without a word. \
+    We are your friends, Frodo.";
+
+/// RFC 7520 section 5.6: compact serialization
+const RFC7520_COMPACT: &str = concat!(
+    "eyJhbGciOiJkaXIiLCJraWQiOiI3N2M3ZTJiOC02ZTEzLTQ1Y2YtODY3Mi02MTdiNWI0NTI0M2EiLCJlbmMiOiJBMTI4R0NNIn0",
+    "..",
+    "refa467QzzKx6QAB",
+    ".",
+    "JW_i_f52hww_ELQPGaYyeAB6HYGcR559l9TYnSovc23XJoBcW29rHP8yZOZG7YhLpT1bjFuvZPjQS-m0IFtVcXkZXdH_lr_FrdYt9HRUYkshtrMmIUAyGmUnd9zMDB2n0cRDIHAzFVeJUDxkUwVAE7_YGRPdcqMyiBoCO-FBdE-Nceb4h3-FtBP-c_BIwCPTjb9o0SbdcdREEMJMyZBH8ySWMVi1gPD9yxi-aQpGbSv_F9N4IZAxscj5g-NJsUPbjk29-s7LJAGb15wEBtXphVCgyy53CoIKLHHeJHXex45Uz9aKZSRSInZI-wjsY0yu3cT4_aQ3i1o-tiE-F8Ios61EKgyIQ4CWao8PFMj8TTnp",
+    ".",
+    "vbb32Xvllea2OtmHAdccRQ",
+);
+
+#[test]
+fn rfc7520_dir_a128gcm_compact() {
+    let decrypted = jwe::decrypt(DecryptionKey::Direct(&RFC7520_KEY), RFC7520_COMPACT).unwrap();
+
+    assert_eq!(decrypted.plaintext, RFC7520_PLAINTEXT.as_bytes());
+    assert_eq!(decrypted.header.algorithm(), Algorithm::Dir);
+    assert_eq!(decrypted.header.encryption(), Encryption::A128Gcm);
+    assert_eq!(decrypted.header.key_id(), Some(RFC7520_KID));
+    assert_eq!(
+        jwe::decode_header(RFC7520_COMPACT).unwrap(),
+        decrypted.header
+    );
+}
+
+#[test]
+fn rfc7520_dir_a128gcm_json() {
+    let parts = RFC7520_COMPACT.split('.').collect::<Vec<_>>();
+
+    let flattened = format!(
+        r#"{{"protected":"{}","iv":"{}","ciphertext":"{}","tag":"{}"}}"#,
+        parts[0], parts[2], parts[3], parts[4]
+    );
+
+    let general = format!(
+        r#"{{
+            "recipients": [{{}}],
+            "protected": "{}",
+            "iv": "{}",
+            "ciphertext": "{}",
+            "tag": "{}"
+        }}"#,
+        parts[0], parts[2], parts[3], parts[4]
+    );
+
+    for jwe in [flattened, general] {
+        let decrypted = jwe::decrypt(DecryptionKey::Direct(&RFC7520_KEY), &jwe).unwrap();
+        assert_eq!(decrypted.plaintext, RFC7520_PLAINTEXT.as_bytes());
+        assert_eq!(decrypted.header.key_id(), Some(RFC7520_KID));
+        assert!(decrypted.aad.is_empty());
+    }
+}
+
+/// General JSON serialization with shared unprotected and per-recipient
+/// headers, and additional authenticated data
+#[test]
+fn json_unprotected_headers_and_aad() {
+    let jwe = r#"{
+        "protected": "eyJlbmMiOiJBMTI4R0NNIn0",
+        "unprotected": {
+            "kid": "77c7e2b8-6e13-45cf-8672-617b5b45243a"
+        },
+        "recipients": [
+            {
+                "header": {
+                    "alg": "dir"
+                }
+            }
+        ],
+        "aad": "VGhlIEZlbGxvd3NoaXAgb2YgdGhlIFJpbmc",
+        "iv": "GBkaGxwdHh8gISIj",
+        "ciphertext": "Ufdunbea3ByHdL80Lm70acoiBEOzNg",
+        "tag": "50Nas3HXZsSQKfvckDqycA"
+    }"#;
+
+    let decrypted = jwe::decrypt(DecryptionKey::Direct(&RFC7520_KEY), jwe).unwrap();
+    assert_eq!(decrypted.plaintext, PLAINTEXT);
+    assert_eq!(decrypted.aad, b"The Fellowship of the Ring");
+    assert_eq!(decrypted.header.algorithm(), Algorithm::Dir);
+    assert_eq!(decrypted.header.key_id(), Some(RFC7520_KID));
+
+    // The AAD is authenticated
+    let modified = jwe.replace("VGhlIEZlbGxvd3NoaXAgb2YgdGhlIFJpbmc", "VGhlIFR3byBUb3dlcnM");
+    assert_eq!(
+        jwe::decrypt(DecryptionKey::Direct(&RFC7520_KEY), &modified),
+        Err(JweError::Decryption)
+    );
+
+    // Header parameters must be disjoint
+    let duplicated = jwe.replace(r#""alg": "dir""#, r#""alg": "dir", "enc": "A128GCM""#);
+    assert_eq!(
+        jwe::decrypt(DecryptionKey::Direct(&RFC7520_KEY), &duplicated),
+        Err(JweError::Malformed)
+    );
+}
+
+/// ECDH-ES with the keys and `apu`/`apv` from RFC 7518 Appendix C
+#[test]
+fn rfc7518_ecdh_es_p256() {
+    // Bob's private key
+    let private_key = P256PrivateKey::new(&hex!(
+        "5449836690d75caf29f0dd029ddb31b3ddb8aba9d2d515c5012465e817d4a9dc"
+    ));
+
+    let jwe = concat!(
+        "eyJhbGciOiJFQ0RILUVTIiwiZW5jIjoiQTEyOEdDTSIsImFwdSI6IlFXeHBZMlUiLCJhcHYiOiJRbTlpIiwiZXBrIjp7",
+        "Imt0eSI6IkVDIiwiY3J2IjoiUC0yNTYiLCJ4IjoiZ0kwR0FJTEJkdTdUNTNha3JGbU15R2NzRjNuNWRPN01td05CSEtX",
+        "NVNWMCIsInkiOiJTTFdfeFNmZnpsUFdySEVWSTMwREhNXzRlZ1Z3dDNOUXFlVUQ3bk1GcHBzIn19",
+        "..AAECAwQFBgcICQoL.OOJ0gW29xd7dIlx_S61IxQjnT9Q6HA.YyP2SxphBXbAq-G2ODC2rw",
+    );
+
+    let decrypted = jwe::decrypt(DecryptionKey::EcdhEs(&private_key), jwe).unwrap();
+    assert_eq!(decrypted.plaintext, PLAINTEXT);
+    assert_eq!(decrypted.header.algorithm(), Algorithm::EcdhEs);
+    assert_eq!(decrypted.header.encryption(), Encryption::A128Gcm);
+
+    // The key must match the algorithm
+    assert_eq!(
+        jwe::decrypt(DecryptionKey::Direct(&[0; 16]), jwe),
+        Err(JweError::InvalidKey)
+    );
+}
+
+/// ECDH-ES with X25519 and A256GCM, using the keys from RFC 7748
+#[test]
+fn ecdh_es_x25519() {
+    let private_key = X25519PrivateKey::new(hex!(
+        "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb"
+    ));
+
+    let jwe = concat!(
+        "eyJhbGciOiJFQ0RILUVTIiwiZW5jIjoiQTI1NkdDTSIsImVwayI6eyJrdHkiOiJPS1AiLCJjcnYiOiJYMjU1MTkiLCJ4",
+        "IjoiaFNEd0NZa3dwMVIwaTMzY3RENzNXZzJfT2cwbU9CcjA2NlNwanFxYlRtbyJ9fQ",
+        "..DA0ODxAREhMUFRYX.uFDaOzbWgKshQ3gRtaRdDg8xXqOQaw.4C7dx1v8Y09_MzM6CV9RAw",
+    );
+
+    let decrypted = jwe::decrypt(DecryptionKey::EcdhEs(&private_key), jwe).unwrap();
+    assert_eq!(decrypted.plaintext, PLAINTEXT);
+    assert_eq!(decrypted.header.encryption(), Encryption::A256Gcm);
+
+    // The key's curve must match the ephemeral key's
+    let p256_key = P256PrivateKey::new(&[0x42; 32]);
+    assert_eq!(
+        jwe::decrypt(DecryptionKey::EcdhEs(&p256_key), jwe),
+        Err(JweError::InvalidKey)
+    );
+}
+
+#[test]
+fn dir_round_trip() {
+    let key = [0x42; 32];
+    let encryptor = Encryptor::new(Recipient::Direct(&key), Encryption::A256Gcm)
+        .with_key_id("key-1")
+        .with_media_type("JWT")
+        .with_content_type("JWT");
+
+    assert!(format!("{:?}", encryptor).contains("recipient: Direct { .. }"));
+
+    let compact = encryptor.encrypt_compact(PLAINTEXT).unwrap();
+    assert_eq!(compact.split('.').nth(1), Some(""));
+
+    let decrypted = jwe::decrypt(DecryptionKey::Direct(&key), &compact).unwrap();
+    assert_eq!(decrypted.plaintext, PLAINTEXT);
+    assert_eq!(decrypted.header.key_id(), Some("key-1"));
+    assert_eq!(decrypted.header.media_type(), Some("JWT"));
+    assert_eq!(decrypted.header.content_type(), Some("JWT"));
+
+    let json = encryptor.encrypt_json(PLAINTEXT, b"aad").unwrap();
+    let decrypted = jwe::decrypt(DecryptionKey::Direct(&key), &json).unwrap();
+    assert_eq!(decrypted.plaintext, PLAINTEXT);
+    assert_eq!(decrypted.aad, b"aad");
+
+    // Key size must match the content encryption algorithm
+    assert_eq!(
+        Encryptor::new(Recipient::Direct(&key), Encryption::A128Gcm).encrypt_compact(PLAINTEXT),
+        Err(JweError::InvalidKey)
+    );
+    assert_eq!(
+        jwe::decrypt(DecryptionKey::Direct(&key[..16]), &compact),
+        Err(JweError::InvalidKey)
+    );
+}
+
+#[test]
+fn ecdh_es_round_trip() {
+    let p256_key = P256PrivateKey::new(&[0x42; 32]);
+    let p384_key = P384PrivateKey::new(&[0x42; 48]);
+    let x25519_key = X25519PrivateKey::new([0x42; 32]);
+
+    let recipients: [(Recipient<'_>, &dyn PrivateKey); 3] = [
+        (
+            Recipient::EcdhEs(Curve::P256, p256_key.public_key()),
+            &p256_key,
+        ),
+        (
+            Recipient::EcdhEs(Curve::P384, p384_key.public_key()),
+            &p384_key,
+        ),
+        (
+            Recipient::EcdhEs(Curve::X25519, x25519_key.public_key()),
+            &x25519_key,
+        ),
+    ];
+
+    for (recipient, private_key) in recipients {
+        for enc in [Encryption::A128Gcm, Encryption::A256Gcm] {
+            let encryptor = Encryptor::new(recipient, enc).with_party_info(b"Alice", b"Bob");
+            let compact = encryptor.encrypt_compact(PLAINTEXT).unwrap();
+            let json = encryptor.encrypt_json(PLAINTEXT, b"").unwrap();
+
+            for jwe in [compact, json] {
+                let decrypted = jwe::decrypt(DecryptionKey::EcdhEs(private_key), &jwe).unwrap();
+                assert_eq!(decrypted.plaintext, PLAINTEXT);
+                assert_eq!(decrypted.header.algorithm(), Algorithm::EcdhEs);
+                assert_eq!(decrypted.header.encryption(), enc);
+            }
+        }
+    }
+}
+
+#[test]
+fn protected_header_is_authenticated() {
+    let mut parts = RFC7520_COMPACT.split('.').collect::<Vec<_>>();
+
+    // {"alg":"dir","kid":"other","enc":"A128GCM"}
+    parts[0] = "eyJhbGciOiJkaXIiLCJraWQiOiJvdGhlciIsImVuYyI6IkExMjhHQ00ifQ";
+    assert_eq!(
+        jwe::decrypt(DecryptionKey::Direct(&RFC7520_KEY), &parts.join(".")),
+        Err(JweError::Decryption)
+    );
+}
+
+#[test]
+fn invalid_recipient_public_key() {
+    let encryptor = Encryptor::new(
+        Recipient::EcdhEs(Curve::X25519, &[0; 32]),
+        Encryption::A128Gcm,
+    );
+    assert_eq!(
+        encryptor.encrypt_compact(PLAINTEXT),
+        Err(JweError::InvalidKey)
+    );
+
+    let encryptor = Encryptor::new(
+        Recipient::EcdhEs(Curve::P256, &[4; 65]),
+        Encryption::A128Gcm,
+    );
+    assert_eq!(
+        encryptor.encrypt_compact(PLAINTEXT),
+        Err(JweError::InvalidKey)
+    );
+}
+
+#[test]
+fn unsupported_and_malformed() {
+    let key = DecryptionKey::Direct(&RFC7520_KEY);
+    let parts = RFC7520_COMPACT.split('.').collect::<Vec<_>>();
+    let with_header = |header: &str| [header, "", parts[2], parts[3], parts[4]].join(".");
+
+    // RFC 7516 Appendix A.1 header: {"alg":"RSA-OAEP","enc":"A256GCM"}
+    let rsa = with_header("eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkEyNTZHQ00ifQ");
+    assert_eq!(jwe::decode_header(&rsa), Err(JweError::Unsupported));
+
+    // {"alg":"dir","enc":"A128GCM","zip":"DEF"}
+    let zip = with_header("eyJhbGciOiJkaXIiLCJlbmMiOiJBMTI4R0NNIiwiemlwIjoiREVGIn0");
+    assert_eq!(jwe::decrypt(key, &zip), Err(JweError::Unsupported));
+
+    // {"alg":"dir","enc":"A128GCM","crit":["exp"],"exp":0}
+    let crit =
+        with_header("eyJhbGciOiJkaXIiLCJlbmMiOiJBMTI4R0NNIiwiY3JpdCI6WyJleHAiXSwiZXhwIjowfQ");
+    assert_eq!(jwe::decrypt(key, &crit), Err(JweError::Unsupported));
+
+    // {"alg":"ECDH-ES","enc":"A128GCM"} without `epk`
+    let no_epk = with_header("eyJhbGciOiJFQ0RILUVTIiwiZW5jIjoiQTEyOEdDTSJ9");
+    assert_eq!(jwe::decrypt(key, &no_epk), Err(JweError::Malformed));
+
+    // Neither `dir` nor `ECDH-ES` have an encrypted key
+    let encrypted_key = [parts[0], "AAAA", parts[2], parts[3], parts[4]].join(".");
+    assert_eq!(jwe::decrypt(key, &encrypted_key), Err(JweError::Malformed));
+
+    for jwe in [
+        "",
+        "{}",
+        &parts[..4].join("."),
+        &[RFC7520_COMPACT, ""].join("."),
+        &RFC7520_COMPACT.replace("refa467QzzKx6QAB", "refa467QzzKx6Q"),
+        &RFC7520_COMPACT.replace("vbb32Xvllea2OtmHAdccRQ", "vbb32Xvllea2OtmHAdccRQ=="),
+        &RFC7520_COMPACT.replace("vbb32Xvllea2OtmHAdccRQ", "vbb32Xvllea2OtmHAdccRR"),
+    ] {
+        assert_eq!(jwe::decrypt(key, jwe), Err(JweError::Malformed), "{}", jwe);
+    }
+}
@@ -0,0 +1,185 @@
+//! Keyset tests
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{
+        keyset::{KeyInfo, KeyStatus, Keyset},
+        AeadAlgorithm, AeadInPlace, AnyAead,
+    },
+    generic_array::GenericArray,
+};
+
+const AAD: &[u8] = b"keyset test";
+const PLAINTEXT: &[u8] = b"rotate me";
+
+const KEY_128: [u8; 16] = hex!("000102030405060708090a0b0c0d0e0f");
+const KEY_256: [u8; 32] = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+
+fn keyset() -> Keyset {
+    let mut keyset = Keyset::new();
+    keyset
+        .add_key(
+            0x0102_0304,
+            AeadAlgorithm::Aes128Gcm,
+            &KEY_128,
+            KeyStatus::Enabled,
+        )
+        .unwrap();
+    keyset
+        .add_key(
+            0x0506_0708,
+            AeadAlgorithm::ChaCha20Poly1305,
+            &KEY_256,
+            KeyStatus::Primary,
+        )
+        .unwrap();
+    keyset
+}
+
+#[test]
+fn ciphertext_format() {
+    let keyset = keyset();
+    let ciphertext = keyset.encrypt(PLAINTEXT, AAD).unwrap();
+
+    assert_eq!(ciphertext.len(), 5 + 12 + PLAINTEXT.len() + 16);
+    assert_eq!(&ciphertext[..5], hex!("0105060708"));
+    assert_eq!(Keyset::key_id(&ciphertext).unwrap(), 0x0506_0708);
+
+    // The body is nonce || ciphertext || tag under the primary key
+    let (nonce, rest) = ciphertext[5..].split_at(12);
+    let mut buffer = rest.to_vec();
+    AnyAead::new(AeadAlgorithm::ChaCha20Poly1305, &KEY_256)
+        .unwrap()
+        .decrypt_in_place(GenericArray::from_slice(nonce), AAD, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+}
+
+#[test]
+fn random_nonces() {
+    let keyset = keyset();
+    assert_ne!(
+        keyset.encrypt(PLAINTEXT, AAD).unwrap(),
+        keyset.encrypt(PLAINTEXT, AAD).unwrap()
+    );
+}
+
+#[test]
+fn rotation() {
+    let mut keyset = keyset();
+    let old_ciphertext = keyset.encrypt(PLAINTEXT, AAD).unwrap();
+
+    let new_id = keyset.generate_key(AeadAlgorithm::Aes256Gcm).unwrap();
+    assert_eq!(keyset.primary_id(), Some(0x0506_0708));
+    keyset.set_primary(new_id).unwrap();
+    assert_eq!(keyset.primary_id(), Some(new_id));
+
+    let new_ciphertext = keyset.encrypt(PLAINTEXT, AAD).unwrap();
+    assert_eq!(Keyset::key_id(&new_ciphertext).unwrap(), new_id);
+
+    // Both old and new ciphertexts are readable
+    assert_eq!(keyset.decrypt(&old_ciphertext, AAD).unwrap(), PLAINTEXT);
+    assert_eq!(keyset.decrypt(&new_ciphertext, AAD).unwrap(), PLAINTEXT);
+
+    // Until the old key is disabled
+    keyset.disable(0x0506_0708).unwrap();
+    assert!(keyset.decrypt(&old_ciphertext, AAD).is_err());
+    keyset.enable(0x0506_0708).unwrap();
+    assert_eq!(keyset.decrypt(&old_ciphertext, AAD).unwrap(), PLAINTEXT);
+
+    // ...or removed
+    keyset.remove(0x0506_0708).unwrap();
+    assert!(keyset.decrypt(&old_ciphertext, AAD).is_err());
+}
+
+#[test]
+fn primary_key_restrictions() {
+    let mut keyset = keyset();
+
+    assert!(keyset.disable(0x0506_0708).is_err());
+    assert!(keyset.remove(0x0506_0708).is_err());
+    assert!(keyset
+        .add_key(1, AeadAlgorithm::Aes128Gcm, &KEY_128, KeyStatus::Primary)
+        .is_err());
+    assert!(keyset
+        .add_key(
+            0x0102_0304,
+            AeadAlgorithm::Aes128Gcm,
+            &KEY_128,
+            KeyStatus::Enabled
+        )
+        .is_err());
+    assert!(keyset
+        .add_key(1, AeadAlgorithm::Aes256Gcm, &KEY_128, KeyStatus::Enabled)
+        .is_err());
+    assert!(keyset.set_primary(1).is_err());
+
+    assert!(Keyset::new().encrypt(PLAINTEXT, AAD).is_err());
+}
+
+#[test]
+fn decrypt_invalid() {
+    let keyset = keyset();
+    let ciphertext = keyset.encrypt(PLAINTEXT, AAD).unwrap();
+
+    // Wrong associated data
+    assert!(keyset.decrypt(&ciphertext, b"wrong").is_err());
+
+    // Unknown key ID
+    let mut modified = ciphertext.clone();
+    modified[4] ^= 1;
+    assert!(keyset.decrypt(&modified, AAD).is_err());
+
+    // Wrong version
+    let mut modified = ciphertext.clone();
+    modified[0] = 0;
+    assert!(keyset.decrypt(&modified, AAD).is_err());
+
+    // Truncated
+    assert!(keyset.decrypt(&ciphertext[..5 + 12 + 15], AAD).is_err());
+    assert!(keyset.decrypt(&ciphertext[..3], AAD).is_err());
+}
+
+#[test]
+fn serialization() {
+    let mut keyset = keyset();
+    keyset.disable(0x0102_0304).unwrap();
+    let bytes = keyset.to_bytes();
+
+    assert_eq!(
+        bytes[..],
+        [
+            &b"RCKS"[..],
+            &hex!("01 0002"),
+            &hex!("01020304 02 0001 10"),
+            &KEY_128,
+            &hex!("05060708 03 001d 20"),
+            &KEY_256,
+        ]
+        .concat()
+    );
+
+    let decoded = Keyset::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        decoded.keys().collect::<Vec<_>>(),
+        [
+            KeyInfo {
+                id: 0x0102_0304,
+                status: KeyStatus::Disabled,
+                algorithm: AeadAlgorithm::Aes128Gcm,
+            },
+            KeyInfo {
+                id: 0x0506_0708,
+                status: KeyStatus::Primary,
+                algorithm: AeadAlgorithm::ChaCha20Poly1305,
+            },
+        ]
+    );
+
+    let ciphertext = keyset.encrypt(PLAINTEXT, AAD).unwrap();
+    assert_eq!(decoded.decrypt(&ciphertext, AAD).unwrap(), PLAINTEXT);
+
+    // Trailing data and truncation are rejected
+    assert!(Keyset::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+    assert!(Keyset::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}
@@ -0,0 +1,181 @@
+//! Invocation limit tests
+
+use ring_compat::{
+    aead::{
+        limits::{InvocationLimits, LimitError, Limited, Limits},
+        AeadAlgorithm, Aes128Gcm, AnyAead, ChaCha20Poly1305, KeyInit, Tag,
+    },
+    generic_array::GenericArray,
+};
+
+const AAD: &[u8] = b"invocation limits";
+const PLAINTEXT: &[u8] = b"count me";
+
+/// Small limits which can be reached in a test
+const LIMITS: Limits = Limits {
+    max_encryptions: 2,
+    max_failed_decryptions: 2,
+    max_message_size: 16,
+};
+
+fn cipher(byte: u8) -> Aes128Gcm {
+    Aes128Gcm::new(GenericArray::from_slice(&[byte; 16]))
+}
+
+#[test]
+fn default_limits() {
+    assert_eq!(cipher(0).limits(), Limits::AES_GCM);
+    assert_eq!(Limits::AES_GCM.max_encryptions, 1 << 23);
+    assert_eq!(Limits::AES_GCM.max_failed_decryptions, 1 << 52);
+    assert_eq!(Limits::AES_GCM.max_message_size, 1 << 16);
+
+    let chacha = ChaCha20Poly1305::new(GenericArray::from_slice(&[0; 32]));
+    assert_eq!(chacha.limits(), Limits::CHACHA20_POLY1305);
+    assert_eq!(Limits::CHACHA20_POLY1305.max_failed_decryptions, 1 << 36);
+
+    let any = AnyAead::new(AeadAlgorithm::ChaCha20Poly1305, &[0; 32]).unwrap();
+    assert_eq!(any.limits(), Limits::CHACHA20_POLY1305);
+
+    let limited = Limited::new(cipher(0));
+    assert_eq!(limited.limits(), &Limits::AES_GCM);
+}
+
+#[test]
+fn confidentiality_limit() {
+    let mut limited = Limited::with_limits(cipher(0), LIMITS);
+
+    for i in 0..2u8 {
+        let mut buffer = PLAINTEXT.to_vec();
+        limited
+            .encrypt_in_place(&[i; 12].into(), AAD, &mut buffer)
+            .unwrap();
+    }
+
+    assert_eq!(limited.usage().encryptions, 2);
+    assert_eq!(limited.usage().bytes_encrypted, 2 * PLAINTEXT.len() as u64);
+
+    let mut buffer = PLAINTEXT.to_vec();
+    assert_eq!(
+        limited.encrypt_in_place_detached(&[2; 12].into(), AAD, &mut buffer),
+        Err(LimitError::ConfidentialityLimit)
+    );
+    assert_eq!(buffer, PLAINTEXT);
+
+    limited.rekey(cipher(1));
+    assert_eq!(limited.usage().encryptions, 0);
+    limited
+        .encrypt_in_place_detached(&[2; 12].into(), AAD, &mut buffer)
+        .unwrap();
+}
+
+#[test]
+fn integrity_limit() {
+    let mut limited = Limited::with_limits(cipher(0), LIMITS);
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = limited
+        .encrypt_in_place_detached(&[0; 12].into(), AAD, &mut buffer)
+        .unwrap();
+
+    for _ in 0..2 {
+        let mut forgery = buffer.clone();
+        assert_eq!(
+            limited.decrypt_in_place_detached(&[0; 12].into(), AAD, &mut forgery, &Tag::default()),
+            Err(LimitError::Aead)
+        );
+    }
+
+    assert_eq!(limited.usage().failed_decryptions, 2);
+
+    // Even genuine messages are rejected once the limit has been reached
+    assert_eq!(
+        limited.decrypt_in_place_detached(&[0; 12].into(), AAD, &mut buffer, &tag),
+        Err(LimitError::IntegrityLimit)
+    );
+}
+
+#[test]
+fn successful_decryptions_are_not_counted() {
+    let mut limited = Limited::with_limits(cipher(0), LIMITS);
+    let mut buffer = PLAINTEXT.to_vec();
+    limited
+        .encrypt_in_place(&[0; 12].into(), AAD, &mut buffer)
+        .unwrap();
+
+    for _ in 0..3 {
+        let mut message = buffer.clone();
+        limited
+            .decrypt_in_place(&[0; 12].into(), AAD, &mut message)
+            .unwrap();
+        assert_eq!(message, PLAINTEXT);
+    }
+
+    assert_eq!(limited.usage().failed_decryptions, 0);
+}
+
+#[test]
+fn message_too_large() {
+    let mut limited = Limited::with_limits(cipher(0), LIMITS);
+    let mut buffer = [0u8; 17];
+
+    assert_eq!(
+        limited.encrypt_in_place_detached(&[0; 12].into(), AAD, &mut buffer),
+        Err(LimitError::MessageTooLarge)
+    );
+    assert_eq!(limited.usage().encryptions, 0);
+
+    // The default limits only hold for messages of up to 2^16 bytes
+    let mut limited = Limited::new(cipher(0));
+    let mut buffer = vec![0u8; (1 << 16) + 1];
+    assert_eq!(
+        limited.encrypt_in_place_detached(&[0; 12].into(), AAD, &mut buffer),
+        Err(LimitError::MessageTooLarge)
+    );
+    limited
+        .encrypt_in_place_detached(&[0; 12].into(), AAD, &mut buffer[1..])
+        .unwrap();
+}
+
+#[test]
+fn rekey_callback() {
+    let mut next_key = 0u8;
+    let mut ciphertexts = Vec::new();
+
+    {
+        let mut limited = Limited::with_rekey(cipher(0), LIMITS, || {
+            next_key += 1;
+            cipher(next_key)
+        });
+
+        for _ in 0..5 {
+            let mut buffer = PLAINTEXT.to_vec();
+            limited
+                .encrypt_in_place(&[0; 12].into(), AAD, &mut buffer)
+                .unwrap();
+            ciphertexts.push(buffer);
+        }
+
+        assert_eq!(limited.usage().encryptions, 1);
+
+        // The integrity limit isn't handled by the callback
+        for _ in 0..2 {
+            let mut forgery = ciphertexts[4].clone();
+            assert_eq!(
+                limited.decrypt_in_place(&[1; 12].into(), AAD, &mut forgery),
+                Err(LimitError::Aead)
+            );
+        }
+
+        let mut message = ciphertexts[4].clone();
+        assert_eq!(
+            limited.decrypt_in_place(&[0; 12].into(), AAD, &mut message),
+            Err(LimitError::IntegrityLimit)
+        );
+    }
+
+    assert_eq!(next_key, 2);
+
+    // The same nonce was used under three different keys
+    assert_ne!(ciphertexts[0], ciphertexts[2]);
+    assert_ne!(ciphertexts[2], ciphertexts[4]);
+    assert_eq!(ciphertexts[0], ciphertexts[1]);
+}
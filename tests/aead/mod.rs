@@ -5,4 +5,30 @@ mod common;
 
 mod aes128gcm;
 mod aes256gcm;
+mod age;
+mod any;
 mod chacha20poly1305;
+mod chacha20poly1305_openssh;
+mod committing;
+mod cose;
+mod datagram;
+mod derived_key;
+mod envelope;
+mod hpke;
+mod keyset;
+mod limits;
+mod nonce_sequence;
+mod prefixed;
+mod quic;
+mod tls13;
+mod vectored;
+mod xchacha20poly1305;
+
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "digest")]
+mod jwe;
+#[cfg(feature = "digest")]
+mod noise;
+#[cfg(feature = "stream")]
+mod stream;
@@ -0,0 +1,546 @@
+//! Noise tests
+//!
+//! `data/noise/cacophony.txt` contains the cacophony vectors for the
+//! fundamental interactive patterns with 25519, both ciphers and both hash
+//! functions, as distributed with snow. Each vector is a block of
+//! `key: value` lines, with a `payload` and `ciphertext` line per message.
+
+use crate::agreement::{P256PrivateKey, X25519PrivateKey};
+use ring_compat::{
+    aead::{
+        noise::{
+            CipherState, Dh, HandshakePattern, HandshakeState, NoiseCipher, NoiseError, NoiseHash,
+            Role, SymmetricState, TransportState, DH_LEN, MAX_MESSAGE_LEN,
+        },
+        Aes256Gcm, ChaCha20Poly1305,
+    },
+    agreement::PrivateKey,
+    digest::{Sha256, Sha512},
+};
+
+const PROLOGUE: &[u8] = b"ring-compat noise tests";
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Generates random `x25519-dalek` private keys
+struct RandomDh;
+
+impl Dh for RandomDh {
+    fn generate_key_pair(&mut self) -> Result<Box<dyn PrivateKey + Send + Sync>, NoiseError> {
+        Ok(Box::new(X25519PrivateKey::generate()))
+    }
+}
+
+/// Returns the fixed ephemeral private key of a test vector
+struct FixedDh(Option<X25519PrivateKey>);
+
+impl Dh for FixedDh {
+    fn generate_key_pair(&mut self) -> Result<Box<dyn PrivateKey + Send + Sync>, NoiseError> {
+        let private_key = self.0.take().expect("single ephemeral key");
+        Ok(Box::new(private_key))
+    }
+}
+
+fn private_key(hex: &str) -> Box<dyn PrivateKey + Send + Sync> {
+    Box::new(X25519PrivateKey::new(decode_hex(hex).try_into().unwrap()))
+}
+
+fn fixed_dh(hex: &str) -> Option<Box<dyn Dh>> {
+    Some(Box::new(FixedDh(Some(X25519PrivateKey::new(
+        decode_hex(hex).try_into().unwrap(),
+    )))))
+}
+
+fn pattern(name: &str) -> HandshakePattern {
+    match name {
+        "NN" => HandshakePattern::NN,
+        "NK" => HandshakePattern::NK,
+        "NX" => HandshakePattern::NX,
+        "XN" => HandshakePattern::XN,
+        "XK" => HandshakePattern::XK,
+        "XX" => HandshakePattern::XX,
+        "KN" => HandshakePattern::KN,
+        "KK" => HandshakePattern::KK,
+        "KX" => HandshakePattern::KX,
+        "IN" => HandshakePattern::IN,
+        "IK" => HandshakePattern::IK,
+        "IX" => HandshakePattern::IX,
+        other => panic!("unknown pattern {}", other),
+    }
+}
+
+/// Cacophony vector
+#[derive(Default)]
+struct Vector<'a> {
+    protocol_name: &'a str,
+    init_prologue: Vec<u8>,
+    init_static: Option<&'a str>,
+    init_ephemeral: &'a str,
+    init_remote_static: Option<Vec<u8>>,
+    resp_prologue: Vec<u8>,
+    resp_static: Option<&'a str>,
+    resp_ephemeral: &'a str,
+    resp_remote_static: Option<Vec<u8>>,
+    handshake_hash: Vec<u8>,
+    messages: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl<'a> Vector<'a> {
+    fn parse(block: &'a str) -> Self {
+        let mut vector = Self::default();
+
+        for line in block.lines() {
+            let (key, value) = line.split_once(": ").unwrap();
+
+            match key {
+                "protocol_name" => vector.protocol_name = value,
+                "init_prologue" => vector.init_prologue = decode_hex(value),
+                "init_static" => vector.init_static = Some(value),
+                "init_ephemeral" => vector.init_ephemeral = value,
+                "init_remote_static" => vector.init_remote_static = Some(decode_hex(value)),
+                "resp_prologue" => vector.resp_prologue = decode_hex(value),
+                "resp_static" => vector.resp_static = Some(value),
+                "resp_ephemeral" => vector.resp_ephemeral = value,
+                "resp_remote_static" => vector.resp_remote_static = Some(decode_hex(value)),
+                "handshake_hash" => vector.handshake_hash = decode_hex(value),
+                "payload" => vector.messages.push((decode_hex(value), Vec::new())),
+                "ciphertext" => vector.messages.last_mut().unwrap().1 = decode_hex(value),
+                other => panic!("unknown key {}", other),
+            }
+        }
+
+        vector
+    }
+
+    fn run(&self) {
+        let name = self.protocol_name;
+        let mut parts = name.split('_');
+        assert_eq!(parts.next(), Some("Noise"));
+        let pattern = pattern(parts.next().unwrap());
+        assert_eq!(parts.next(), Some("25519"));
+
+        match (parts.next().unwrap(), parts.next().unwrap()) {
+            ("AESGCM", "SHA256") => self.run_with::<Aes256Gcm, Sha256>(pattern),
+            ("AESGCM", "SHA512") => self.run_with::<Aes256Gcm, Sha512>(pattern),
+            ("ChaChaPoly", "SHA256") => self.run_with::<ChaCha20Poly1305, Sha256>(pattern),
+            ("ChaChaPoly", "SHA512") => self.run_with::<ChaCha20Poly1305, Sha512>(pattern),
+            other => panic!("{}: unknown algorithms {:?}", name, other),
+        }
+    }
+
+    fn run_with<C: NoiseCipher, H: NoiseHash>(&self, pattern: HandshakePattern) {
+        let name = self.protocol_name;
+
+        let mut initiator = HandshakeState::<C, H>::new(
+            pattern,
+            Role::Initiator,
+            &self.init_prologue,
+            self.init_static.map(private_key),
+            self.init_remote_static.as_deref(),
+            fixed_dh(self.init_ephemeral),
+        )
+        .unwrap();
+
+        let mut responder = HandshakeState::<C, H>::new(
+            pattern,
+            Role::Responder,
+            &self.resp_prologue,
+            self.resp_static.map(private_key),
+            self.resp_remote_static.as_deref(),
+            fixed_dh(self.resp_ephemeral),
+        )
+        .unwrap();
+
+        let mut messages = self.messages.iter().enumerate();
+
+        for (i, (payload, ciphertext)) in messages.by_ref() {
+            let (sender, receiver) = if i % 2 == 0 {
+                (&mut initiator, &mut responder)
+            } else {
+                (&mut responder, &mut initiator)
+            };
+
+            assert!(sender.is_my_turn(), "{}: message {}", name, i);
+            assert_eq!(
+                &sender.write_message(payload).unwrap(),
+                ciphertext,
+                "{}: message {}",
+                name,
+                i
+            );
+            assert_eq!(&receiver.read_message(ciphertext).unwrap(), payload);
+
+            if initiator.is_finished() {
+                break;
+            }
+        }
+
+        assert!(responder.is_finished(), "{}", name);
+        assert_eq!(initiator.handshake_hash(), self.handshake_hash, "{}", name);
+        assert_eq!(responder.handshake_hash(), self.handshake_hash, "{}", name);
+
+        let mut initiator = initiator.into_transport().unwrap();
+        let mut responder = responder.into_transport().unwrap();
+
+        for (i, (payload, ciphertext)) in messages {
+            let (sender, receiver) = if i % 2 == 0 {
+                (&mut initiator, &mut responder)
+            } else {
+                (&mut responder, &mut initiator)
+            };
+
+            assert_eq!(
+                &sender.write_message(payload).unwrap(),
+                ciphertext,
+                "{}: message {}",
+                name,
+                i
+            );
+            assert_eq!(&receiver.read_message(ciphertext).unwrap(), payload);
+        }
+    }
+}
+
+#[test]
+fn cacophony() {
+    let vectors = include_str!("data/noise/cacophony.txt");
+    let mut count = 0;
+
+    for block in vectors.split("\n\n") {
+        let block: String = block
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| format!("{}\n", line))
+            .collect();
+
+        if block.is_empty() {
+            continue;
+        }
+
+        Vector::parse(&block).run();
+        count += 1;
+    }
+
+    assert_eq!(count, 48);
+}
+
+/// Run a handshake with generated ephemeral keys, returning both transport
+/// states.
+///
+/// Ephemeral keys are generated by *ring* for `NN`, and with [`RandomDh`]
+/// otherwise.
+fn handshake<C: NoiseCipher, H: NoiseHash>(
+    pattern: HandshakePattern,
+    initiator_static: Option<Box<dyn PrivateKey + Send + Sync>>,
+    responder_static: Option<Box<dyn PrivateKey + Send + Sync>>,
+    initiator_remote_static: Option<&[u8]>,
+) -> (TransportState<C>, TransportState<C>) {
+    let dh = || -> Option<Box<dyn Dh>> {
+        if pattern == HandshakePattern::NN {
+            None
+        } else {
+            Some(Box::new(RandomDh))
+        }
+    };
+
+    let mut initiator = HandshakeState::<C, H>::new(
+        pattern,
+        Role::Initiator,
+        PROLOGUE,
+        initiator_static,
+        initiator_remote_static,
+        dh(),
+    )
+    .unwrap();
+
+    let mut responder = HandshakeState::<C, H>::new(
+        pattern,
+        Role::Responder,
+        PROLOGUE,
+        responder_static,
+        None,
+        dh(),
+    )
+    .unwrap();
+
+    while !initiator.is_finished() {
+        let (sender, receiver) = if initiator.is_my_turn() {
+            (&mut initiator, &mut responder)
+        } else {
+            (&mut responder, &mut initiator)
+        };
+
+        let message = sender.write_message(b"hello").unwrap();
+        assert_eq!(receiver.read_message(&message).unwrap(), b"hello");
+    }
+
+    assert_eq!(initiator.handshake_hash(), responder.handshake_hash());
+    (
+        initiator.into_transport().unwrap(),
+        responder.into_transport().unwrap(),
+    )
+}
+
+#[test]
+fn nn_round_trip() {
+    let (mut initiator, mut responder) =
+        handshake::<ChaCha20Poly1305, Sha256>(HandshakePattern::NN, None, None, None);
+
+    assert_eq!(initiator.remote_static(), None);
+    let message = initiator.write_message(b"ping").unwrap();
+    assert_eq!(responder.read_message(&message).unwrap(), b"ping");
+    let message = responder.write_message(b"pong").unwrap();
+    assert_eq!(initiator.read_message(&message).unwrap(), b"pong");
+}
+
+#[test]
+fn xx_round_trip() {
+    let initiator_static = X25519PrivateKey::generate();
+    let responder_static = X25519PrivateKey::generate();
+    let initiator_public: [u8; DH_LEN] = initiator_static.public_key().try_into().unwrap();
+    let responder_public: [u8; DH_LEN] = responder_static.public_key().try_into().unwrap();
+
+    let (initiator, responder) = handshake::<Aes256Gcm, Sha512>(
+        HandshakePattern::XX,
+        Some(Box::new(initiator_static)),
+        Some(Box::new(responder_static)),
+        None,
+    );
+
+    assert_eq!(initiator.remote_static(), Some(&responder_public));
+    assert_eq!(responder.remote_static(), Some(&initiator_public));
+    assert_eq!(initiator.handshake_hash().len(), 64);
+}
+
+#[test]
+fn ik_wrong_responder_key() {
+    let other_static = X25519PrivateKey::generate();
+
+    let mut initiator = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::IK,
+        Role::Initiator,
+        PROLOGUE,
+        Some(Box::new(X25519PrivateKey::generate())),
+        Some(other_static.public_key()),
+        Some(Box::new(RandomDh)),
+    )
+    .unwrap();
+
+    let mut responder = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::IK,
+        Role::Responder,
+        PROLOGUE,
+        Some(Box::new(X25519PrivateKey::generate())),
+        None,
+        Some(Box::new(RandomDh)),
+    )
+    .unwrap();
+
+    let message = initiator.write_message(b"").unwrap();
+    assert_eq!(
+        responder.read_message(&message),
+        Err(NoiseError::Decryption)
+    );
+
+    // The failed handshake can't be continued
+    assert!(!responder.is_my_turn());
+    assert_eq!(responder.write_message(b""), Err(NoiseError::InvalidState));
+}
+
+#[test]
+fn missing_keys() {
+    let result = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::XX,
+        Role::Initiator,
+        PROLOGUE,
+        None,
+        None,
+        Some(Box::new(RandomDh)),
+    );
+    assert!(matches!(result, Err(NoiseError::MissingKey)));
+
+    let result = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::IK,
+        Role::Initiator,
+        PROLOGUE,
+        Some(Box::new(X25519PrivateKey::generate())),
+        None,
+        Some(Box::new(RandomDh)),
+    );
+    assert!(matches!(result, Err(NoiseError::MissingKey)));
+
+    // The responder's ephemeral key is used twice in `XN`
+    let result = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::XN,
+        Role::Responder,
+        PROLOGUE,
+        None,
+        None,
+        None,
+    );
+    assert!(matches!(result, Err(NoiseError::MissingKey)));
+
+    let result = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::NK,
+        Role::Initiator,
+        PROLOGUE,
+        None,
+        Some(&[0; 31]),
+        Some(Box::new(RandomDh)),
+    );
+    assert!(matches!(result, Err(NoiseError::InvalidKey)));
+}
+
+#[test]
+fn non_x25519_static_key() {
+    let result = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::XX,
+        Role::Initiator,
+        PROLOGUE,
+        Some(Box::new(P256PrivateKey::new(&[1; 32]))),
+        None,
+        Some(Box::new(RandomDh)),
+    );
+    assert!(matches!(result, Err(NoiseError::InvalidKey)));
+}
+
+#[test]
+fn low_order_remote_static() {
+    let mut initiator = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::NK,
+        Role::Initiator,
+        PROLOGUE,
+        None,
+        Some(&[0; DH_LEN]),
+        Some(Box::new(RandomDh)),
+    )
+    .unwrap();
+
+    assert_eq!(initiator.write_message(b""), Err(NoiseError::InvalidKey));
+}
+
+#[test]
+fn out_of_turn() {
+    let mut initiator = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::NN,
+        Role::Initiator,
+        PROLOGUE,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        initiator.read_message(&[0; 32]),
+        Err(NoiseError::InvalidState)
+    );
+    initiator.write_message(b"").unwrap();
+    assert_eq!(initiator.write_message(b""), Err(NoiseError::InvalidState));
+    assert!(matches!(
+        initiator.into_transport(),
+        Err(NoiseError::InvalidState)
+    ));
+}
+
+#[test]
+fn short_handshake_message() {
+    let mut responder = HandshakeState::<ChaCha20Poly1305, Sha256>::new(
+        HandshakePattern::NN,
+        Role::Responder,
+        PROLOGUE,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(responder.read_message(&[0; 31]), Err(NoiseError::Malformed));
+}
+
+#[test]
+fn transport_message_too_long() {
+    let (mut initiator, _) =
+        handshake::<ChaCha20Poly1305, Sha256>(HandshakePattern::NN, None, None, None);
+
+    assert_eq!(
+        initiator.write_message(&[0; MAX_MESSAGE_LEN]),
+        Err(NoiseError::MessageTooLong)
+    );
+    assert_eq!(
+        initiator
+            .write_message(&[0; MAX_MESSAGE_LEN - 16])
+            .unwrap()
+            .len(),
+        MAX_MESSAGE_LEN
+    );
+}
+
+#[test]
+fn transport_failure_keeps_nonce() {
+    let (mut initiator, mut responder) =
+        handshake::<Aes256Gcm, Sha256>(HandshakePattern::NN, None, None, None);
+
+    let mut message = initiator.write_message(b"payload").unwrap();
+    message[0] ^= 1;
+    assert_eq!(
+        responder.read_message(&message),
+        Err(NoiseError::Decryption)
+    );
+
+    message[0] ^= 1;
+    assert_eq!(responder.read_message(&message).unwrap(), b"payload");
+}
+
+#[test]
+fn rekey() {
+    let (mut initiator, mut responder) =
+        handshake::<ChaCha20Poly1305, Sha512>(HandshakePattern::NN, None, None, None);
+
+    initiator.rekey_outgoing();
+    let message = initiator.write_message(b"after rekey").unwrap();
+    assert_eq!(
+        responder.read_message(&message),
+        Err(NoiseError::Decryption)
+    );
+
+    responder.rekey_incoming();
+    assert_eq!(responder.read_message(&message).unwrap(), b"after rekey");
+}
+
+#[test]
+fn cipher_state() {
+    let mut sender = CipherState::<ChaCha20Poly1305>::new([7; 32]);
+    let mut receiver = CipherState::<ChaCha20Poly1305>::new([7; 32]);
+
+    sender.set_nonce(u64::MAX - 1);
+    receiver.set_nonce(u64::MAX - 1);
+    let ciphertext = sender.encrypt_with_ad(b"ad", b"last").unwrap();
+    assert_eq!(
+        receiver.decrypt_with_ad(b"ad", &ciphertext).unwrap(),
+        b"last"
+    );
+
+    // The maximum nonce is reserved for rekeying
+    assert_eq!(
+        sender.encrypt_with_ad(b"ad", b""),
+        Err(NoiseError::NonceExhausted)
+    );
+    assert_eq!(sender.nonce(), u64::MAX);
+}
+
+#[test]
+fn symmetric_state_long_protocol_name() {
+    let name = "Noise_XXfallback+psk0_25519_ChaChaPoly_SHA256_with_a_long_suffix";
+    let state = SymmetricState::<ChaCha20Poly1305, Sha256>::new(name);
+    assert_eq!(
+        state.handshake_hash(),
+        ring::digest::digest(&ring::digest::SHA256, name.as_bytes()).as_ref()
+    );
+    assert!(!state.has_key());
+}
@@ -0,0 +1,196 @@
+//! Nonce sequence tests
+
+use ring_compat::{
+    aead::{
+        nonce_sequence::{Counter32, Counter64, OpeningKey, SealingKey, WindowedOpeningKey},
+        AeadInPlace, ChaCha20Poly1305, KeyInit,
+    },
+    generic_array::GenericArray,
+};
+
+const KEY: &[u8; 32] = &[0x42; 32];
+const AAD: &[u8] = b"nonce sequence test";
+const PLAINTEXT: &[u8] = b"each message is sealed under a fresh nonce";
+
+fn cipher() -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(GenericArray::from_slice(KEY))
+}
+
+#[test]
+fn counter32_nonces() {
+    let mut key = SealingKey::new(cipher(), Counter32::new([1; 8]));
+    assert_eq!(key.nonce_sequence().next_counter(), Some(0));
+
+    for counter in 0u32..3 {
+        let mut buffer = PLAINTEXT.to_vec();
+        let tag = key.seal_in_place_detached(AAD, &mut buffer).unwrap();
+
+        let mut nonce = [1u8; 12];
+        nonce[8..].copy_from_slice(&counter.to_be_bytes());
+        let mut expected = PLAINTEXT.to_vec();
+        let expected_tag = cipher()
+            .encrypt_in_place_detached(&nonce.into(), AAD, &mut expected)
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+        assert_eq!(tag, expected_tag);
+    }
+
+    assert_eq!(key.nonce_sequence().next_counter(), Some(3));
+}
+
+#[test]
+fn counter64_nonces() {
+    let mut key = SealingKey::new(cipher(), Counter64::starting_at([2; 4], 1 << 40));
+
+    let mut buffer = PLAINTEXT.to_vec();
+    key.seal_in_place(AAD, &mut buffer).unwrap();
+
+    let mut nonce = [2u8; 12];
+    nonce[4..].copy_from_slice(&(1u64 << 40).to_be_bytes());
+    cipher()
+        .decrypt_in_place(&nonce.into(), AAD, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+}
+
+#[test]
+fn sealing_refuses_when_exhausted() {
+    let mut key = SealingKey::new(cipher(), Counter32::starting_at([0; 8], u32::MAX - 1));
+    let mut buffer = PLAINTEXT.to_vec();
+
+    key.seal_in_place_detached(AAD, &mut buffer).unwrap();
+    key.seal_in_place_detached(AAD, &mut buffer).unwrap();
+    assert_eq!(key.nonce_sequence().next_counter(), None);
+
+    let mut buffer = PLAINTEXT.to_vec();
+    assert!(key.seal_in_place_detached(AAD, &mut buffer).is_err());
+    assert!(key.seal_in_place(AAD, &mut buffer).is_err());
+    assert_eq!(buffer, PLAINTEXT);
+
+    let mut key = SealingKey::new(cipher(), Counter64::starting_at([0; 4], u64::MAX));
+    key.seal_in_place(AAD, &mut buffer).unwrap();
+    assert!(key.seal_in_place(AAD, &mut buffer).is_err());
+}
+
+#[test]
+fn open_in_order() {
+    let mut sealing_key = SealingKey::new(cipher(), Counter32::new([3; 8]));
+    let mut opening_key = OpeningKey::new(cipher(), Counter32::new([3; 8]));
+
+    for _ in 0..3 {
+        let mut buffer = PLAINTEXT.to_vec();
+        let tag = sealing_key
+            .seal_in_place_detached(AAD, &mut buffer)
+            .unwrap();
+        opening_key
+            .open_in_place_detached(AAD, &mut buffer, &tag)
+            .unwrap();
+        assert_eq!(buffer, PLAINTEXT);
+    }
+}
+
+#[test]
+fn open_out_of_order_fails() {
+    let mut sealing_key = SealingKey::new(cipher(), Counter32::new([3; 8]));
+    let mut opening_key = OpeningKey::new(cipher(), Counter32::new([3; 8]));
+
+    let mut first = PLAINTEXT.to_vec();
+    sealing_key.seal_in_place(AAD, &mut first).unwrap();
+    let mut second = PLAINTEXT.to_vec();
+    sealing_key.seal_in_place(AAD, &mut second).unwrap();
+
+    assert!(opening_key.open_in_place(AAD, &mut second).is_err());
+}
+
+#[test]
+fn open_after_forgery() {
+    let mut sealing_key = SealingKey::new(cipher(), Counter32::new([3; 8]));
+    let mut opening_key = OpeningKey::new(cipher(), Counter32::new([3; 8]));
+
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = sealing_key
+        .seal_in_place_detached(AAD, &mut buffer)
+        .unwrap();
+
+    // Failed attempts don't consume the nonce
+    let mut forged = buffer.clone();
+    forged[0] ^= 1;
+    assert!(opening_key
+        .open_in_place_detached(AAD, &mut forged, &tag)
+        .is_err());
+    assert!(opening_key
+        .open_in_place_detached(b"other", &mut buffer.clone(), &tag)
+        .is_err());
+
+    opening_key
+        .open_in_place_detached(AAD, &mut buffer, &tag)
+        .unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+
+    let mut buffer = PLAINTEXT.to_vec();
+    sealing_key.seal_in_place(AAD, &mut buffer).unwrap();
+    opening_key.open_in_place(AAD, &mut buffer).unwrap();
+    assert_eq!(buffer, PLAINTEXT);
+}
+
+#[test]
+fn open_windowed() {
+    let mut sealing_key = SealingKey::new(cipher(), Counter64::new([4; 4]));
+    let mut opening_key = WindowedOpeningKey::new(cipher(), [4; 4]);
+    let mut messages = Vec::new();
+
+    for _ in 0..5 {
+        let counter = sealing_key.nonce_sequence().next_counter().unwrap();
+        let mut buffer = PLAINTEXT.to_vec();
+        let tag = sealing_key
+            .seal_in_place_detached(AAD, &mut buffer)
+            .unwrap();
+        messages.push((counter, buffer, tag));
+    }
+
+    // Out of order delivery is accepted...
+    for &i in &[3, 0, 4, 1] {
+        let (counter, ciphertext, tag) = &messages[i];
+        let mut buffer = ciphertext.clone();
+        opening_key
+            .open_in_place_detached(*counter, AAD, &mut buffer, tag)
+            .unwrap();
+        assert_eq!(buffer, PLAINTEXT);
+    }
+
+    // ...but replays are not
+    let (counter, ciphertext, tag) = &messages[3];
+    let mut buffer = ciphertext.clone();
+    assert!(opening_key
+        .open_in_place_detached(*counter, AAD, &mut buffer, tag)
+        .is_err());
+
+    // Forgeries don't consume a counter
+    let (counter, ciphertext, tag) = &messages[2];
+    let mut buffer = ciphertext.clone();
+    assert!(opening_key
+        .open_in_place_detached(*counter, b"wrong", &mut buffer, tag)
+        .is_err());
+    let mut buffer = ciphertext.clone();
+    opening_key
+        .open_in_place_detached(*counter, AAD, &mut buffer, tag)
+        .unwrap();
+}
+
+#[test]
+fn open_windowed_stale() {
+    let window = WindowedOpeningKey::<ChaCha20Poly1305>::WINDOW_SIZE;
+    let mut sealing_key = SealingKey::new(cipher(), Counter64::new([5; 4]));
+    let mut opening_key = WindowedOpeningKey::new(cipher(), [5; 4]);
+
+    let mut stale = PLAINTEXT.to_vec();
+    sealing_key.seal_in_place(AAD, &mut stale).unwrap();
+
+    let mut sealing_key = SealingKey::new(cipher(), Counter64::starting_at([5; 4], window));
+    let mut latest = PLAINTEXT.to_vec();
+    sealing_key.seal_in_place(AAD, &mut latest).unwrap();
+    opening_key.open_in_place(window, AAD, &mut latest).unwrap();
+
+    assert!(opening_key.open_in_place(0, AAD, &mut stale).is_err());
+}
@@ -0,0 +1,171 @@
+//! Random nonce prefixed ciphertext tests
+
+use ring_compat::{
+    aead::{
+        prefixed::{Layout, PrefixedError, SealRandom},
+        AeadAlgorithm, AeadInPlace, Aes256Gcm, AnyAead, ChaCha20Poly1305, KeyInit,
+        XChaCha20Poly1305,
+    },
+    generic_array::GenericArray,
+};
+
+const AAD: &[u8] = b"prefixed test";
+const PLAINTEXT: &[u8] = b"nonce || ciphertext || tag";
+
+fn cipher() -> Aes256Gcm {
+    Aes256Gcm::new(&[0x42; 32].into())
+}
+
+#[test]
+fn bare_layout() {
+    let sealed = cipher().seal_random(PLAINTEXT, AAD).unwrap();
+    assert_eq!(sealed.len(), 12 + PLAINTEXT.len() + 16);
+
+    // Equivalent to encrypting by hand under the prefixed nonce
+    let (nonce, rest) = sealed.split_at(12);
+    let (ciphertext, tag) = rest.split_at(PLAINTEXT.len());
+    let mut buffer = PLAINTEXT.to_vec();
+    let expected_tag = cipher()
+        .encrypt_in_place_detached(GenericArray::from_slice(nonce), AAD, &mut buffer)
+        .unwrap();
+    assert_eq!(buffer, ciphertext);
+    assert_eq!(expected_tag.as_slice(), tag);
+
+    assert_eq!(cipher().open_prefixed(&sealed, AAD).unwrap(), PLAINTEXT);
+}
+
+#[test]
+fn random_nonces() {
+    let first = cipher().seal_random(PLAINTEXT, AAD).unwrap();
+    let second = cipher().seal_random(PLAINTEXT, AAD).unwrap();
+    assert_ne!(first[..12], second[..12]);
+}
+
+#[test]
+fn extended_nonce() {
+    let cipher = XChaCha20Poly1305::new(&[0x42; 32].into());
+    let sealed = cipher.seal_random(PLAINTEXT, AAD).unwrap();
+    assert_eq!(sealed.len(), 24 + PLAINTEXT.len() + 16);
+    assert_eq!(cipher.open_prefixed(&sealed, AAD).unwrap(), PLAINTEXT);
+}
+
+#[test]
+fn versioned_layout() {
+    let layout = Layout::versioned(2).with_algorithm();
+    assert_eq!(layout.header_len(), 3);
+
+    let cipher = ChaCha20Poly1305::new(&[0x42; 32].into());
+    let sealed = cipher.seal_random_with(&layout, PLAINTEXT, AAD).unwrap();
+    assert_eq!(sealed[..3], [0x02, 0x00, 0x1d]);
+    assert_eq!(
+        cipher.open_prefixed_with(&layout, &sealed, AAD).unwrap(),
+        PLAINTEXT
+    );
+
+    // Header is authenticated
+    assert_eq!(
+        cipher.open_prefixed(&sealed[3..], AAD),
+        Err(PrefixedError::Aead)
+    );
+}
+
+#[test]
+fn any_aead_layout() {
+    let cipher = AnyAead::new(AeadAlgorithm::Aes128Gcm, &[0x42; 16]).unwrap();
+    let layout = Layout::BARE.with_algorithm();
+    let sealed = cipher.seal_random_with(&layout, PLAINTEXT, AAD).unwrap();
+    assert_eq!(sealed[..2], [0x00, 0x01]);
+    assert_eq!(
+        cipher.open_prefixed_with(&layout, &sealed, AAD).unwrap(),
+        PLAINTEXT
+    );
+}
+
+#[test]
+fn unsupported_version() {
+    let sealed = cipher()
+        .seal_random_with(&Layout::versioned(1), PLAINTEXT, AAD)
+        .unwrap();
+    assert_eq!(
+        cipher().open_prefixed_with(&Layout::versioned(2), &sealed, AAD),
+        Err(PrefixedError::UnsupportedVersion(1))
+    );
+}
+
+#[test]
+fn algorithm_mismatch() {
+    let layout = Layout::BARE.with_algorithm();
+    let sealed = cipher().seal_random_with(&layout, PLAINTEXT, AAD).unwrap();
+    assert_eq!(sealed[..2], [0x00, 0x02]);
+
+    let other = AnyAead::new(AeadAlgorithm::Aes128Gcm, &[0x42; 16]).unwrap();
+    assert_eq!(
+        other.open_prefixed_with(&layout, &sealed, AAD),
+        Err(PrefixedError::AlgorithmMismatch(2))
+    );
+}
+
+#[test]
+fn no_algorithm() {
+    let cipher = XChaCha20Poly1305::new(&[0x42; 32].into());
+    let layout = Layout::versioned(1).with_algorithm();
+    assert_eq!(
+        cipher.seal_random_with(&layout, PLAINTEXT, AAD),
+        Err(PrefixedError::NoAlgorithm)
+    );
+
+    let sealed = cipher
+        .seal_random_with(&Layout::versioned(1), PLAINTEXT, AAD)
+        .unwrap();
+    assert_eq!(
+        cipher.open_prefixed_with(&layout, &sealed, AAD),
+        Err(PrefixedError::NoAlgorithm)
+    );
+}
+
+#[test]
+fn truncated() {
+    let layout = Layout::versioned(1);
+    let sealed = cipher().seal_random_with(&layout, b"", AAD).unwrap();
+    assert_eq!(sealed.len(), 1 + 12 + 16);
+
+    assert_eq!(
+        cipher().open_prefixed_with(&layout, &sealed[..28], AAD),
+        Err(PrefixedError::Truncated)
+    );
+    assert_eq!(
+        cipher().open_prefixed_with(&layout, &sealed, AAD).unwrap(),
+        b""
+    );
+}
+
+#[test]
+fn modified() {
+    let sealed = cipher().seal_random(PLAINTEXT, AAD).unwrap();
+
+    for i in 0..sealed.len() {
+        let mut modified = sealed.clone();
+        modified[i] ^= 1;
+        assert_eq!(
+            cipher().open_prefixed(&modified, AAD),
+            Err(PrefixedError::Aead)
+        );
+    }
+
+    assert_eq!(
+        cipher().open_prefixed(&sealed, b"other"),
+        Err(PrefixedError::Aead)
+    );
+}
+
+#[test]
+fn error_display() {
+    assert_eq!(
+        PrefixedError::UnsupportedVersion(3).to_string(),
+        "unsupported ciphertext version: 3"
+    );
+    assert_eq!(
+        PrefixedError::AlgorithmMismatch(29).to_string(),
+        "unexpected AEAD algorithm: 29"
+    );
+}
@@ -0,0 +1,165 @@
+//! QUIC packet protection tests
+//!
+//! Sample packets from RFC 9001 Appendix A:
+//! <https://www.rfc-editor.org/rfc/rfc9001#appendix-A>
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{
+        quic::{HeaderProtection, InitialSecrets, Secret},
+        Aes128Gcm, Aes256Gcm, ChaCha20Poly1305, Tag,
+    },
+    ring::hkdf,
+};
+
+/// Destination Connection ID chosen by the client
+const DCID: &[u8] = &hex!("8394c8f03e515708");
+
+#[test]
+fn initial_secrets() {
+    let secrets = InitialSecrets::new(DCID);
+
+    assert_eq!(
+        secrets.client.as_ref(),
+        hex!("c00cf151ca5be075ed0ebfb5c80323c42d6b7db67881289af4008f1f6c357aea")
+    );
+    assert_eq!(
+        secrets.server.as_ref(),
+        hex!("3c199828fd139efd216c155ad844cc81fb82fa8d7446fa7d78be803acdda951b")
+    );
+}
+
+#[test]
+fn initial_keys() {
+    let secrets = InitialSecrets::new(DCID);
+    let client = secrets.client.keys::<Aes128Gcm>().unwrap();
+    let server = secrets.server.keys::<Aes128Gcm>().unwrap();
+
+    assert_eq!(client.iv.as_ref(), hex!("fa044b2f42a3fd3b46fb255c"));
+    assert_eq!(server.iv.as_ref(), hex!("0ac1493ca1905853b0bba03e"));
+
+    // Initial secrets use SHA-256 so can't be used with AES-256-GCM
+    assert!(secrets.client.keys::<Aes256Gcm>().is_err());
+}
+
+/// A.2: Client Initial header protection
+#[test]
+fn client_initial_header_protection() {
+    let keys = InitialSecrets::new(DCID)
+        .client
+        .keys::<Aes128Gcm>()
+        .unwrap();
+    let sample = hex!("d1b1c98dd7689fb8ec11d242b123dc9b");
+    assert_eq!(keys.header.new_mask(&sample), hex!("437b9aec36"));
+
+    let mut header = hex!("c300000001088394c8f03e5157080000449e00000002");
+    let (first_byte, rest) = header.split_first_mut().unwrap();
+    let pn_offset = rest.len() - 4;
+    keys.header
+        .protect(&sample, first_byte, &mut rest[pn_offset..])
+        .unwrap();
+    assert_eq!(header, hex!("c000000001088394c8f03e5157080000449e7b9aec34"));
+
+    let (first_byte, rest) = header.split_first_mut().unwrap();
+    let pn_len = keys
+        .header
+        .unprotect(&sample, first_byte, &mut rest[pn_offset..])
+        .unwrap();
+    assert_eq!(pn_len, 4);
+    assert_eq!(header, hex!("c300000001088394c8f03e5157080000449e00000002"));
+}
+
+/// A.3: Server Initial header protection
+#[test]
+fn server_initial_header_protection() {
+    let keys = InitialSecrets::new(DCID)
+        .server
+        .keys::<Aes128Gcm>()
+        .unwrap();
+    let sample = hex!("2cd0991cd25b0aac406a5816b6394100");
+    assert_eq!(keys.header.new_mask(&sample), hex!("2ec0d8356a"));
+
+    let mut header = hex!("c1000000010008f067a5502a4262b50040750001");
+    let (first_byte, rest) = header.split_first_mut().unwrap();
+    let pn_offset = rest.len() - 2;
+    keys.header
+        .protect(&sample, first_byte, &mut rest[pn_offset..])
+        .unwrap();
+    assert_eq!(header, hex!("cf000000010008f067a5502a4262b5004075c0d9"));
+}
+
+/// A.5: ChaCha20-Poly1305 Short Header Packet
+#[test]
+fn chacha20poly1305_short_header_packet() {
+    let secret = Secret::new(
+        hkdf::HKDF_SHA256,
+        &hex!("9ac312a7f877468ebe69422748ad00a15443f18203a07d6060f688f30f21632b"),
+    )
+    .unwrap();
+    assert_eq!(
+        secret.update().as_ref(),
+        hex!("1223504755036d556342ee9361d253421a826c9ecdf3c7148684b36b714881f9")
+    );
+
+    let keys = secret.keys::<ChaCha20Poly1305>().unwrap();
+    let packet_number = 654360564;
+    assert_eq!(keys.iv.as_ref(), hex!("e0459b3474bdd0e44a41c144"));
+    assert_eq!(
+        keys.iv.nonce(packet_number).as_slice(),
+        hex!("e0459b3474bdd0e46d417eb0")
+    );
+
+    let mut header = hex!("4200bff4");
+    let mut payload = hex!("01");
+    let tag = keys
+        .encrypt_in_place_detached(packet_number, &header, &mut payload)
+        .unwrap();
+    assert_eq!(payload, hex!("65"));
+    assert_eq!(tag.as_slice(), hex!("5e5cd55c41f69080575d7999c25a5bfb"));
+
+    // The sample starts 4 bytes after the start of the packet number field
+    let sample = hex!("5e5cd55c41f69080575d7999c25a5bfb");
+    assert_eq!(keys.header.new_mask(&sample), hex!("aefefe7d03"));
+
+    let (first_byte, packet_number_field) = header.split_first_mut().unwrap();
+    keys.header
+        .protect(&sample, first_byte, packet_number_field)
+        .unwrap();
+    assert_eq!(header, hex!("4cfe4189"));
+
+    // Receive the packet
+    let (first_byte, packet_number_field) = header.split_first_mut().unwrap();
+    assert_eq!(
+        keys.header
+            .unprotect(&sample, first_byte, packet_number_field)
+            .unwrap(),
+        3
+    );
+    assert_eq!(header, hex!("4200bff4"));
+
+    keys.decrypt_in_place_detached(packet_number, &header, &mut payload, &tag)
+        .unwrap();
+    assert_eq!(payload, hex!("01"));
+
+    let bad_tag = Tag::default();
+    assert!(keys
+        .decrypt_in_place_detached(packet_number, &header, &mut payload, &bad_tag)
+        .is_err());
+}
+
+#[test]
+fn protect_wrong_packet_number_len() {
+    let keys = InitialSecrets::new(DCID)
+        .client
+        .keys::<Aes128Gcm>()
+        .unwrap();
+    let sample = [0u8; 16];
+    let mut first_byte = 0xc3;
+    let mut packet_number = [0u8; 2];
+
+    assert!(keys
+        .header
+        .protect(&sample, &mut first_byte, &mut packet_number)
+        .is_err());
+    assert_eq!(first_byte, 0xc3);
+}
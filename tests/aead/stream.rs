@@ -0,0 +1,194 @@
+//! STREAM tests
+//!
+//! Checks that segments produced with the *ring*-backed ciphers are
+//! byte-for-byte identical to those produced with the pure Rust RustCrypto
+//! implementations, in both directions.
+
+use ring_compat::{
+    aead::{
+        stream::{
+            DecryptorBE32, DecryptorLE31, EncryptorBE32, EncryptorLE31, NewStream, StreamBE32,
+            StreamLE31, StreamPrimitive,
+        },
+        AeadCore, AeadInPlace, KeyInit, Payload,
+    },
+    generic_array::{typenum::U12, GenericArray},
+};
+
+const AAD: &[u8] = b"STREAM associated data";
+
+/// Message segments, including an empty one
+const SEGMENTS: &[&[u8]] = &[
+    b"The STREAM construction encrypts a sequence of segments",
+    b"",
+    b"each under a nonce that encodes its position",
+    b"and whether or not it is the last one",
+];
+
+fn payload(msg: &[u8]) -> Payload<'_, '_> {
+    Payload { msg, aad: AAD }
+}
+
+macro_rules! stream_tests {
+    ($name:ident, $ring:ty, $rustcrypto:ty, $key_size:expr) => {
+        mod $name {
+            use super::*;
+
+            const KEY: &[u8; $key_size] = &[0x42; $key_size];
+
+            fn encrypt_be32<A>(aead: A) -> Vec<Vec<u8>>
+            where
+                A: AeadInPlace + AeadCore<NonceSize = U12> + KeyInit,
+            {
+                let mut encryptor = EncryptorBE32::from_aead(aead, (&[0x24; 7]).into());
+                let (last, rest) = SEGMENTS.split_last().unwrap();
+                let mut ciphertexts = Vec::new();
+
+                for segment in rest {
+                    ciphertexts.push(encryptor.encrypt_next(payload(segment)).unwrap());
+                }
+
+                ciphertexts.push(encryptor.encrypt_last(payload(last)).unwrap());
+                ciphertexts
+            }
+
+            fn encrypt_le31<A>(aead: A) -> Vec<Vec<u8>>
+            where
+                A: AeadInPlace + AeadCore<NonceSize = U12> + KeyInit,
+            {
+                let mut encryptor = EncryptorLE31::from_aead(aead, (&[0x24; 8]).into());
+                let (last, rest) = SEGMENTS.split_last().unwrap();
+                let mut ciphertexts = Vec::new();
+
+                for segment in rest {
+                    ciphertexts.push(encryptor.encrypt_next(payload(segment)).unwrap());
+                }
+
+                ciphertexts.push(encryptor.encrypt_last(payload(last)).unwrap());
+                ciphertexts
+            }
+
+            fn decrypt_be32<A>(aead: A, ciphertexts: &[Vec<u8>]) -> Vec<Vec<u8>>
+            where
+                A: AeadInPlace + AeadCore<NonceSize = U12> + KeyInit,
+            {
+                let mut decryptor = DecryptorBE32::from_aead(aead, (&[0x24; 7]).into());
+                let (last, rest) = ciphertexts.split_last().unwrap();
+                let mut plaintexts = Vec::new();
+
+                for segment in rest {
+                    plaintexts.push(decryptor.decrypt_next(payload(segment)).unwrap());
+                }
+
+                plaintexts.push(decryptor.decrypt_last(payload(last)).unwrap());
+                plaintexts
+            }
+
+            fn decrypt_le31<A>(aead: A, ciphertexts: &[Vec<u8>]) -> Vec<Vec<u8>>
+            where
+                A: AeadInPlace + AeadCore<NonceSize = U12> + KeyInit,
+            {
+                let mut decryptor = DecryptorLE31::from_aead(aead, (&[0x24; 8]).into());
+                let (last, rest) = ciphertexts.split_last().unwrap();
+                let mut plaintexts = Vec::new();
+
+                for segment in rest {
+                    plaintexts.push(decryptor.decrypt_next(payload(segment)).unwrap());
+                }
+
+                plaintexts.push(decryptor.decrypt_last(payload(last)).unwrap());
+                plaintexts
+            }
+
+            fn ring() -> $ring {
+                <$ring>::new(GenericArray::from_slice(KEY))
+            }
+
+            fn rustcrypto() -> $rustcrypto {
+                <$rustcrypto>::new(GenericArray::from_slice(KEY))
+            }
+
+            #[test]
+            fn be32_matches_rustcrypto() {
+                let ciphertexts = encrypt_be32(ring());
+                assert_eq!(ciphertexts, encrypt_be32(rustcrypto()));
+                assert_eq!(decrypt_be32(rustcrypto(), &ciphertexts), SEGMENTS);
+                assert_eq!(decrypt_be32(ring(), &ciphertexts), SEGMENTS);
+            }
+
+            #[test]
+            fn le31_matches_rustcrypto() {
+                let ciphertexts = encrypt_le31(ring());
+                assert_eq!(ciphertexts, encrypt_le31(rustcrypto()));
+                assert_eq!(decrypt_le31(rustcrypto(), &ciphertexts), SEGMENTS);
+                assert_eq!(decrypt_le31(ring(), &ciphertexts), SEGMENTS);
+            }
+
+            #[test]
+            fn last_segment_flag() {
+                let ciphertexts = encrypt_be32(ring());
+                let decryptor = DecryptorBE32::from_aead(ring(), (&[0x24; 7]).into());
+
+                // A non-final segment must not be accepted as the last one
+                assert!(decryptor.decrypt_last(payload(&ciphertexts[0])).is_err());
+
+                // ...and the last segment must not be accepted as a non-final one
+                let stream = StreamBE32::from_aead(ring(), (&[0x24; 7]).into());
+                let mut last = ciphertexts[3].clone();
+                assert!(stream.decrypt_in_place(3, false, AAD, &mut last).is_err());
+            }
+
+            #[test]
+            fn be32_max_position() {
+                let ring_stream = StreamBE32::from_aead(ring(), (&[0x24; 7]).into());
+                let rustcrypto_stream = StreamBE32::from_aead(rustcrypto(), (&[0x24; 7]).into());
+
+                let mut a = SEGMENTS[0].to_vec();
+                let mut b = SEGMENTS[0].to_vec();
+                ring_stream
+                    .encrypt_in_place(u32::MAX, true, AAD, &mut a)
+                    .unwrap();
+                rustcrypto_stream
+                    .encrypt_in_place(u32::MAX, true, AAD, &mut b)
+                    .unwrap();
+
+                assert_eq!(a, b);
+            }
+
+            #[test]
+            fn le31_counter_overflow() {
+                let stream = StreamLE31::from_aead(ring(), (&[0x24; 8]).into());
+                let max = <StreamLE31<$ring> as StreamPrimitive<$ring>>::COUNTER_MAX;
+                let mut buffer = SEGMENTS[0].to_vec();
+
+                assert!(stream
+                    .encrypt_in_place(max + 1, false, AAD, &mut buffer)
+                    .is_err());
+                assert_eq!(buffer, SEGMENTS[0]);
+
+                stream
+                    .encrypt_in_place(max, true, AAD, &mut buffer)
+                    .unwrap();
+            }
+        }
+    };
+}
+
+stream_tests!(
+    aes128gcm,
+    ring_compat::aead::Aes128Gcm,
+    ::aes_gcm::Aes128Gcm,
+    16
+);
+stream_tests!(
+    aes256gcm,
+    ring_compat::aead::Aes256Gcm,
+    ::aes_gcm::Aes256Gcm,
+    32
+);
+stream_tests!(
+    chacha20poly1305,
+    ring_compat::aead::ChaCha20Poly1305,
+    ::chacha20poly1305::ChaCha20Poly1305,
+    32
+);
@@ -0,0 +1,289 @@
+//! TLS 1.3 record protection and key schedule tests
+//!
+//! Vectors are from the "Simple 1-RTT Handshake" trace in RFC 8448 Section 3
+//! (`TLS_AES_128_GCM_SHA256`), and from Python's `cryptography` package for
+//! the other cipher suites.
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{
+        tls13::{ContentType, Iv, KeySchedule, RecordKeys, Secret},
+        Aes128Gcm, Aes256Gcm, ChaCha20Poly1305, KeyInit,
+    },
+    ring::hkdf::{HKDF_SHA256, HKDF_SHA384},
+};
+
+/// (EC)DHE shared secret
+const SHARED_SECRET: [u8; 32] =
+    hex!("8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d");
+
+/// Transcript hash of ClientHello..ServerHello
+const HELLO_HASH: [u8; 32] =
+    hex!("860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8");
+
+/// Server application traffic secret
+const SERVER_APPLICATION_SECRET: [u8; 32] =
+    hex!("a11af9f05531f856ad47116b45a950328204b4f44bfb6b3a4b4f1f3fcb631643");
+
+/// Application data sent by the client and echoed by the server
+const APPLICATION_DATA: [u8; 50] = hex!(
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+    "202122232425262728292a2b2c2d2e2f3031"
+);
+
+/// Client's first application data record
+const CLIENT_RECORD: [u8; 72] = hex!(
+    "1703030043a23f7054b62c94d0affafe8228ba55cbefacea42f914aa66bcab3f"
+    "2b9819a8a5b46b395bd54a9a20441e2b62974e1f5a6292a2977014bd1e3deae6"
+    "3aeebb21694915e4"
+);
+
+/// Server's second application data record (after NewSessionTicket)
+const SERVER_RECORD: [u8; 72] = hex!(
+    "17030300432e937e11ef4ac740e538ad36005fc4a46932fc3225d05f82aa1b36"
+    "e30efaf97d90e6dffc602dcb501a59a8fcc49c4bf2e5f0a21c0047c2abf33254"
+    "0dd032e167c2955d"
+);
+
+fn client_application_keys() -> RecordKeys<Aes128Gcm> {
+    RecordKeys::new(
+        Aes128Gcm::new(&hex!("17422dda596ed5d9acd890e3c63f5051").into()),
+        Iv::from(hex!("5b78923dee08579033e523d9")),
+    )
+}
+
+#[test]
+fn rfc8448_key_schedule() {
+    let schedule = KeySchedule::new(HKDF_SHA256, None);
+    assert_eq!(
+        schedule.secret().as_ref(),
+        hex!("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a")
+    );
+
+    let schedule = schedule.into_handshake(&SHARED_SECRET);
+    assert_eq!(
+        schedule.secret().as_ref(),
+        hex!("1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac")
+    );
+    assert_eq!(
+        schedule
+            .derive_secret(b"c hs traffic", &HELLO_HASH)
+            .unwrap()
+            .as_ref(),
+        hex!("b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21")
+    );
+    assert_eq!(
+        schedule
+            .derive_secret(b"s hs traffic", &HELLO_HASH)
+            .unwrap()
+            .as_ref(),
+        hex!("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38")
+    );
+
+    let schedule = schedule.into_master();
+    assert_eq!(
+        schedule.secret().as_ref(),
+        hex!("18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919")
+    );
+}
+
+#[test]
+fn rfc8448_traffic_keys() {
+    let secret = Secret::new(
+        HKDF_SHA256,
+        &hex!("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38"),
+    )
+    .unwrap();
+
+    let mut key = [0u8; 16];
+    let mut iv = [0u8; 12];
+    secret.expand_label(b"key", &[], &mut key).unwrap();
+    secret.expand_label(b"iv", &[], &mut iv).unwrap();
+    assert_eq!(key, hex!("3fce516009c21727d0f2e4e86ee403bc"));
+    assert_eq!(iv, hex!("5d313eb2671276ee13000b30"));
+}
+
+#[test]
+fn expand_label_lengths() {
+    let secret = Secret::new(HKDF_SHA256, &[0x42; 32]).unwrap();
+    let mut out = [0u8; 32];
+
+    // "tls13 " followed by the label must fit in 255 bytes
+    assert!(secret.expand_label(&[b'a'; 249], &[], &mut out).is_ok());
+    assert!(secret.expand_label(&[b'a'; 250], &[], &mut out).is_err());
+    assert!(secret.derive_secret(&[b'a'; 250], &[]).is_err());
+
+    assert!(secret.expand_label(b"key", &[0; 255], &mut out).is_ok());
+    assert!(secret.expand_label(b"key", &[0; 256], &mut out).is_err());
+
+    // HKDF-SHA256 outputs at most 255 hash lengths
+    assert!(secret
+        .expand_label(b"key", &[], &mut [0u8; 255 * 32 + 1])
+        .is_err());
+}
+
+#[test]
+fn iv_debug_is_redacted() {
+    let iv = Iv::from(hex!("5b78923dee08579033e523d9"));
+    assert_eq!(format!("{:?}", iv), "Iv { .. }");
+}
+
+#[test]
+fn rfc8448_open() {
+    let mut keys = client_application_keys();
+    let mut record = CLIENT_RECORD;
+    let (content_type, content) = keys.open_in_place(&mut record).unwrap();
+    assert_eq!(content_type, ContentType::APPLICATION_DATA);
+    assert_eq!(content, APPLICATION_DATA);
+    assert_eq!(keys.sequence_number(), 1);
+}
+
+#[test]
+fn rfc8448_open_with_derived_keys() {
+    let mut keys = Secret::new(HKDF_SHA256, &SERVER_APPLICATION_SECRET)
+        .unwrap()
+        .record_keys::<Aes128Gcm>()
+        .unwrap();
+
+    // Skip the NewSessionTicket record
+    let mut record = SERVER_RECORD;
+    assert!(keys.open_in_place(&mut record.clone()).is_err());
+
+    let (content_type, content) = keys.open_in_place(&mut record).unwrap();
+    assert_eq!(content_type, ContentType::APPLICATION_DATA);
+    assert_eq!(content, APPLICATION_DATA);
+}
+
+#[test]
+fn rfc8448_seal() {
+    let mut keys = client_application_keys();
+    let record = keys
+        .seal(ContentType::APPLICATION_DATA, &APPLICATION_DATA, 0)
+        .unwrap();
+    assert_eq!(record, CLIENT_RECORD);
+}
+
+#[test]
+fn sha384_key_schedule() {
+    let schedule = KeySchedule::new(HKDF_SHA384, None);
+    assert_eq!(
+        schedule.secret().as_ref(),
+        hex!(
+            "7ee8206f5570023e6dc7519eb1073bc4e791ad37b5c382aa10ba18e2357e7169"
+            "71f9362f2c2fe2a76bfd78dfec4ea9b5"
+        )
+    );
+
+    let schedule = schedule.into_handshake(&hex!(
+        "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"
+    ));
+    let secret = schedule
+        .derive_secret(
+            b"s hs traffic",
+            &hex!(
+                "520cde83e730ef1c04fe443dc399ded36c0f275993c190b6fc9fc11db7dae644"
+                "ae6073f4371fa061a7482e6cc2e90ed7"
+            ),
+        )
+        .unwrap();
+    assert_eq!(
+        secret.as_ref(),
+        hex!(
+            "1044989f862b4454d53130e6e6dabed1a7deb5162caf2c7043565d88720fc5c6"
+            "b015d5b9ed339799e500c1f866da1b4f"
+        )
+    );
+
+    let mut keys = secret.record_keys::<Aes256Gcm>().unwrap();
+    assert!(secret.record_keys::<Aes128Gcm>().is_err());
+
+    // Second record, padded with 3 zero bytes
+    let mut record = hex!("1703030019837f2f07d1a28eed7c5a50bf80db2a92ad061c20d719e06a7d");
+    assert!(keys.open_in_place(&mut record.clone()).is_err());
+    let (content_type, content) = keys.open_in_place(&mut record).unwrap();
+    assert_eq!(content_type, ContentType::HANDSHAKE);
+    assert_eq!(content, b"hello");
+}
+
+#[test]
+fn chacha20poly1305_with_psk() {
+    let schedule = KeySchedule::new(HKDF_SHA256, Some(&[0x42; 32]));
+    assert_eq!(
+        schedule.secret().as_ref(),
+        hex!("b8f58bb59550f5bd61aa8b38d441bb5b00c05ad8c0fafea9fb82d61e55911e8b")
+    );
+
+    let secret = schedule.secret().update();
+    assert_eq!(
+        secret.as_ref(),
+        hex!("bc13b5b6177b8328c2edcfa4d9f45554cb5a05ac96dbfbd55fc15bc1acc6e544")
+    );
+
+    let expected = hex!("1703030015f9c576707ccc1f7981d59cef7036844adc1de3ee93");
+    let mut keys = secret.record_keys::<ChaCha20Poly1305>().unwrap();
+    let record = keys
+        .seal(ContentType::APPLICATION_DATA, b"data", 0)
+        .unwrap();
+    assert_eq!(record, expected);
+}
+
+#[test]
+fn padding_round_trip() {
+    let mut sealer = client_application_keys();
+    let mut opener = client_application_keys();
+
+    for padding in [0, 1, 100] {
+        let mut record = sealer
+            .seal(ContentType::ALERT, b"\x01\x00", padding)
+            .unwrap();
+        assert_eq!(record.len(), 5 + 2 + 1 + padding + 16);
+
+        let (content_type, content) = opener.open_in_place(&mut record).unwrap();
+        assert_eq!(content_type, ContentType::ALERT);
+        assert_eq!(content, b"\x01\x00");
+    }
+}
+
+#[test]
+fn record_size_limits() {
+    let mut keys = client_application_keys();
+    assert!(keys
+        .seal(ContentType::APPLICATION_DATA, &[0; 1 << 14], 255 - 16)
+        .is_ok());
+    assert!(keys
+        .seal(ContentType::APPLICATION_DATA, &[0; (1 << 14) + 1], 0)
+        .is_err());
+    assert!(keys
+        .seal(ContentType::APPLICATION_DATA, &[0; 1 << 14], 256)
+        .is_err());
+}
+
+#[test]
+fn all_padding() {
+    let mut sealer = client_application_keys();
+    let mut opener = client_application_keys();
+
+    let mut record = sealer.seal(ContentType(0), b"", 4).unwrap();
+    assert!(opener.open_in_place(&mut record).is_err());
+}
+
+#[test]
+fn modified_record() {
+    for i in 0..CLIENT_RECORD.len() {
+        let mut record = CLIENT_RECORD;
+        record[i] ^= 1;
+        assert!(client_application_keys()
+            .open_in_place(&mut record)
+            .is_err());
+    }
+}
+
+#[test]
+fn truncated_record() {
+    for len in 0..CLIENT_RECORD.len() {
+        let mut record = CLIENT_RECORD;
+        assert!(client_application_keys()
+            .open_in_place(&mut record[..len])
+            .is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Vectored AEAD tests
+//!
+//! Checks that messages split across multiple buffers encrypt to the same
+//! ciphertext and tag as the equivalent contiguous message.
+
+use ring_compat::aead::{
+    vectored::AeadVectored, AeadAlgorithm, AeadInPlace, Aes128Gcm, Aes256Gcm, AnyAead,
+    ChaCha20Poly1305, KeyInit, XChaCha20Poly1305,
+};
+
+const AAD: &[u8] = b"header bytes which are authenticated";
+const PLAINTEXT: &[u8] = b"a packet payload split into several fragments";
+
+/// Ways of splitting a buffer, as lists of fragment lengths (the remainder
+/// forms the last fragment)
+const SPLITS: &[&[usize]] = &[&[], &[0], &[0, 0], &[1], &[7, 0, 13], &[3, 3, 3, 3, 3, 3]];
+
+/// Split `data` at the given lengths
+fn split<'a>(mut data: &'a mut [u8], lengths: &[usize]) -> Vec<&'a mut [u8]> {
+    let mut fragments = Vec::new();
+
+    for &len in lengths {
+        let (fragment, rest) = data.split_at_mut(len);
+        fragments.push(fragment);
+        data = rest;
+    }
+
+    fragments.push(data);
+    fragments
+}
+
+fn split_aad(lengths: &[usize]) -> Vec<&'static [u8]> {
+    let mut aad = Vec::new();
+    let mut rest = AAD;
+
+    for &len in lengths {
+        aad.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    aad.push(rest);
+    aad
+}
+
+fn check<A: AeadInPlace + AeadVectored>(cipher: &A) {
+    let nonce = Default::default();
+    let mut expected = PLAINTEXT.to_vec();
+    let expected_tag = cipher
+        .encrypt_in_place_detached(&nonce, AAD, &mut expected)
+        .unwrap();
+
+    for buffer_split in SPLITS {
+        for aad_split in SPLITS {
+            let aad = split_aad(aad_split);
+            let mut buffer = PLAINTEXT.to_vec();
+            let mut buffers = split(&mut buffer, buffer_split);
+
+            let tag = cipher
+                .encrypt_vectored_detached(&nonce, &aad, &mut buffers)
+                .unwrap();
+            assert_eq!(tag, expected_tag);
+            assert_eq!(buffer, expected);
+
+            let mut buffers = split(&mut buffer, buffer_split);
+            cipher
+                .decrypt_vectored_detached(&nonce, &aad, &mut buffers, &tag)
+                .unwrap();
+            assert_eq!(buffer, PLAINTEXT);
+        }
+    }
+}
+
+#[test]
+fn aes128gcm() {
+    check(&Aes128Gcm::new(&[0x11; 16].into()));
+}
+
+#[test]
+fn aes256gcm() {
+    check(&Aes256Gcm::new(&[0x22; 32].into()));
+}
+
+#[test]
+fn chacha20poly1305() {
+    check(&ChaCha20Poly1305::new(&[0x33; 32].into()));
+}
+
+#[test]
+fn xchacha20poly1305() {
+    check(&XChaCha20Poly1305::new(&[0x44; 32].into()));
+}
+
+#[test]
+fn any_aead() {
+    check(&AnyAead::new(AeadAlgorithm::Aes256Gcm, &[0x55; 32]).unwrap());
+}
+
+#[test]
+fn empty_message() {
+    let cipher = ChaCha20Poly1305::new(&[0x33; 32].into());
+    let nonce = Default::default();
+    let expected = cipher
+        .encrypt_in_place_detached(&nonce, AAD, &mut [])
+        .unwrap();
+
+    let tag = cipher
+        .encrypt_vectored_detached(&nonce, &[AAD], &mut [&mut [], &mut []])
+        .unwrap();
+    assert_eq!(tag, expected);
+
+    cipher
+        .decrypt_vectored_detached(&nonce, &[AAD], &mut [], &tag)
+        .unwrap();
+}
+
+#[test]
+fn modified() {
+    let cipher = Aes128Gcm::new(&[0x11; 16].into());
+    let nonce = Default::default();
+    let aad = split_aad(&[4, 4]);
+
+    let mut buffer = PLAINTEXT.to_vec();
+    let tag = cipher
+        .encrypt_vectored_detached(&nonce, &aad, &mut split(&mut buffer, &[5, 10]))
+        .unwrap();
+
+    for i in 0..buffer.len() {
+        let mut modified = buffer.clone();
+        modified[i] ^= 1;
+        let mut buffers = split(&mut modified, &[5, 10]);
+        assert!(cipher
+            .decrypt_vectored_detached(&nonce, &aad, &mut buffers, &tag)
+            .is_err());
+    }
+
+    let mut buffers = split(&mut buffer, &[5, 10]);
+    assert!(cipher
+        .decrypt_vectored_detached(&nonce, &[AAD, b"!"], &mut buffers, &tag)
+        .is_err());
+}
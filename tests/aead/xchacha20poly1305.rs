@@ -0,0 +1,131 @@
+//! XChaCha20Poly1305 test vectors.
+//!
+//! From draft-irtf-cfrg-xchacha-03 Appendix A.3.1:
+//! <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha-03#appendix-A.3.1>
+
+use hex_literal::hex;
+use ring_compat::{
+    aead::{Aead, AeadInPlace, KeyInit, Payload, XChaCha20Poly1305},
+    generic_array::GenericArray,
+};
+
+const KEY: &[u8; 32] = &hex!("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+
+const AAD: &[u8; 12] = &hex!("50515253c0c1c2c3c4c5c6c7");
+
+const PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: \
+    If I could offer you only one tip for the future, sunscreen would be it.";
+
+const NONCE: &[u8; 24] = &hex!("404142434445464748494a4b4c4d4e4f5051525354555657");
+
+const CIPHERTEXT: &[u8] = &hex!(
+    "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb"
+    "731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452"
+    "2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9"
+    "21f9664c97637da9768812f615c68b13b52e"
+);
+
+const TAG: &[u8; 16] = &hex!("c0875924c1c7987947deafd8780acf49");
+
+#[test]
+fn encrypt() {
+    let key = GenericArray::from_slice(KEY);
+    let nonce = GenericArray::from_slice(NONCE);
+    let payload = Payload {
+        msg: PLAINTEXT,
+        aad: AAD,
+    };
+
+    let cipher = XChaCha20Poly1305::new(key);
+    let ciphertext = cipher.encrypt(nonce, payload).unwrap();
+
+    let tag_begins = ciphertext.len() - 16;
+    assert_eq!(CIPHERTEXT, &ciphertext[..tag_begins]);
+    assert_eq!(TAG, &ciphertext[tag_begins..]);
+}
+
+#[test]
+fn decrypt() {
+    let key = GenericArray::from_slice(KEY);
+    let nonce = GenericArray::from_slice(NONCE);
+
+    let mut ciphertext = Vec::from(CIPHERTEXT);
+    ciphertext.extend_from_slice(TAG);
+    let payload = Payload {
+        msg: &ciphertext,
+        aad: AAD,
+    };
+
+    let cipher = XChaCha20Poly1305::new(key);
+    let plaintext = cipher.decrypt(nonce, payload).unwrap();
+
+    assert_eq!(PLAINTEXT, plaintext.as_slice());
+}
+
+#[test]
+fn decrypt_in_place_detached() {
+    let key = GenericArray::from_slice(KEY);
+    let nonce = GenericArray::from_slice(NONCE);
+    let tag = GenericArray::clone_from_slice(TAG);
+    let mut buffer = CIPHERTEXT.to_vec();
+
+    XChaCha20Poly1305::new(key)
+        .decrypt_in_place_detached(nonce, AAD, &mut buffer, &tag)
+        .unwrap();
+
+    assert_eq!(PLAINTEXT, buffer.as_slice());
+}
+
+#[test]
+fn decrypt_modified() {
+    let key = GenericArray::from_slice(KEY);
+    let nonce = GenericArray::from_slice(NONCE);
+
+    let mut ciphertext = Vec::from(CIPHERTEXT);
+    ciphertext.extend_from_slice(TAG);
+
+    // Tweak the first byte
+    ciphertext[0] ^= 0xaa;
+
+    let payload = Payload {
+        msg: &ciphertext,
+        aad: AAD,
+    };
+
+    let cipher = XChaCha20Poly1305::new(key);
+    assert!(cipher.decrypt(nonce, payload).is_err());
+}
+
+#[test]
+fn matches_rustcrypto() {
+    let key = GenericArray::from_slice(KEY);
+    let ring_cipher = XChaCha20Poly1305::new(key);
+    let rustcrypto_cipher = ::chacha20poly1305::XChaCha20Poly1305::new(key);
+
+    for i in 0..=255u8 {
+        let nonce = GenericArray::from([i; 24]);
+        let msg = vec![i; usize::from(i)];
+        let aad = [i];
+
+        assert_eq!(
+            ring_cipher
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: &msg,
+                        aad: &aad
+                    }
+                )
+                .unwrap(),
+            rustcrypto_cipher
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: &msg,
+                        aad: &aad
+                    }
+                )
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Static private keys held outside of this crate
+
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring_compat::agreement::{Algorithm, PrivateKey, Unspecified, ECDH_P256, ECDH_P384, X25519};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// X25519 private key
+pub struct X25519PrivateKey {
+    secret: StaticSecret,
+    public_key: [u8; 32],
+}
+
+impl X25519PrivateKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        let secret = StaticSecret::from(bytes);
+        let public_key = PublicKey::from(&secret).to_bytes();
+        Self { secret, public_key }
+    }
+
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new().fill(&mut bytes).unwrap();
+        Self::new(bytes)
+    }
+}
+
+impl PrivateKey for X25519PrivateKey {
+    fn algorithm(&self) -> &'static Algorithm {
+        &X25519
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn diffie_hellman(
+        &self,
+        peer_public_key: &[u8],
+        shared_secret: &mut [u8],
+    ) -> Result<(), Unspecified> {
+        let peer_public_key: [u8; 32] = peer_public_key.try_into().map_err(|_| Unspecified)?;
+        let shared = self
+            .secret
+            .diffie_hellman(&PublicKey::from(peer_public_key));
+
+        if !shared.was_contributory() || shared_secret.len() != 32 {
+            return Err(Unspecified);
+        }
+
+        shared_secret.copy_from_slice(shared.as_bytes());
+        Ok(())
+    }
+}
+
+macro_rules! ecdh_private_key {
+    ($name:ident, $curve:ident, $algorithm:expr) => {
+        #[doc = concat!(stringify!($curve), " private key")]
+        pub struct $name {
+            secret: $curve::SecretKey,
+            public_key: Vec<u8>,
+        }
+
+        impl $name {
+            pub fn new(bytes: &[u8]) -> Self {
+                let secret = $curve::SecretKey::from_slice(bytes).unwrap();
+                let public_key = secret
+                    .public_key()
+                    .to_encoded_point(false)
+                    .as_bytes()
+                    .to_vec();
+                Self { secret, public_key }
+            }
+        }
+
+        impl PrivateKey for $name {
+            fn algorithm(&self) -> &'static Algorithm {
+                &$algorithm
+            }
+
+            fn public_key(&self) -> &[u8] {
+                &self.public_key
+            }
+
+            fn diffie_hellman(
+                &self,
+                peer_public_key: &[u8],
+                shared_secret: &mut [u8],
+            ) -> Result<(), Unspecified> {
+                let peer_public_key =
+                    $curve::PublicKey::from_sec1_bytes(peer_public_key).map_err(|_| Unspecified)?;
+                let shared = $curve::ecdh::diffie_hellman(
+                    self.secret.to_nonzero_scalar(),
+                    peer_public_key.as_affine(),
+                );
+
+                if shared_secret.len() != shared.raw_secret_bytes().len() {
+                    return Err(Unspecified);
+                }
+
+                shared_secret.copy_from_slice(shared.raw_secret_bytes());
+                Ok(())
+            }
+        }
+    };
+}
+
+ecdh_private_key!(P256PrivateKey, p256, ECDH_P256);
+ecdh_private_key!(P384PrivateKey, p384, ECDH_P384);
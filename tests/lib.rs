@@ -3,6 +3,9 @@
 #[cfg(all(feature = "aead", feature = "alloc"))]
 mod aead;
 
+#[cfg(all(feature = "aead", feature = "alloc"))]
+mod agreement;
+
 #[cfg(feature = "digest")]
 mod digest;
 
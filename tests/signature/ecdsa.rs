@@ -26,6 +26,20 @@ macro_rules! ecdsa_tests {
             assert!(verifying_key.verify(msg, &sig).is_ok());
         }
 
+        #[test]
+        fn clone_and_debug() {
+            let signing_key = example_signing_key().clone();
+            let msg = $test_vectors[0].msg;
+            let sig = signing_key.sign(msg);
+
+            assert_eq!(
+                signing_key.verifying_key(),
+                example_signing_key().verifying_key()
+            );
+            assert!(signing_key.verifying_key().verify(msg, &sig).is_ok());
+            assert_eq!(format!("{:?}", signing_key), "SigningKey { .. }");
+        }
+
         #[test]
         fn verify_nist_test_vectors() {
             for vector in $test_vectors {
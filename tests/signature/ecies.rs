@@ -0,0 +1,188 @@
+//! ECIES tests
+//!
+//! The test vectors were generated with Python's `cryptography` package,
+//! using its `X963KDF` and `AESGCM` implementations.
+
+use crate::agreement::{P256PrivateKey, P384PrivateKey};
+use hex_literal::hex;
+use ring_compat::{
+    aead::{Aes128Gcm, Aes256Gcm},
+    agreement::PrivateKey,
+    digest::{Sha256, Sha384},
+    signature::{
+        ecdsa::{
+            p256::{self, NistP256},
+            p384::{self, NistP384},
+        },
+        ecies::{self, EciesError},
+    },
+};
+
+const PLAINTEXT: &[u8] = b"attack at dawn";
+const ASSOCIATED_DATA: &[u8] = b"header";
+
+#[test]
+fn p256_sha256_aes128gcm_vector() {
+    let private_key = P256PrivateKey::new(&hex!(
+        "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!(
+            "04515c3d6eb9e396b904d3feca7f54fdcd0cc1e997bf375dca515ad0a6c3b4035f"
+            "4536be3a50f318fbf9a5475902a221502bef0d57e08c53b2cc0a56f17d9f9354"
+        )
+    );
+
+    let ciphertext = hex!(
+        "0242f51861b18970b2990f4c28b44ce7688f8c7d8d99a95311f7f3a26f4e1267c7"
+        "649c99fb7e2e73170968c1a9234433674764d84361343ae57d4b338a73b0"
+    );
+
+    assert_eq!(
+        ecies::decrypt::<NistP256, Sha256, Aes128Gcm>(&private_key, &ciphertext, ASSOCIATED_DATA)
+            .unwrap(),
+        PLAINTEXT
+    );
+}
+
+#[test]
+fn p384_sha384_aes256gcm_vector() {
+    let private_key = P384PrivateKey::new(&hex!(
+        "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"
+        "2122232425262728292a2b2c2d2e2f30"
+    ));
+
+    assert_eq!(
+        private_key.public_key(),
+        hex!(
+            "04c76f2283dda95cd49b0ed9e733d2904474e37216f124e13d2c9ab4cf01021c49"
+            "ad9cabb3d0b97499aef2f0ab313fa02826bc1f83451b5c8962a75caff73588d440"
+            "0a6296436154fb343c393e91048a6c7bcbadc83cd8a5f26feae883156f92a1"
+        )
+    );
+
+    let ciphertext = hex!(
+        "035d55e75249195909f76687b1ec4489a650ced5fa0c1a6b69abbe96dfacfcbd92"
+        "3e14bf39898bc04cf4596238ad55431a35f75b8f0dbdcda5789123d32d72704c3d"
+        "c2c805378bed562b9c73255b66"
+    );
+
+    assert_eq!(
+        ecies::decrypt::<NistP384, Sha384, Aes256Gcm>(&private_key, &ciphertext, ASSOCIATED_DATA)
+            .unwrap(),
+        PLAINTEXT
+    );
+}
+
+#[test]
+fn p256_round_trip() {
+    let private_key = P256PrivateKey::new(&[0x42; 32]);
+    let ciphertext = ecies::encrypt::<_, Sha256, Aes256Gcm>(
+        &p256::VerifyingKey::new(private_key.public_key()).unwrap(),
+        PLAINTEXT,
+        ASSOCIATED_DATA,
+    )
+    .unwrap();
+
+    assert_eq!(ciphertext.len(), 33 + PLAINTEXT.len() + 16);
+    assert_eq!(
+        ecies::decrypt::<NistP256, Sha256, Aes256Gcm>(&private_key, &ciphertext, ASSOCIATED_DATA)
+            .unwrap(),
+        PLAINTEXT
+    );
+}
+
+#[test]
+fn p384_round_trip() {
+    let private_key = P384PrivateKey::new(&[0x42; 48]);
+    let ciphertext = ecies::encrypt::<_, Sha384, Aes128Gcm>(
+        &p384::VerifyingKey::new(private_key.public_key()).unwrap(),
+        PLAINTEXT,
+        ASSOCIATED_DATA,
+    )
+    .unwrap();
+
+    assert_eq!(ciphertext.len(), 49 + PLAINTEXT.len() + 16);
+    assert_eq!(
+        ecies::decrypt::<NistP384, Sha384, Aes128Gcm>(&private_key, &ciphertext, ASSOCIATED_DATA)
+            .unwrap(),
+        PLAINTEXT
+    );
+}
+
+#[test]
+fn decryption_failures() {
+    let private_key = P256PrivateKey::new(&[0x42; 32]);
+    let mut ciphertext = ecies::encrypt::<_, Sha256, Aes128Gcm>(
+        &p256::VerifyingKey::new(private_key.public_key()).unwrap(),
+        PLAINTEXT,
+        ASSOCIATED_DATA,
+    )
+    .unwrap();
+
+    let decrypt = |ciphertext: &[u8], associated_data: &[u8]| {
+        ecies::decrypt::<NistP256, Sha256, Aes128Gcm>(&private_key, ciphertext, associated_data)
+    };
+
+    assert_eq!(decrypt(&ciphertext, b"other"), Err(EciesError::Decryption));
+    assert_eq!(
+        decrypt(&ciphertext[..33 + 15], ASSOCIATED_DATA),
+        Err(EciesError::Malformed)
+    );
+
+    // A different KDF hash derives a different key
+    assert_eq!(
+        ecies::decrypt::<NistP256, Sha384, Aes128Gcm>(&private_key, &ciphertext, ASSOCIATED_DATA),
+        Err(EciesError::Decryption)
+    );
+
+    let last = ciphertext.len() - 1;
+    ciphertext[last] ^= 1;
+    assert_eq!(
+        decrypt(&ciphertext, ASSOCIATED_DATA),
+        Err(EciesError::Decryption)
+    );
+
+    // The private key must be on the curve
+    assert_eq!(
+        ecies::decrypt::<NistP256, Sha256, Aes128Gcm>(
+            &P384PrivateKey::new(&[0x42; 48]),
+            &ciphertext,
+            ASSOCIATED_DATA
+        ),
+        Err(EciesError::InvalidKey)
+    );
+
+    // Ephemeral public keys must be compressed
+    ciphertext[0] = 0x04;
+    assert_eq!(
+        decrypt(&ciphertext, ASSOCIATED_DATA),
+        Err(EciesError::Malformed)
+    );
+}
+
+#[test]
+fn invalid_recipient() {
+    // Compressed public keys aren't supported by *ring*
+    let compressed = p256::VerifyingKey::new(&hex!(
+        "03515c3d6eb9e396b904d3feca7f54fdcd0cc1e997bf375dca515ad0a6c3b4035f"
+    ))
+    .unwrap();
+
+    assert_eq!(
+        ecies::encrypt::<_, Sha256, Aes128Gcm>(&compressed, PLAINTEXT, ASSOCIATED_DATA),
+        Err(EciesError::InvalidKey)
+    );
+
+    // Point which isn't on the curve
+    let mut point = [0x11; 97];
+    point[0] = 0x04;
+    let invalid = p384::VerifyingKey::new(&point).unwrap();
+
+    assert_eq!(
+        ecies::encrypt::<_, Sha384, Aes256Gcm>(&invalid, PLAINTEXT, ASSOCIATED_DATA),
+        Err(EciesError::InvalidKey)
+    );
+}
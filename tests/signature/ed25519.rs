@@ -39,6 +39,17 @@ fn rejects_tweaked_rfc8032_test_vectors() {
 }
 
 /// Ed25519 test vectors (from RFC 8032)
+#[test]
+fn debug_redacts_seed() {
+    let signing_key = SigningKey::from_slice(TEST_VECTORS[0].sk).unwrap();
+    let verifying_key = VerifyingKey::from_slice(TEST_VECTORS[0].pk).unwrap();
+
+    assert_eq!(
+        format!("{:?}", signing_key),
+        format!("SigningKey {{ verifying_key: {:?}, .. }}", verifying_key)
+    );
+}
+
 const TEST_VECTORS: &[TestVector] = &[
     TestVector {
         sk: &hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"),
@@ -0,0 +1,192 @@
+//! Password-protected key storage tests
+
+use core::num::NonZeroU32;
+use hex_literal::hex;
+use ring_compat::{
+    pkcs8::{DecodePrivateKey, EncodePrivateKey},
+    ring::{
+        rand::SystemRandom,
+        signature::{
+            EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING,
+        },
+    },
+    signature::{
+        ecdsa::{p256, p384},
+        encrypted_key::{self, EncryptedKeyError},
+        Signer, Verifier,
+    },
+};
+
+const PASSWORD: &[u8] = b"correct horse";
+
+/// Ed25519 key from RFC 8032 test 1 as a PKCS#8 v1 document, encrypted with
+/// Python's `hashlib.pbkdf2_hmac` and `cryptography` using 1000 iterations
+const ED25519_CONTAINER: &[u8] = &hex!(
+    "5243454b01000003e810000102030405060708090a0b0c0d0e0f101112131415"
+    "161718191a1bce31d16e9013d7dd90c84ed18e8a5dc0e57309222d7bad2deb87"
+    "5ff6b60dc38e0944dc3321bea12d91957eb3ce1964ce50d0518d49f7619a82c5"
+    "155e581ac346"
+);
+
+/// Seed of the key in [`ED25519_CONTAINER`]
+const ED25519_SEED: [u8; 32] =
+    hex!("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60");
+
+fn iterations() -> NonZeroU32 {
+    NonZeroU32::new(1000).unwrap()
+}
+
+fn p256_key() -> p256::SigningKey {
+    let pkcs8 =
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+            .unwrap();
+    p256::SigningKey::from_pkcs8_der(pkcs8.as_ref()).unwrap()
+}
+
+#[test]
+fn ecdsa_pkcs8_round_trip() {
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, &rng).unwrap();
+    let signing_key = p384::SigningKey::from_pkcs8_der(pkcs8.as_ref()).unwrap();
+
+    // Matches the encoding *ring* generates
+    let document = signing_key.to_pkcs8_der().unwrap();
+    assert_eq!(document.as_bytes(), pkcs8.as_ref());
+}
+
+#[test]
+fn p256_round_trip() {
+    let signing_key = p256_key();
+    let container =
+        encrypted_key::encrypt_with_iterations(&signing_key, PASSWORD, iterations()).unwrap();
+
+    let decrypted: p256::SigningKey = encrypted_key::decrypt(&container, PASSWORD).unwrap();
+    assert_eq!(decrypted.verifying_key(), signing_key.verifying_key());
+
+    let signature: p256::Signature = decrypted.sign(b"message");
+    assert!(signing_key
+        .verifying_key()
+        .verify(b"message", &signature)
+        .is_ok());
+}
+
+#[test]
+fn p384_round_trip() {
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P384_SHA384_FIXED_SIGNING, &rng).unwrap();
+    let signing_key = p384::SigningKey::from_pkcs8_der(pkcs8.as_ref()).unwrap();
+    let container =
+        encrypted_key::encrypt_with_iterations(&signing_key, PASSWORD, iterations()).unwrap();
+
+    let decrypted: p384::SigningKey = encrypted_key::decrypt(&container, PASSWORD).unwrap();
+    assert_eq!(decrypted.verifying_key(), signing_key.verifying_key());
+}
+
+#[cfg(feature = "pkcs8")]
+#[test]
+fn ed25519_round_trip() {
+    use ring_compat::signature::ed25519;
+
+    let signing_key = ed25519::SigningKey::from_bytes(&ED25519_SEED);
+    let container =
+        encrypted_key::encrypt_with_iterations(&signing_key, PASSWORD, iterations()).unwrap();
+
+    let decrypted: ed25519::SigningKey = encrypted_key::decrypt(&container, PASSWORD).unwrap();
+    assert_eq!(decrypted.to_bytes(), ED25519_SEED);
+}
+
+#[cfg(feature = "pkcs8")]
+#[test]
+fn ed25519_known_container() {
+    use ring_compat::signature::ed25519;
+
+    let decrypted: ed25519::SigningKey =
+        encrypted_key::decrypt(ED25519_CONTAINER, PASSWORD).unwrap();
+    assert_eq!(decrypted.to_bytes(), ED25519_SEED);
+}
+
+#[test]
+fn container_records_parameters() {
+    let container =
+        encrypted_key::encrypt_with_iterations(&p256_key(), PASSWORD, iterations()).unwrap();
+
+    assert_eq!(&container[..5], b"RCEK\x01");
+    assert_eq!(container[5..9], 1000u32.to_be_bytes());
+    assert_eq!(container[9], 16);
+}
+
+#[test]
+fn known_container_pkcs8() {
+    let document = encrypted_key::decrypt_pkcs8(ED25519_CONTAINER, PASSWORD).unwrap();
+    assert_eq!(
+        document.as_bytes(),
+        [&hex!("302e020100300506032b657004220420")[..], &ED25519_SEED].concat()
+    );
+}
+
+#[test]
+fn wrong_password() {
+    assert_eq!(
+        encrypted_key::decrypt_pkcs8(ED25519_CONTAINER, b"wrong").unwrap_err(),
+        EncryptedKeyError::Decryption
+    );
+}
+
+#[test]
+fn wrong_key_type() {
+    assert!(matches!(
+        encrypted_key::decrypt::<p256::SigningKey>(ED25519_CONTAINER, PASSWORD),
+        Err(EncryptedKeyError::Pkcs8(_))
+    ));
+}
+
+#[test]
+fn modified_container() {
+    // Magic, version, iterations, salt length, salt, nonce, ciphertext, tag
+    for i in [0, 4, 8, 9, 10, 26, 40, ED25519_CONTAINER.len() - 1] {
+        let mut container = ED25519_CONTAINER.to_vec();
+        container[i] ^= 1;
+        assert!(encrypted_key::decrypt_pkcs8(&container, PASSWORD).is_err());
+    }
+}
+
+#[test]
+fn truncated_container() {
+    for len in [0, 9, 10, 26, 37, 38, 60, ED25519_CONTAINER.len() - 1] {
+        assert!(encrypted_key::decrypt_pkcs8(&ED25519_CONTAINER[..len], PASSWORD).is_err());
+    }
+
+    assert_eq!(
+        encrypted_key::decrypt_pkcs8(&ED25519_CONTAINER[..20], PASSWORD).unwrap_err(),
+        EncryptedKeyError::Malformed
+    );
+}
+
+#[test]
+fn too_many_iterations() {
+    assert!(encrypted_key::decrypt_pkcs8_with_max_iterations(
+        ED25519_CONTAINER,
+        PASSWORD,
+        iterations()
+    )
+    .is_ok());
+
+    let max_iterations = NonZeroU32::new(999).unwrap();
+    assert_eq!(
+        encrypted_key::decrypt_pkcs8_with_max_iterations(
+            ED25519_CONTAINER,
+            PASSWORD,
+            max_iterations
+        )
+        .unwrap_err(),
+        EncryptedKeyError::TooManyIterations
+    );
+
+    // The iteration count is checked before deriving the key
+    let mut container = ED25519_CONTAINER.to_vec();
+    container[5..9].copy_from_slice(&(encrypted_key::DEFAULT_MAX_ITERATIONS + 1).to_be_bytes());
+    assert_eq!(
+        encrypted_key::decrypt_pkcs8(&container, PASSWORD).unwrap_err(),
+        EncryptedKeyError::TooManyIterations
+    );
+}
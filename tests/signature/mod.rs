@@ -3,6 +3,12 @@
 mod ecdsa;
 mod ed25519;
 
+#[cfg(all(feature = "aead", feature = "alloc"))]
+mod encrypted_key;
+
+#[cfg(all(feature = "aead", feature = "alloc", feature = "digest"))]
+mod ecies;
+
 /// Signature test vector
 #[derive(Copy, Clone, Debug)]
 struct TestVector {